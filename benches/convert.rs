@@ -0,0 +1,81 @@
+// Conversion throughput across layout pairs and input sizes, on one thread,
+// split between threads and, for pairs that allow it, in place.
+//
+//     cargo bench --bench convert -- --save-baseline main
+//     cargo bench --bench convert -- --baseline main
+//
+// The second run reports changes against the first. Criterion keeps the
+// results as JSON under target/criterion/<group>/<bench>/<size>/new/.
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use keymorph::converter::Converter;
+use keymorph::layouts::{self, LayoutCode, Parallelism};
+use std::hint::black_box;
+
+const SAMPLE: &str = "The quick brown fox jumps over the lazy dog, 1234567890! ";
+
+const SIZES: [usize; 3] = [100, 10_000, 1_000_000];
+
+const PAIRS: [(LayoutCode, LayoutCode); 4] = [
+    // ASCII to ASCII, converts in place
+    (LayoutCode::Qwerty, LayoutCode::Dvorak),
+    // Latin to Cyrillic
+    (LayoutCode::Qwerty, LayoutCode::Russian),
+    (LayoutCode::Russian, LayoutCode::Qwerty),
+    // Composes dead keys
+    (LayoutCode::Qwerty, LayoutCode::Czech),
+];
+
+// About `size` bytes of text typed on `layout`
+fn input(layout: LayoutCode, size: usize) -> String {
+    let typed = layouts::convert_text(SAMPLE, LayoutCode::Qwerty, layout);
+    typed.chars().cycle().take(size).collect()
+}
+
+fn convert(c: &mut Criterion) {
+    for (from, to) in PAIRS {
+        let mut group = c.benchmark_group(format!("{:?}-{:?}", from, to));
+        let serial = Converter::builder()
+            .from(from)
+            .to(to)
+            .parallelism(Parallelism {
+                max_threads: 1,
+                ..Parallelism::default()
+            })
+            .build()
+            .unwrap();
+        let parallel = Converter::builder()
+            .from(from)
+            .to(to)
+            .parallelism(Parallelism {
+                threshold: 0,
+                chunk_size: None,
+                max_threads: 4,
+            })
+            .build()
+            .unwrap();
+
+        for size in SIZES {
+            let text = input(from, size);
+            group.throughput(Throughput::Bytes(text.len() as u64));
+            group.bench_with_input(BenchmarkId::new("serial", size), &text, |b, text| {
+                b.iter(|| serial.convert(black_box(text)))
+            });
+            group.bench_with_input(BenchmarkId::new("parallel", size), &text, |b, text| {
+                b.iter(|| parallel.convert(black_box(text)))
+            });
+            if layouts::same_width(from, to) {
+                group.bench_with_input(BenchmarkId::new("in_place", size), &text, |b, text| {
+                    b.iter_batched_ref(
+                        || text.clone(),
+                        |text| layouts::convert_in_place(text, from, to),
+                        BatchSize::LargeInput,
+                    )
+                });
+            }
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, convert);
+criterion_main!(benches);
@@ -0,0 +1,102 @@
+// Builds the character trigram models used for detection from the corpora
+// and dictionaries in src/, so that they are compiled in as sorted tables of
+// counts rather than as text.
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::path::Path;
+
+#[path = "src/trigram/smoothing.rs"]
+mod smoothing;
+
+const MODELS: [(&str, &str); 2] = [("ENGLISH", "en"), ("RUSSIAN", "ru")];
+
+// Words of `text` in lower case, each padded with a space on both sides
+fn words(text: &str) -> impl Iterator<Item = Vec<char>> + '_ {
+    text.split(|c: char| !c.is_alphabetic())
+        .filter(|word| !word.is_empty())
+        .map(smoothing::pad)
+}
+
+struct Counts {
+    // Letter, pair and triple counts, by n - 1
+    ngrams: [BTreeMap<String, u32>; 3],
+    total: u32,
+}
+
+impl Counts {
+    fn count(&self, ngram: &[char]) -> u32 {
+        let key: String = ngram.iter().collect();
+        self.ngrams[ngram.len() - 1].get(&key).copied().unwrap_or(0)
+    }
+
+    // Log probability of the last letter of `trigram` after the first two
+    fn log_probability(&self, trigram: &[char]) -> f64 {
+        smoothing::probability(
+            |ngram| self.count(ngram),
+            self.total,
+            self.ngrams[0].len(),
+            trigram,
+        )
+        .ln()
+    }
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=src/trigram/smoothing.rs");
+    let mut generated = String::from("// Generated by build.rs\n");
+    for (name, language) in MODELS {
+        let mut text = String::new();
+        for source in [
+            format!("src/corpora/{}.txt", language),
+            format!("src/dictionaries/{}.txt", language),
+        ] {
+            println!("cargo:rerun-if-changed={}", source);
+            text += &std::fs::read_to_string(&source).expect("cannot read training text");
+            text.push('\n');
+        }
+
+        let mut counts = Counts {
+            ngrams: Default::default(),
+            total: 0,
+        };
+        for word in words(&text) {
+            for (n, ngrams) in counts.ngrams.iter_mut().enumerate() {
+                for ngram in word.windows(n + 1) {
+                    *ngrams.entry(ngram.iter().collect()).or_default() += 1;
+                }
+            }
+            counts.total += word.len() as u32;
+        }
+
+        // Average log probability of the letters of the training words,
+        // which is what a typical word scores
+        let (mut total, mut count) = (0.0, 0);
+        for word in words(&text) {
+            let trigrams = word.windows(3);
+            let len = trigrams.len();
+            total += trigrams
+                .map(|trigram| counts.log_probability(trigram))
+                .sum::<f64>()
+                / len as f64;
+            count += 1;
+        }
+
+        let [letters, pairs, triples] = counts.ngrams;
+        writeln!(
+            generated,
+            "pub static {}: TrigramModel = TrigramModel {{",
+            name
+        )
+        .unwrap();
+        writeln!(generated, "    total: {},", counts.total).unwrap();
+        writeln!(generated, "    typical: {:?},", total / count as f64).unwrap();
+        for (field, ngrams) in [("letters", letters), ("pairs", pairs), ("triples", triples)] {
+            let table: Vec<_> = ngrams.into_iter().collect();
+            writeln!(generated, "    {}: &{:?},", field, table).unwrap();
+        }
+        writeln!(generated, "}};").unwrap();
+    }
+
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    std::fs::write(Path::new(&out_dir).join("trigrams.rs"), generated).unwrap();
+}
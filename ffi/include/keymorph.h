@@ -0,0 +1,117 @@
+#ifndef KEYMORPH_H
+#define KEYMORPH_H
+
+/* Generated by cbindgen from ffi/src/lib.rs, do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Result of every keymorph_* call that can fail
+ */
+typedef enum KeymorphStatus {
+  KEYMORPH_STATUS_OK = 0,
+  /**
+   * A required pointer argument was NULL
+   */
+  KEYMORPH_STATUS_NULL_POINTER = 1,
+  /**
+   * Input text or a layout name was not valid UTF-8
+   */
+  KEYMORPH_STATUS_INVALID_UTF8 = 2,
+  KEYMORPH_STATUS_UNKNOWN_LAYOUT = 3,
+  /**
+   * The output buffer is too small; the required size was written to
+   * `out_len`
+   */
+  KEYMORPH_STATUS_BUFFER_TOO_SMALL = 4,
+  /**
+   * keymorph_detect found no layout mistake in the text
+   */
+  KEYMORPH_STATUS_NOT_DETECTED = 5,
+  /**
+   * An internal error occurred; this is always a bug
+   */
+  KEYMORPH_STATUS_PANIC = 6,
+} KeymorphStatus;
+
+/**
+ * Filled in by keymorph_detect
+ */
+typedef struct KeymorphDetection {
+  /**
+   * Layout ids, see keymorph_layout_name()
+   */
+  uint32_t from;
+  uint32_t to;
+  /**
+   * Share of words the conversion fixes, between 0 and 1
+   */
+  double confidence;
+} KeymorphDetection;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Number of built-in layouts; valid layout ids are 0 to count - 1
+ */
+size_t keymorph_layout_count(void);
+
+/**
+ * NUL-terminated name of a layout id, or NULL if the id is out of range. The
+ * string is static and must not be freed.
+ */
+const char *keymorph_layout_name(uint32_t id);
+
+/**
+ * Converts `text_len` bytes of UTF-8 `text` from layout `from` to layout `to`
+ * (NUL-terminated names such as "qwerty") into `out`.
+ *
+ * # Safety
+ * `text` must point to `text_len` readable bytes, `from` and `to` must be
+ * NUL-terminated strings, `out` must point to `out_capacity` writable bytes
+ * and `out_len` must be a valid pointer.
+ */
+enum KeymorphStatus keymorph_convert(const uint8_t *text,
+                                     size_t text_len,
+                                     const char *from,
+                                     const char *to,
+                                     uint8_t *out,
+                                     size_t out_capacity,
+                                     size_t *out_len);
+
+/**
+ * Converts only the words of `text` that look like they were typed with the
+ * wrong one of QWERTY and Russian active.
+ *
+ * # Safety
+ * Same requirements as `keymorph_convert`.
+ */
+enum KeymorphStatus keymorph_fix(const uint8_t *text,
+                                 size_t text_len,
+                                 uint8_t *out,
+                                 size_t out_capacity,
+                                 size_t *out_len);
+
+/**
+ * Detects which of QWERTY and Russian `text` was mistakenly typed in.
+ * Returns KEYMORPH_STATUS_NOT_DETECTED if it looks correctly typed.
+ *
+ * # Safety
+ * `text` must point to `text_len` readable bytes and `detection` must be a
+ * valid pointer.
+ */
+enum KeymorphStatus keymorph_detect(const uint8_t *text,
+                                    size_t text_len,
+                                    struct KeymorphDetection *detection);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* KEYMORPH_H */
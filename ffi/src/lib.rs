@@ -0,0 +1,201 @@
+//! C interface to keymorph. Text crosses the boundary as (pointer, length)
+//! pairs of UTF-8 bytes; results are written into caller-owned buffers, so
+//! nothing allocated here ever has to be freed by the caller.
+use keymorph::detect;
+use keymorph::layouts::{self, LayoutCode};
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Result of every keymorph_* call that can fail
+#[repr(C)]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum KeymorphStatus {
+    Ok = 0,
+    /// A required pointer argument was NULL
+    NullPointer = 1,
+    /// Input text or a layout name was not valid UTF-8
+    InvalidUtf8 = 2,
+    UnknownLayout = 3,
+    /// The output buffer is too small; the required size was written to
+    /// `out_len`
+    BufferTooSmall = 4,
+    /// keymorph_detect found no layout mistake in the text
+    NotDetected = 5,
+    /// An internal error occurred; this is always a bug
+    Panic = 6,
+}
+
+/// Filled in by keymorph_detect
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct KeymorphDetection {
+    /// Layout ids, see keymorph_layout_name()
+    pub from: u32,
+    pub to: u32,
+    /// Share of words the conversion fixes, between 0 and 1
+    pub confidence: f64,
+}
+
+// Names of the layouts as NUL-terminated strings, indexed by layout id
+fn layout_names() -> &'static [CString] {
+    static NAMES: OnceLock<Vec<CString>> = OnceLock::new();
+    NAMES.get_or_init(|| {
+        LayoutCode::ALL
+            .iter()
+            .map(|layout| CString::new(layout.as_str()).expect("layout names have no NUL bytes"))
+            .collect()
+    })
+}
+
+fn layout_id(layout: LayoutCode) -> u32 {
+    LayoutCode::ALL
+        .iter()
+        .position(|&code| code == layout)
+        .expect("every layout is in LayoutCode::ALL") as u32
+}
+
+unsafe fn text_arg<'a>(text: *const u8, len: usize) -> Result<&'a str, KeymorphStatus> {
+    if text.is_null() {
+        return if len == 0 {
+            Ok("")
+        } else {
+            Err(KeymorphStatus::NullPointer)
+        };
+    }
+    let bytes = std::slice::from_raw_parts(text, len);
+    std::str::from_utf8(bytes).map_err(|_| KeymorphStatus::InvalidUtf8)
+}
+
+unsafe fn layout_arg(name: *const c_char) -> Result<LayoutCode, KeymorphStatus> {
+    if name.is_null() {
+        return Err(KeymorphStatus::NullPointer);
+    }
+    let name = CStr::from_ptr(name)
+        .to_str()
+        .map_err(|_| KeymorphStatus::InvalidUtf8)?;
+    LayoutCode::from_str(name).map_err(|_| KeymorphStatus::UnknownLayout)
+}
+
+// Copies `result` into the caller's buffer. `out_len` always receives the
+// size of the result, so callers can retry with a large enough buffer.
+unsafe fn write_output(
+    result: &str,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> KeymorphStatus {
+    if out_len.is_null() {
+        return KeymorphStatus::NullPointer;
+    }
+    *out_len = result.len();
+    if result.len() > out_capacity {
+        return KeymorphStatus::BufferTooSmall;
+    }
+    if out.is_null() {
+        return if result.is_empty() {
+            KeymorphStatus::Ok
+        } else {
+            KeymorphStatus::NullPointer
+        };
+    }
+    std::ptr::copy_nonoverlapping(result.as_ptr(), out, result.len());
+    KeymorphStatus::Ok
+}
+
+fn guard(f: impl FnOnce() -> Result<KeymorphStatus, KeymorphStatus>) -> KeymorphStatus {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(status)) | Ok(Err(status)) => status,
+        Err(_) => KeymorphStatus::Panic,
+    }
+}
+
+/// Number of built-in layouts; valid layout ids are 0 to count - 1
+#[no_mangle]
+pub extern "C" fn keymorph_layout_count() -> usize {
+    LayoutCode::ALL.len()
+}
+
+/// NUL-terminated name of a layout id, or NULL if the id is out of range. The
+/// string is static and must not be freed.
+#[no_mangle]
+pub extern "C" fn keymorph_layout_name(id: u32) -> *const c_char {
+    layout_names()
+        .get(id as usize)
+        .map_or(std::ptr::null(), |name| name.as_ptr())
+}
+
+/// Converts `text_len` bytes of UTF-8 `text` from layout `from` to layout `to`
+/// (NUL-terminated names such as "qwerty") into `out`.
+///
+/// # Safety
+/// `text` must point to `text_len` readable bytes, `from` and `to` must be
+/// NUL-terminated strings, `out` must point to `out_capacity` writable bytes
+/// and `out_len` must be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn keymorph_convert(
+    text: *const u8,
+    text_len: usize,
+    from: *const c_char,
+    to: *const c_char,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> KeymorphStatus {
+    guard(|| {
+        let text = text_arg(text, text_len)?;
+        let (from, to) = (layout_arg(from)?, layout_arg(to)?);
+        let converted = layouts::convert_text(text, from, to);
+        Ok(write_output(&converted, out, out_capacity, out_len))
+    })
+}
+
+/// Converts only the words of `text` that look like they were typed with the
+/// wrong one of QWERTY and Russian active.
+///
+/// # Safety
+/// Same requirements as `keymorph_convert`.
+#[no_mangle]
+pub unsafe extern "C" fn keymorph_fix(
+    text: *const u8,
+    text_len: usize,
+    out: *mut u8,
+    out_capacity: usize,
+    out_len: *mut usize,
+) -> KeymorphStatus {
+    guard(|| {
+        let text = text_arg(text, text_len)?;
+        let fixed = detect::fix_text(text, &detect::DEFAULT_LAYOUTS);
+        Ok(write_output(&fixed, out, out_capacity, out_len))
+    })
+}
+
+/// Detects which of QWERTY and Russian `text` was mistakenly typed in.
+/// Returns KEYMORPH_STATUS_NOT_DETECTED if it looks correctly typed.
+///
+/// # Safety
+/// `text` must point to `text_len` readable bytes and `detection` must be a
+/// valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn keymorph_detect(
+    text: *const u8,
+    text_len: usize,
+    detection: *mut KeymorphDetection,
+) -> KeymorphStatus {
+    guard(|| {
+        if detection.is_null() {
+            return Err(KeymorphStatus::NullPointer);
+        }
+        let text = text_arg(text, text_len)?;
+        let Some(found) = detect::detect(text, &detect::DEFAULT_LAYOUTS) else {
+            return Ok(KeymorphStatus::NotDetected);
+        };
+        *detection = KeymorphDetection {
+            from: layout_id(found.from),
+            to: layout_id(found.to),
+            confidence: found.confidence,
+        };
+        Ok(KeymorphStatus::Ok)
+    })
+}
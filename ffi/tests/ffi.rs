@@ -0,0 +1,37 @@
+// The C interface, called the way C callers do
+use keymorph_ffi::{keymorph_detect, keymorph_fix, KeymorphDetection, KeymorphStatus};
+
+fn fix(text: &str) -> String {
+    let mut out = vec![0u8; text.len() * 4];
+    let mut len = 0;
+    let status = unsafe {
+        keymorph_fix(
+            text.as_ptr(),
+            text.len(),
+            out.as_mut_ptr(),
+            out.len(),
+            &mut len,
+        )
+    };
+    assert_eq!(status, KeymorphStatus::Ok);
+    String::from_utf8(out[..len].to_vec()).unwrap()
+}
+
+fn detect(text: &str) -> KeymorphStatus {
+    let mut detection = KeymorphDetection {
+        from: 0,
+        to: 0,
+        confidence: 0.0,
+    };
+    unsafe { keymorph_detect(text.as_ptr(), text.len(), &mut detection) }
+}
+
+#[test]
+fn correct_text_passes_through_unchanged() {
+    for text in ["yesterday we met at noon", "the quick brown fox", ""] {
+        assert_eq!(fix(text), text);
+        assert_eq!(detect(text), KeymorphStatus::NotDetected);
+    }
+    assert_eq!(fix("ghbdtn, the fox"), "привет, the fox");
+    assert_eq!(detect("ghbdtn"), KeymorphStatus::Ok);
+}
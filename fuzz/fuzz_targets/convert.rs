@@ -0,0 +1,32 @@
+// Converts arbitrary text between two layouts picked by the first bytes of
+// the input, with and without the converter's options:
+//
+//     cargo +nightly fuzz run convert
+#![no_main]
+
+use keymorph::converter::Converter;
+use keymorph::layouts::{self, LayoutCode};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let [from, to, options, text @ ..] = data else {
+        return;
+    };
+    let Ok(text) = std::str::from_utf8(text) else {
+        return;
+    };
+    let from = LayoutCode::ALL[*from as usize % LayoutCode::ALL.len()];
+    let to = LayoutCode::ALL[*to as usize % LayoutCode::ALL.len()];
+
+    let converted = layouts::convert_text(text, from, to);
+    assert_eq!(layouts::parallel_convert_text(text, from, to), converted);
+
+    let converter = Converter::builder()
+        .from(from)
+        .to(to)
+        .strict(options & 1 != 0)
+        .skip_urls(options & 2 != 0)
+        .build()
+        .unwrap();
+    converter.convert(text);
+});
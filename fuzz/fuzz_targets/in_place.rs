@@ -0,0 +1,26 @@
+// Converts arbitrary bytes in place, which has to fail on invalid UTF-8 and
+// otherwise agree with `convert_text`:
+//
+//     cargo +nightly fuzz run in_place
+#![no_main]
+
+use keymorph::layouts::{self, LayoutCode};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let [from, to, bytes @ ..] = data else {
+        return;
+    };
+    let from = LayoutCode::ALL[*from as usize % LayoutCode::ALL.len()];
+    let to = LayoutCode::ALL[*to as usize % LayoutCode::ALL.len()];
+
+    let mut converted = bytes.to_vec();
+    match layouts::convert_bytes_in_place(&mut converted, from, to) {
+        Ok(()) => {
+            let text = std::str::from_utf8(bytes).unwrap();
+            let converted = std::str::from_utf8(&converted).unwrap();
+            assert_eq!(converted, layouts::convert_text(text, from, to));
+        }
+        Err(_) => assert_eq!(converted, bytes),
+    }
+});
@@ -0,0 +1,17 @@
+export interface Detection {
+  from: string
+  to: string
+  confidence: number
+}
+/** Converts `text` typed on the `from` layout to what the same keys produce on `to`. */
+export function convert(text: string, from: string, to: string): string
+/**
+ * Returns the layout `text` was most likely typed in by mistake and the one
+ * that was meant, or `null` if it looks correctly typed. `layouts` defaults
+ * to QWERTY and Russian.
+ */
+export function detect(text: string, layouts?: Array<string> | undefined | null): Detection | null
+/** Converts only the words of `text` that look like they were typed with the wrong one of `layouts` active, QWERTY and Russian by default. */
+export function fix(text: string, layouts?: Array<string> | undefined | null): string
+/** Names of the built-in layouts. */
+export function listLayouts(): Array<string>
@@ -0,0 +1,67 @@
+//! Node.js addon exposing the keymorph converter, built with napi-rs.
+//!
+//! Layouts are passed by name (`"qwerty"`, `"russian"`, ...); unknown names
+//! throw. Build with `npm run build`, which places `keymorph.node` next to
+//! `index.js`.
+use keymorph::detect;
+use keymorph::layouts::{self, LayoutCode};
+use napi::{Error, Result};
+use napi_derive::napi;
+use std::str::FromStr;
+
+fn parse_layout(layout: &str) -> Result<LayoutCode> {
+    LayoutCode::from_str(layout)
+        .map_err(|_| Error::from_reason(format!("unknown layout '{}'", layout)))
+}
+
+fn parse_layouts(layouts: Option<Vec<String>>) -> Result<Vec<LayoutCode>> {
+    match layouts {
+        Some(layouts) => layouts.iter().map(|layout| parse_layout(layout)).collect(),
+        None => Ok(detect::DEFAULT_LAYOUTS.to_vec()),
+    }
+}
+
+/// Converts `text` typed on the `from` layout to what the same keys produce
+/// on `to`.
+#[napi]
+pub fn convert(text: String, from: String, to: String) -> Result<String> {
+    let (from, to) = (parse_layout(&from)?, parse_layout(&to)?);
+    Ok(layouts::convert_text(&text, from, to))
+}
+
+#[napi(object)]
+pub struct Detection {
+    pub from: String,
+    pub to: String,
+    pub confidence: f64,
+}
+
+/// Returns the layout `text` was most likely typed in by mistake and the one
+/// that was meant, or `null` if it looks correctly typed. `layouts` defaults
+/// to QWERTY and Russian.
+#[napi]
+pub fn detect(text: String, layouts: Option<Vec<String>>) -> Result<Option<Detection>> {
+    let layouts = parse_layouts(layouts)?;
+    Ok(detect::detect(&text, &layouts).map(|detection| Detection {
+        from: detection.from.as_str().to_string(),
+        to: detection.to.as_str().to_string(),
+        confidence: detection.confidence,
+    }))
+}
+
+/// Converts only the words of `text` that look like they were typed with the
+/// wrong one of `layouts` active, QWERTY and Russian by default.
+#[napi]
+pub fn fix(text: String, layouts: Option<Vec<String>>) -> Result<String> {
+    let layouts = parse_layouts(layouts)?;
+    Ok(detect::fix_text(&text, &layouts))
+}
+
+/// Names of the built-in layouts.
+#[napi]
+pub fn list_layouts() -> Vec<String> {
+    LayoutCode::ALL
+        .iter()
+        .map(|layout| layout.as_str().to_string())
+        .collect()
+}
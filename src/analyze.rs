@@ -0,0 +1,275 @@
+use crate::geometry::{self, Finger, Hand, Row};
+use crate::layouts::{self, LayoutCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Typing effort of a text on one layout. Whitespace is treated as thumb
+// input and ignored; characters the layout cannot produce are counted as
+// untyped and break bigram and trigram sequences.
+#[derive(Serialize, Clone, Debug)]
+pub struct Report {
+    pub layout: String,
+    pub keystrokes: usize,
+    pub untyped: usize,
+    // Total distance of every key from its finger's home key, in key widths
+    pub finger_travel: f64,
+    // Percentage of consecutive keystrokes typed by the same finger on
+    // different keys
+    pub same_finger_bigrams: f64,
+    // Percentage of consecutive keystrokes by neighbouring fingers of a hand
+    // two or more key widths apart, one of them stretching sideways
+    pub lateral_stretches: f64,
+    // Percentage of consecutive keystrokes by two fingers of a hand, moving
+    // towards the index finger or towards the pinky
+    pub inward_rolls: f64,
+    pub outward_rolls: f64,
+    // Percentage of three consecutive keystrokes by three fingers of a hand
+    // that change direction, like `sdf` does not and `sfd` does
+    pub redirects: f64,
+    // Percentage of keystrokes on the home row
+    pub home_row: f64,
+    // Percentage of keystrokes on each row, number row first
+    pub row_usage: Vec<RowUsage>,
+    // Percentage of keystrokes typed by each finger, pinky to pinky
+    pub finger_load: Vec<FingerLoad>,
+    // Percentage of keystrokes typed by each hand
+    pub left_hand: f64,
+    pub right_hand: f64,
+}
+
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct RowUsage {
+    pub row: Row,
+    pub usage: f64,
+}
+
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct FingerLoad {
+    pub finger: Finger,
+    pub load: f64,
+}
+
+// Maps each character `layout` can produce to the QWERTY key producing it
+pub(crate) fn typing_keys(layout: LayoutCode) -> HashMap<char, char> {
+    let mut keys = HashMap::new();
+    for (lower, upper) in layouts::shift_pairs() {
+        keys.entry(layouts::char_at(layout, lower)).or_insert(lower);
+        keys.entry(layouts::char_at(layout, upper)).or_insert(upper);
+    }
+    keys
+}
+
+// How far in from the pinky a finger is, 0 for the pinky to 3 for the index
+fn rank(finger: Finger) -> i32 {
+    let index = Finger::ALL
+        .iter()
+        .position(|&f| f == finger)
+        .expect("every finger is listed") as i32;
+    match finger.hand() {
+        Hand::Left => index,
+        Hand::Right => 7 - index,
+    }
+}
+
+fn percent(part: usize, whole: usize) -> f64 {
+    if whole == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / whole as f64
+    }
+}
+
+pub fn analyze(text: &str, layout: LayoutCode) -> Report {
+    let keys = typing_keys(layout);
+
+    let mut keystrokes = 0;
+    let mut untyped = 0;
+    let mut finger_travel = 0.0;
+    let mut rows: HashMap<Row, usize> = HashMap::new();
+    let mut bigrams = 0;
+    let mut same_finger = 0;
+    let mut lateral_stretches = 0;
+    let mut inward_rolls = 0;
+    let mut outward_rolls = 0;
+    let mut trigrams = 0;
+    let mut redirects = 0;
+    let mut fingers: HashMap<Finger, usize> = HashMap::new();
+    let mut previous: Option<geometry::KeyPosition> = None;
+    let mut before: Option<geometry::KeyPosition> = None;
+
+    for c in text.chars() {
+        if c.is_whitespace() {
+            (previous, before) = (None, None);
+            continue;
+        }
+        let Some(pos) = keys.get(&c).and_then(|&key| geometry::position(key)) else {
+            untyped += 1;
+            (previous, before) = (None, None);
+            continue;
+        };
+
+        keystrokes += 1;
+        *fingers.entry(pos.finger).or_default() += 1;
+        finger_travel += geometry::distance(&pos, &geometry::home(pos.finger));
+        *rows.entry(pos.row).or_default() += 1;
+        if let Some(prev) = previous {
+            bigrams += 1;
+            if prev.finger == pos.finger && (prev.x, prev.y) != (pos.x, pos.y) {
+                same_finger += 1;
+            }
+            if prev.finger.hand() == pos.finger.hand() {
+                let step = rank(pos.finger) - rank(prev.finger);
+                if step.abs() == 1 && (pos.x - prev.x).abs() >= 2.0 {
+                    lateral_stretches += 1;
+                }
+                if step > 0 {
+                    inward_rolls += 1;
+                } else if step < 0 {
+                    outward_rolls += 1;
+                }
+            }
+            if let Some(first) = before {
+                trigrams += 1;
+                let hand = pos.finger.hand();
+                if first.finger.hand() == hand && prev.finger.hand() == hand {
+                    let (a, b, c) = (rank(first.finger), rank(prev.finger), rank(pos.finger));
+                    if a != c && (b - a) * (c - b) < 0 {
+                        redirects += 1;
+                    }
+                }
+            }
+        }
+        before = previous;
+        previous = Some(pos);
+    }
+
+    let usage = |row: &Row| percent(rows.get(row).copied().unwrap_or(0), keystrokes);
+    let load = |finger: &Finger| percent(fingers.get(finger).copied().unwrap_or(0), keystrokes);
+    let hand = |hand: Hand| {
+        let strokes = fingers
+            .iter()
+            .filter(|(finger, _)| finger.hand() == hand)
+            .map(|(_, &strokes)| strokes)
+            .sum();
+        percent(strokes, keystrokes)
+    };
+    Report {
+        layout: layout.as_str().to_string(),
+        keystrokes,
+        untyped,
+        finger_travel,
+        same_finger_bigrams: percent(same_finger, bigrams),
+        lateral_stretches: percent(lateral_stretches, bigrams),
+        inward_rolls: percent(inward_rolls, bigrams),
+        outward_rolls: percent(outward_rolls, bigrams),
+        redirects: percent(redirects, trigrams),
+        home_row: usage(&Row::Home),
+        row_usage: Row::ALL
+            .iter()
+            .map(|row| RowUsage {
+                row: *row,
+                usage: usage(row),
+            })
+            .collect(),
+        finger_load: Finger::ALL
+            .iter()
+            .map(|finger| FingerLoad {
+                finger: *finger,
+                load: load(finger),
+            })
+            .collect(),
+        left_hand: hand(Hand::Left),
+        right_hand: hand(Hand::Right),
+    }
+}
+
+// Metric layouts are ranked by in a comparison
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    // Finger travel per keystroke
+    #[default]
+    FingerTravel,
+    SameFingerBigrams,
+    LateralStretches,
+    Redirects,
+    InwardRolls,
+    HomeRow,
+}
+
+impl Metric {
+    fn value(self, report: &Report) -> f64 {
+        match self {
+            Metric::FingerTravel if report.keystrokes == 0 => 0.0,
+            Metric::FingerTravel => report.finger_travel / report.keystrokes as f64,
+            Metric::SameFingerBigrams => report.same_finger_bigrams,
+            Metric::LateralStretches => report.lateral_stretches,
+            Metric::Redirects => report.redirects,
+            Metric::InwardRolls => report.inward_rolls,
+            Metric::HomeRow => report.home_row,
+        }
+    }
+
+    fn higher_is_better(self) -> bool {
+        matches!(self, Metric::InwardRolls | Metric::HomeRow)
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Ranked {
+    // 1 for the best layout; layouts that score the same share a rank
+    pub rank: usize,
+    #[serde(flatten)]
+    pub report: Report,
+}
+
+// Reports of `text` on each of `layouts`, best first by `metric`, after
+// fewest untyped characters so that layouts of another script do not win by
+// typing nothing. Layouts are analyzed on threads of their own, as many at a
+// time as `layouts::parallelism` allows.
+pub fn compare(text: &str, layouts: &[LayoutCode], metric: Metric) -> Vec<Ranked> {
+    let max_threads = layouts::parallelism().max_threads;
+    let mut reports: Vec<Report> = if max_threads <= 1 || cfg!(target_arch = "wasm32") {
+        layouts
+            .iter()
+            .map(|&layout| analyze(text, layout))
+            .collect()
+    } else {
+        let mut reports = Vec::with_capacity(layouts.len());
+        for batch in layouts.chunks(max_threads) {
+            std::thread::scope(|scope| {
+                let handles: Vec<_> = batch
+                    .iter()
+                    .map(|&layout| scope.spawn(move || analyze(text, layout)))
+                    .collect();
+                for handle in handles {
+                    reports.push(handle.join().unwrap());
+                }
+            });
+        }
+        reports
+    };
+
+    reports.sort_by(|a, b| {
+        let by_metric = if metric.higher_is_better() {
+            metric.value(b).total_cmp(&metric.value(a))
+        } else {
+            metric.value(a).total_cmp(&metric.value(b))
+        };
+        a.untyped.cmp(&b.untyped).then(by_metric)
+    });
+    let mut ranked: Vec<Ranked> = Vec::with_capacity(reports.len());
+    for (i, report) in reports.into_iter().enumerate() {
+        let rank = match ranked.last() {
+            Some(last)
+                if last.report.untyped == report.untyped
+                    && metric.value(&last.report) == metric.value(&report) =>
+            {
+                last.rank
+            }
+            _ => i + 1,
+        };
+        ranked.push(Ranked { rank, report });
+    }
+    ranked
+}
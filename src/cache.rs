@@ -0,0 +1,82 @@
+use crate::config::CacheConfig;
+use crate::registry::{self, ConvertOptions};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+// (from, to, options, text)
+type Key = (String, String, ConvertOptions, String);
+
+struct Entries {
+    // Registry generation the entries were computed against
+    generation: u64,
+    results: HashMap<Key, String>,
+    order: VecDeque<Key>,
+}
+
+// Bounded memo of conversion results. The oldest entry is evicted first and
+// everything is dropped whenever custom layouts are reloaded.
+pub struct ConversionCache {
+    capacity: usize,
+    max_text_chars: usize,
+    entries: Mutex<Entries>,
+}
+
+impl ConversionCache {
+    pub fn new(config: &CacheConfig) -> Self {
+        ConversionCache {
+            capacity: config.capacity,
+            max_text_chars: config.max_text_chars,
+            entries: Mutex::new(Entries {
+                generation: registry::generation(),
+                results: HashMap::new(),
+                order: VecDeque::new(),
+            }),
+        }
+    }
+
+    pub fn get_or_convert(
+        &self,
+        from: &str,
+        to: &str,
+        options: &ConvertOptions,
+        text: &str,
+        convert: impl FnOnce() -> String,
+    ) -> String {
+        if self.capacity == 0 || text.chars().count() > self.max_text_chars {
+            return convert();
+        }
+
+        let key = (
+            from.to_lowercase(),
+            to.to_lowercase(),
+            *options,
+            text.to_string(),
+        );
+        let generation = registry::generation();
+        {
+            let mut entries = self.entries.lock().unwrap();
+            if entries.generation != generation {
+                entries.generation = generation;
+                entries.results.clear();
+                entries.order.clear();
+            }
+            if let Some(result) = entries.results.get(&key) {
+                return result.clone();
+            }
+        }
+
+        let result = convert();
+
+        let mut entries = self.entries.lock().unwrap();
+        if entries.generation == generation && !entries.results.contains_key(&key) {
+            if entries.order.len() >= self.capacity {
+                if let Some(oldest) = entries.order.pop_front() {
+                    entries.results.remove(&oldest);
+                }
+            }
+            entries.order.push_back(key.clone());
+            entries.results.insert(key, result.clone());
+        }
+        result
+    }
+}
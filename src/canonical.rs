@@ -0,0 +1,225 @@
+// The canonical layout schema, the form layouts are exchanged in between
+// keymorph and other tools. Where custom layout files (`schema`) list what
+// changes on top of a base layout, a canonical layout lists every key of the
+// main block by position, with what it types on each layer and hints about
+// where it sits. Documents carry the version of the schema they follow, so
+// that readers can reject those newer than they understand, and layout
+// files are read as canonical layouts when they have one.
+use crate::detect::Language;
+use crate::geometry::{self, Finger, Row};
+use crate::keys;
+use crate::layouts::{self, LayoutCode};
+use crate::registry::Layout;
+use crate::schema::LayoutFile;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+// Version of the schema written, and the newest one read
+pub const VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct CanonicalLayout {
+    pub version: u32,
+    pub name: String,
+    #[serde(default)]
+    pub metadata: Metadata,
+    #[serde(default)]
+    pub geometry: Geometry,
+    // The layers every key lists a character for, in that order
+    pub layers: Vec<LayerName>,
+    pub keys: Vec<Key>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Metadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    // ISO 639-1 code of the language usually typed on the layout
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    // Where the layout came from: `builtin`, or the file it was loaded from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Form {
+    // Row-staggered ANSI main block, as in `geometry`
+    #[default]
+    Ansi,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Geometry {
+    pub form: Form,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum LayerName {
+    Base,
+    Shift,
+    AltGr,
+    AltGrShift,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Key {
+    // `KeyboardEvent.code` of the key, as in `keys`
+    pub code: String,
+    // Geometry hints for renderers and analyzers. They follow from `code`
+    // and are ignored when a layout is imported.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub row: Option<Row>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finger: Option<Finger>,
+    // Left edge of the key in key widths, as `geometry::KeyPosition::x`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub x: Option<f64>,
+    // What the key types on each of `CanonicalLayout::layers`, "" for nothing
+    pub chars: Vec<String>,
+}
+
+// `layout` in the canonical schema. Built-in layouts with AltGr levels
+// also have the `alt_gr` and `alt_gr_shift` layers.
+pub fn export(layout: &Layout) -> CanonicalLayout {
+    let (altgr, language, source) = match layout {
+        Layout::Builtin(code) => (
+            layouts::altgr_keys(*code),
+            Language::of(*code),
+            "builtin".to_string(),
+        ),
+        Layout::Custom(custom) => (&[][..], None, custom.path.display().to_string()),
+    };
+    let mut layers = vec![LayerName::Base, LayerName::Shift];
+    if !altgr.is_empty() {
+        layers.extend([LayerName::AltGr, LayerName::AltGrShift]);
+    }
+
+    let keys = keys::CODES
+        .iter()
+        .map(|&(code, key)| {
+            let position = geometry::position(key);
+            let shifted = layouts::shift_pairs()
+                .find(|&(lower, _)| lower == key)
+                .map_or(key, |(_, upper)| upper);
+            let mut chars = vec![
+                layout.char_at(key).to_string(),
+                layout.char_at(shifted).to_string(),
+            ];
+            if !altgr.is_empty() {
+                let altgr = altgr.iter().find(|altgr| altgr.key == key);
+                chars.push(altgr.map_or(String::new(), |altgr| altgr.unshifted.to_string()));
+                chars.push(altgr.map_or(String::new(), |altgr| altgr.shifted.to_string()));
+            }
+            Key {
+                code: code.to_string(),
+                row: position.map(|position| position.row),
+                finger: position.map(|position| position.finger),
+                x: position.map(|position| position.x),
+                chars,
+            }
+        })
+        .collect();
+
+    CanonicalLayout {
+        version: VERSION,
+        name: layout.name().to_string(),
+        metadata: Metadata {
+            description: None,
+            language: language.map(|language| {
+                match language {
+                    Language::English => "en",
+                    Language::Russian => "ru",
+                }
+                .to_string()
+            }),
+            author: None,
+            source: Some(source),
+        },
+        geometry: Geometry::default(),
+        layers,
+        keys,
+    }
+}
+
+// The custom layout a canonical one describes, based on QWERTY. Custom
+// layouts have no AltGr levels, so the `alt_gr` layers are checked but not
+// imported; keys the layout does not list type what they do on QWERTY.
+pub fn import(layout: &CanonicalLayout) -> Result<LayoutFile, String> {
+    if layout.version == 0 || layout.version > VERSION {
+        return Err(format!(
+            "schema version {} is not supported, the newest is {}",
+            layout.version, VERSION
+        ));
+    }
+    let index = |name: LayerName| layout.layers.iter().position(|&layer| layer == name);
+    let (Some(base), Some(shift)) = (index(LayerName::Base), index(LayerName::Shift)) else {
+        return Err("the layers must include base and shift".to_string());
+    };
+    if layout.layers.iter().collect::<HashSet<_>>().len() != layout.layers.len() {
+        return Err("a layer is listed twice".to_string());
+    }
+
+    let mut seen = HashSet::new();
+    let mut keys = BTreeMap::new();
+    for key in &layout.keys {
+        let Some(&(_, unshifted)) = keys::CODES.iter().find(|(code, _)| *code == key.code) else {
+            return Err(format!("unknown key '{}'", key.code));
+        };
+        if !seen.insert(key.code.as_str()) {
+            return Err(format!("key '{}' is listed twice", key.code));
+        }
+        if key.chars.len() != layout.layers.len() {
+            return Err(format!(
+                "key '{}' has {} characters for {} layers",
+                key.code,
+                key.chars.len(),
+                layout.layers.len()
+            ));
+        }
+        if let Some(chars) = key.chars.iter().find(|chars| chars.chars().count() > 1) {
+            return Err(format!(
+                "key '{}' types '{}', which is not a single character",
+                key.code, chars
+            ));
+        }
+        let shifted = layouts::shift_pairs()
+            .find(|&(lower, _)| lower == unshifted)
+            .map_or(unshifted, |(_, upper)| upper);
+        for (qwerty, layer) in [(unshifted, base), (shifted, shift)] {
+            let Some(c) = key.chars[layer].chars().next() else {
+                continue;
+            };
+            if c != qwerty {
+                keys.insert(qwerty.to_string(), c.to_string());
+            }
+        }
+    }
+
+    Ok(LayoutFile {
+        name: layout.name.clone(),
+        base: LayoutCode::Qwerty.as_str().to_string(),
+        keys,
+        derived: None,
+    })
+}
+
+pub fn to_json(layout: &CanonicalLayout) -> String {
+    serde_json::to_string_pretty(layout).expect("canonical layouts always serialize to JSON")
+}
+
+pub fn to_toml(layout: &CanonicalLayout) -> String {
+    toml::to_string(layout).expect("canonical layouts always serialize to TOML")
+}
+
+pub fn from_json(text: &str) -> Result<CanonicalLayout, String> {
+    serde_json::from_str(text).map_err(|err| err.to_string())
+}
+
+pub fn from_toml(text: &str) -> Result<CanonicalLayout, String> {
+    toml::from_str(text).map_err(|err| err.to_string())
+}
@@ -0,0 +1,1673 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use keymorph::analyze;
+use keymorph::canonical;
+use keymorph::config::Config;
+use keymorph::converter::Converter;
+use keymorph::detect::{self, Language};
+use keymorph::diff;
+use keymorph::document;
+use keymorph::drills::{self, DrillFocus};
+use keymorph::edits::{self, Edit};
+use keymorph::emacs;
+use keymorph::files;
+use keymorph::firmware::{self, Board, Exporter};
+use keymorph::keys::{self, KeyEvent};
+use keymorph::klc;
+use keymorph::layouts::{self, CasePolicy, LayoutCode, LayoutOptions, YoPolicy};
+use keymorph::profiles::{self, ProfileFormat};
+use keymorph::qmk::Qmk;
+use keymorph::registry;
+use keymorph::remapper::Remapper;
+use keymorph::schema;
+use keymorph::shortcuts::{self, KeySemantics};
+use keymorph::validate::{self, Severity};
+use keymorph::vim;
+use keymorph::visualize::{self, HeatScale, Overlay};
+use keymorph::xkb;
+use keymorph::zmk::Zmk;
+use std::collections::HashSet;
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::str::FromStr;
+
+#[derive(Parser)]
+#[command(
+    name = "keymorph",
+    about = "Convert text typed in the wrong keyboard layout"
+)]
+pub struct Cli {
+    /// Configuration file (default: $KEYMORPH_CONFIG, ./keymorph.toml or
+    /// ~/.config/keymorph/keymorph.toml). Flags override environment
+    /// variables, which override the file.
+    #[arg(long, global = true)]
+    pub config: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Run the HTTP server (default)
+    Serve(ServeArgs),
+    /// Inspect built-in and custom layouts
+    Layouts {
+        #[command(subcommand)]
+        command: LayoutsCommand,
+    },
+    /// Convert text from one layout to another
+    Convert {
+        #[arg(long)]
+        from: String,
+        #[arg(long)]
+        to: String,
+        #[command(flatten)]
+        options: ConvertArgs,
+        #[command(flatten)]
+        output: RewriteArgs,
+    },
+    /// Convert only the words that look like they were typed in the wrong layout
+    Fix {
+        /// Comma-separated layouts the text may have been typed in
+        #[arg(long, value_delimiter = ',', default_value = "qwerty,russian")]
+        layouts: Vec<String>,
+        /// How words are scored [default: from config, else dictionary]
+        #[arg(long, value_enum)]
+        backend: Option<BackendArg>,
+        /// Detect each sentence on its own and convert it as a whole
+        #[arg(long)]
+        by_segment: bool,
+        /// Least confidence, from 0 to 1, a sentence is converted with
+        #[arg(long, default_value_t = document::DEFAULT_MIN_CONFIDENCE, requires = "by_segment")]
+        min_confidence: f64,
+        #[command(flatten)]
+        output: RewriteArgs,
+    },
+    /// Compare the typing effort of a text across layouts
+    Analyze {
+        /// Text file to analyze, or - for standard input
+        file: PathBuf,
+        /// Comma-separated layouts to compare [default: from config]
+        #[arg(long, value_delimiter = ',')]
+        layouts: Option<Vec<String>>,
+        /// Print the reports as JSON
+        #[arg(long)]
+        json: bool,
+        #[command(flatten)]
+        tables: AnalyzeTables,
+    },
+    /// Show what to press on one layout for shortcuts documented for another
+    Shortcut {
+        /// Shortcuts like Ctrl+Shift+T, ⌘P or "Ctrl+K Ctrl+S"
+        #[arg(required = true)]
+        shortcuts: Vec<String>,
+        /// Layout the shortcuts are documented for
+        #[arg(long, default_value = "qwerty")]
+        from: String,
+        /// Layout of the user pressing them
+        #[arg(long)]
+        to: String,
+        /// Whether applications bind the character or the key position
+        #[arg(long, value_enum, default_value_t = SemanticsArg::Character)]
+        by: SemanticsArg,
+    },
+    /// Rewrite the mappings of a vimrc to keep their keys on a new layout
+    RemapVim {
+        /// Layout the vimrc was written for
+        #[arg(long, default_value = "qwerty")]
+        from: String,
+        /// Layout switched to
+        #[arg(long)]
+        to: String,
+        #[command(flatten)]
+        output: RewriteArgs,
+    },
+    /// Rewrite the key bindings of Emacs Lisp code to keep their keys on a new
+    /// layout
+    RemapEmacs {
+        /// Layout the bindings were written for
+        #[arg(long, default_value = "qwerty")]
+        from: String,
+        /// Layout switched to
+        #[arg(long)]
+        to: String,
+        #[command(flatten)]
+        output: RewriteArgs,
+    },
+    /// Remap the bindings of an exported shortcut profile to keep their keys on
+    /// a new layout
+    RemapProfile {
+        /// Layout the bindings were set up on
+        #[arg(long, default_value = "qwerty")]
+        from: String,
+        /// Layout switched to
+        #[arg(long)]
+        to: String,
+        /// Profile format [default: from the file extension, else json]
+        #[arg(long, value_enum)]
+        format: Option<ProfileFormatArg>,
+        /// Comma-separated names of the fields holding bindings [default:
+        /// key, keys, binding, bindings, keybind, keybinding, shortcut,
+        /// hotkey, primary, secondary]
+        #[arg(long, value_delimiter = ',')]
+        fields: Vec<String>,
+        #[command(flatten)]
+        output: RewriteArgs,
+    },
+    /// Translate between key events and the text they type on a layout
+    Keys {
+        #[command(subcommand)]
+        command: KeysCommand,
+    },
+    /// Remap a keyboard live, e.g. to type Dvorak on a system set up for QWERTY
+    ///
+    /// Grabs the input device and re-emits its key presses through a uinput
+    /// virtual keyboard. Press the panic key to stop and release the device.
+    #[cfg(all(feature = "evdev", target_os = "linux"))]
+    Daemon(DaemonArgs),
+    /// Watch typing and retype the last word in the intended layout on a hotkey
+    #[cfg(all(feature = "winhook", windows))]
+    Hook(HookArgs),
+    /// Retype the selected text with wrong-layout words fixed on a hotkey
+    ///
+    /// Needs the Accessibility permission. The hotkey defaults to F15, which
+    /// is what the Pause key sends on macOS.
+    #[cfg(all(feature = "mactap", target_os = "macos"))]
+    Tap(HookArgs),
+    /// Fix the selected text and type the result over it (X11 and Wayland)
+    ///
+    /// Bind this to a shortcut in the desktop settings, or run it with
+    /// --watch to listen for the daemon hotkey itself. Needs xclip and xdotool
+    /// on X11, wl-paste and wtype on Wayland.
+    #[cfg(target_os = "linux")]
+    Selection {
+        /// Comma-separated layouts to convert between [default: from config,
+        /// else qwerty,russian]
+        #[arg(long, value_delimiter = ',')]
+        layouts: Option<Vec<String>>,
+        /// Read the clipboard instead of the primary selection
+        #[arg(long)]
+        clipboard: bool,
+        /// Keep running and fix the selection whenever the daemon hotkey is
+        /// pressed on the daemon device
+        #[cfg(feature = "evdev")]
+        #[arg(long)]
+        watch: bool,
+    },
+    /// Serve convert/detect/fix on the session bus as org.keymorph.Converter
+    #[cfg(feature = "dbus")]
+    Dbus {
+        /// Load the layouts directories once instead of reloading files as
+        /// they change
+        #[arg(long)]
+        no_watch: bool,
+    },
+    /// Run as an IBus input method engine offering wrong-layout words converted
+    #[cfg(all(feature = "ibus", target_os = "linux"))]
+    Ime {
+        /// Comma-separated layouts to convert between [default: from config,
+        /// else qwerty,russian]
+        #[arg(long, value_delimiter = ',')]
+        layouts: Option<Vec<String>>,
+        /// Print the IBus component file to install and exit
+        #[arg(long)]
+        xml: bool,
+        /// Passed by ibus-daemon when it starts the engine
+        #[arg(long, hide = true)]
+        ibus: bool,
+    },
+}
+
+#[derive(Args, Default)]
+pub struct ServeArgs {
+    #[arg(long)]
+    pub host: Option<String>,
+    #[arg(long)]
+    pub port: Option<u16>,
+    /// Directory of custom layout files (.toml or .json) to serve, replacing
+    /// the configured directories; may be repeated
+    #[arg(long)]
+    pub layouts_dir: Vec<PathBuf>,
+    /// Load the layouts directory once instead of reloading files as they change
+    #[arg(long)]
+    pub no_watch: bool,
+}
+
+#[cfg(all(feature = "evdev", target_os = "linux"))]
+#[derive(Args)]
+pub struct DaemonArgs {
+    /// Input device: a /dev/input path or part of the device name
+    /// [default: from config, else the first keyboard]
+    #[arg(long)]
+    pub device: Option<String>,
+    /// Layout you type in [default: from config]
+    #[arg(long)]
+    pub layout: Option<String>,
+    /// Layout the system is configured for [default: from config, else qwerty]
+    #[arg(long)]
+    pub system_layout: Option<String>,
+    /// evdev name of the key that stops remapping [default: from config, else KEY_PAUSE]
+    #[arg(long)]
+    pub panic_key: Option<String>,
+    /// List the keyboards that can be remapped and exit
+    #[arg(long)]
+    pub list_devices: bool,
+}
+
+#[cfg(any(
+    all(feature = "winhook", windows),
+    all(feature = "mactap", target_os = "macos")
+))]
+#[derive(Args)]
+pub struct HookArgs {
+    /// Comma-separated layouts to convert between [default: from config,
+    /// else qwerty,russian]
+    #[arg(long, value_delimiter = ',')]
+    pub layouts: Option<Vec<String>>,
+    /// Key that triggers the fix: KEY_PAUSE, KEY_SCROLLLOCK or KEY_F1 ...
+    /// KEY_F24 [default: from config, else KEY_PAUSE]
+    #[arg(long)]
+    pub hotkey: Option<String>,
+}
+
+#[derive(Args)]
+pub struct ConvertArgs {
+    /// What to do with ё in the result
+    #[arg(long, value_enum, default_value_t = YoArg::Keep)]
+    pub yo: YoArg,
+    /// Write ё as е in the result, same as --yo fold-to-e
+    #[arg(long, conflicts_with = "yo")]
+    pub fold_yo: bool,
+    /// Leave words with characters the source layout cannot type unchanged
+    #[arg(long)]
+    pub strict: bool,
+    /// Leave URLs and email addresses unchanged
+    #[arg(long)]
+    pub skip_urls: bool,
+    /// Also convert the symbols on shifted digits that differ, like # and №
+    #[arg(long)]
+    pub digit_symbols: bool,
+    /// Leave punctuation and symbols unchanged where they would become other
+    /// ones, converting keys that type letters on either layout
+    #[arg(long)]
+    pub keep_punctuation: bool,
+    /// How capitals convert to and from layouts without them
+    #[arg(long, value_enum, default_value_t = CaseArg::Keys)]
+    pub case: CaseArg,
+    /// Write the result to this file instead of standard output
+    #[arg(
+        short,
+        long = "output",
+        value_name = "FILE",
+        conflicts_with_all = ["in_place", "diff", "dry_run"]
+    )]
+    pub output_file: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum YoArg {
+    /// Keep ё wherever its key was pressed
+    Keep,
+    /// Write е throughout
+    FoldToE,
+    /// Also write ё for е in words always spelled with it
+    Restore,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum BackendArg {
+    /// Look words up in the dictionaries
+    Dictionary,
+    /// Score words by how much their letters look like the language
+    Trigram,
+    /// Average the dictionary and trigram scores
+    Blend,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum CaseArg {
+    /// Convert each key as typed, with or without Shift
+    Keys,
+    /// Leave capitals the target cannot tell from lower case unchanged
+    KeepCapitals,
+    /// Capitalize sentences converted from a layout without capitals
+    Sentence,
+}
+
+impl ConvertArgs {
+    fn yo_policy(&self) -> YoPolicy {
+        match self.yo {
+            _ if self.fold_yo => YoPolicy::FoldToE,
+            YoArg::Keep => YoPolicy::Keep,
+            YoArg::FoldToE => YoPolicy::FoldToE,
+            YoArg::Restore => YoPolicy::Restore,
+        }
+    }
+
+    fn case_policy(&self) -> CasePolicy {
+        match self.case {
+            CaseArg::Keys => CasePolicy::Keys,
+            CaseArg::KeepCapitals => CasePolicy::KeepCapitals,
+            CaseArg::Sentence => CasePolicy::Sentence,
+        }
+    }
+}
+
+// Tables `analyze` prints after the summary
+#[derive(Args)]
+pub struct AnalyzeTables {
+    /// Also show how the keystrokes spread over fingers and hands
+    #[arg(long)]
+    pub fingers: bool,
+    /// Also show lateral stretches, rolls and redirects
+    #[arg(long)]
+    pub bigrams: bool,
+    /// Also show how the keystrokes spread over the rows
+    #[arg(long)]
+    pub rows: bool,
+}
+
+#[derive(Args)]
+pub struct RewriteArgs {
+    /// Input file, or - for standard input
+    #[arg(default_value = "-")]
+    pub file: PathBuf,
+    /// Write the result back to the input file
+    #[arg(short, long)]
+    pub in_place: bool,
+    /// Show a word-level diff of the changes instead of the result
+    #[arg(long)]
+    pub diff: bool,
+    /// Show what would change without writing anything (implies --diff)
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Colorize the diff
+    #[arg(long, value_enum, default_value_t = Color::Auto)]
+    pub color: Color,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Color {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Subcommand)]
+pub enum LayoutsCommand {
+    /// Print a layout in the custom layout schema, to use as a template, or
+    /// as an XKB symbols file to install it
+    Export {
+        layout: String,
+        /// Output format [default: from config, else json]
+        #[arg(long, value_enum)]
+        format: Option<Format>,
+        /// Print json or toml in the versioned canonical layout schema, which
+        /// lists every key with all its layers
+        #[arg(long)]
+        canonical: bool,
+    },
+    /// Print an XKB symbols file as a custom layout
+    Import {
+        file: PathBuf,
+        /// Output format [default: from config, else json]
+        #[arg(long, value_enum)]
+        format: Option<Format>,
+    },
+    /// Check a custom layout file for conflicts and lossy mappings
+    ///
+    /// Exits with 1 if errors were found (or warnings, with --strict) and
+    /// with 2 if the file could not be read.
+    Lint {
+        file: PathBuf,
+        /// Treat warnings as errors
+        #[arg(long)]
+        strict: bool,
+    },
+    /// Show which keys produce different characters on two layouts
+    Diff {
+        from: String,
+        to: String,
+        /// Also draw the second layout with changed keys marked
+        #[arg(long)]
+        keyboard: bool,
+    },
+    /// Print typing practice lines for learning a layout, weighted towards
+    /// the keys that differ from the layout typed now
+    Drill {
+        layout: String,
+        /// Layout typed now
+        #[arg(long, default_value = "qwerty")]
+        from: String,
+        /// Rows to practise
+        #[arg(long, value_enum, default_value = "all")]
+        focus: FocusArg,
+        /// Language of the words (en, ru) [default: the one usually typed
+        /// on the layout]
+        #[arg(long)]
+        lang: Option<String>,
+        #[arg(long, default_value_t = 5)]
+        lines: usize,
+        /// Seed picking the words, for a different drill
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+    /// Print a QMK or ZMK keymap making a programmable keyboard type a
+    /// layout while the computer stays set to --host, or a kmonad or kanata
+    /// configuration doing it in software
+    Keymap {
+        layout: String,
+        #[arg(long, value_enum, default_value = "ansi_60")]
+        board: BoardArg,
+        #[arg(long, value_enum, default_value = "qmk")]
+        firmware: FirmwareArg,
+        /// Layout the computer is set to
+        #[arg(long, default_value = "qwerty")]
+        host: String,
+        /// Print QMK's keymap.json instead of keymap.c
+        #[arg(long)]
+        json: bool,
+    },
+    /// Draw a layout as an SVG or PNG image, with keys coloured by an overlay
+    Render {
+        layout: String,
+        #[arg(long, value_enum, default_value = "svg")]
+        format: ImageFormatArg,
+        #[arg(long, value_enum, default_value = "none")]
+        overlay: OverlayArg,
+        /// Layout the diff overlay compares against
+        #[arg(long, default_value = "qwerty")]
+        from: String,
+        /// Text whose key presses the heatmap shows [default: the letter
+        /// frequencies of --lang]
+        #[arg(long)]
+        text: Option<PathBuf>,
+        /// Language of the heatmap without --text (en, ru) [default: the one
+        /// usually typed on the layout]
+        #[arg(long)]
+        lang: Option<String>,
+        /// How the heatmap colours follow the presses
+        #[arg(long, value_enum, default_value = "linear")]
+        heat_scale: HeatScaleArg,
+        /// PNG size relative to the SVG one
+        #[arg(long, default_value_t = 1.0)]
+        scale: f32,
+        /// Write the image to this file instead of standard output
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum BoardArg {
+    /// Row-staggered 60% board
+    #[value(name = "ansi_60")]
+    Ansi60,
+    /// Ortholinear 4x12 board
+    Planck,
+    /// Split 3x6 board with three thumb keys a side
+    Corne,
+    /// Split ergonomic board with a function row and six thumb keys a side
+    Glove80,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum FirmwareArg {
+    Qmk,
+    Zmk,
+    /// kmonad configuration, for any keyboard
+    Kmonad,
+    /// kanata configuration, for any keyboard
+    Kanata,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ImageFormatArg {
+    Svg,
+    Png,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum HeatScaleArg {
+    Linear,
+    /// Logarithmic, to tell rarely pressed keys apart
+    Log,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum OverlayArg {
+    None,
+    /// How often each key is pressed
+    Heatmap,
+    /// Keys that type something else than on --from
+    Diff,
+    /// The finger pressing each key
+    Fingers,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum FocusArg {
+    All,
+    NumberRow,
+    TopRow,
+    HomeRow,
+    BottomRow,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ProfileFormatArg {
+    Json,
+    Yaml,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum SemanticsArg {
+    /// Press the key typing the shortcut's character
+    Character,
+    /// Press the key in the same place on the keyboard
+    Position,
+}
+
+#[derive(Subcommand)]
+pub enum KeysCommand {
+    /// Print the text typed by key events, one JSON object per line like
+    /// {"key": "KeyD", "shift": true}, or a JSON array of them
+    Type {
+        #[arg(long)]
+        layout: String,
+        /// Input file, or - for standard input
+        #[arg(default_value = "-")]
+        file: PathBuf,
+    },
+    /// Print the key events that type a text, one JSON object per line
+    Events {
+        #[arg(long)]
+        layout: String,
+        /// Input file, or - for standard input
+        #[arg(default_value = "-")]
+        file: PathBuf,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    Json,
+    Toml,
+    /// XKB symbols file, to install the layout on Linux
+    Xkb,
+    /// Microsoft Keyboard Layout Creator source, to install the layout on
+    /// Windows
+    Klc,
+}
+
+impl ServeArgs {
+    // Applies the flags on top of the file and environment configuration
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(host) = &self.host {
+            config.server.host = host.clone();
+        }
+        if let Some(port) = self.port {
+            config.server.port = port;
+        }
+        if !self.layouts_dir.is_empty() {
+            config.layouts.dirs = self.layouts_dir.clone();
+        }
+        if self.no_watch {
+            config.layouts.watch = false;
+        }
+    }
+}
+
+#[cfg(all(feature = "evdev", target_os = "linux"))]
+impl DaemonArgs {
+    // Applies the flags on top of the file and environment configuration
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(device) = &self.device {
+            config.daemon.device = Some(device.clone());
+        }
+        if let Some(layout) = &self.layout {
+            config.daemon.layout = Some(layout.clone());
+        }
+        if let Some(system_layout) = &self.system_layout {
+            config.daemon.system_layout = system_layout.clone();
+        }
+        if let Some(panic_key) = &self.panic_key {
+            config.daemon.panic_key = panic_key.clone();
+        }
+    }
+}
+
+#[cfg(any(
+    all(feature = "winhook", windows),
+    all(feature = "mactap", target_os = "macos")
+))]
+impl HookArgs {
+    // Applies the flags on top of the file and environment configuration
+    pub fn apply(&self, config: &mut Config) {
+        if let Some(layouts) = &self.layouts {
+            config.daemon.layouts = layouts.clone();
+        }
+        if let Some(hotkey) = &self.hotkey {
+            config.daemon.hotkey = hotkey.clone();
+        }
+    }
+}
+
+// The format given, else the configured one
+fn export_format(format: Option<Format>, config: &Config) -> Result<Format, ExitCode> {
+    match format {
+        Some(format) => Ok(format),
+        None => Format::from_str(&config.cli.export_format, true).map_err(|_| {
+            eprintln!(
+                "Error: invalid export_format '{}' in configuration",
+                config.cli.export_format
+            );
+            ExitCode::from(2)
+        }),
+    }
+}
+
+pub fn layouts(command: LayoutsCommand, config: &Config) -> ExitCode {
+    match command {
+        LayoutsCommand::Export {
+            layout,
+            format,
+            canonical,
+        } => match export_format(format, config) {
+            Ok(format) => export(&layout, format, canonical, config),
+            Err(code) => code,
+        },
+        LayoutsCommand::Import { file, format } => match export_format(format, config) {
+            Ok(format) => import(&file, format),
+            Err(code) => code,
+        },
+        LayoutsCommand::Lint { file, strict } => lint(&file, strict),
+        LayoutsCommand::Diff { from, to, keyboard } => layout_diff(&from, &to, keyboard),
+        LayoutsCommand::Drill {
+            layout,
+            from,
+            focus,
+            lang,
+            lines,
+            seed,
+        } => drill(&layout, &from, focus, lang.as_deref(), lines, seed),
+        LayoutsCommand::Keymap {
+            layout,
+            board,
+            firmware,
+            host,
+            json,
+        } => keymap(&layout, board, firmware, &host, json, config),
+        LayoutsCommand::Render {
+            layout,
+            format,
+            overlay,
+            from,
+            text,
+            lang,
+            heat_scale,
+            scale,
+            output,
+        } => render(
+            &layout,
+            format,
+            overlay,
+            &from,
+            text.as_deref(),
+            lang.as_deref(),
+            heat_scale,
+            scale,
+            output.as_deref(),
+        ),
+    }
+}
+
+fn parse_layout(layout: &str) -> Option<LayoutCode> {
+    let code = LayoutCode::from_str(layout).ok();
+    if code.is_none() {
+        eprintln!("Error: unknown layout '{}'", layout);
+    }
+    code
+}
+
+// A built-in layout or one of the layout directories of the configuration
+fn resolve_layout(layout: &str, config: &Config) -> Option<registry::Layout> {
+    resolve_layout_id(layout, config).map(|(layout, _)| layout)
+}
+
+// `resolve_layout` for names that may carry options, like `russian?yo=false`
+fn resolve_layout_id(layout: &str, config: &Config) -> Option<(registry::Layout, LayoutOptions)> {
+    for dir in &config.layouts.dirs {
+        if let Err(err) = registry::load_dir(dir) {
+            eprintln!("Error: cannot read {}: {}", dir.display(), err);
+            return None;
+        }
+    }
+    let resolved = registry::resolve_with_options(layout);
+    if resolved.is_none() {
+        eprintln!("Error: unknown layout '{}'", layout);
+    }
+    resolved
+}
+
+fn export(layout: &str, format: Format, canonical: bool, config: &Config) -> ExitCode {
+    // Custom layouts only export to the other schemas, being written in the
+    // custom layout one already
+    if canonical || matches!(format, Format::Xkb | Format::Klc) {
+        let Some(layout) = resolve_layout(layout, config) else {
+            return ExitCode::FAILURE;
+        };
+        let exported = match format {
+            Format::Json => (canonical::to_json(&canonical::export(&layout)) + "\n").into_bytes(),
+            Format::Toml => canonical::to_toml(&canonical::export(&layout)).into_bytes(),
+            Format::Xkb => xkb::export(&layout).into_bytes(),
+            Format::Klc => klc::to_utf16(&klc::export(&layout)),
+        };
+        let written = std::io::stdout().write_all(&exported);
+        if let Err(err) = written {
+            eprintln!("Error: {}", err);
+            return ExitCode::FAILURE;
+        }
+        return ExitCode::SUCCESS;
+    }
+    let Some(code) = parse_layout(layout) else {
+        return ExitCode::FAILURE;
+    };
+
+    let file = schema::export(code);
+    match format {
+        Format::Json => println!("{}", schema::to_json(&file)),
+        Format::Toml => print!("{}", schema::to_toml(&file)),
+        Format::Xkb | Format::Klc => unreachable!(),
+    }
+    ExitCode::SUCCESS
+}
+
+fn import(file: &Path, format: Format) -> ExitCode {
+    let layout = match std::fs::read_to_string(file) {
+        Ok(symbols) => xkb::import(&symbols),
+        Err(err) => Err(err.to_string()),
+    };
+    let layout = match layout {
+        Ok(layout) => layout,
+        Err(err) => {
+            eprintln!("Error: cannot import {}: {}", file.display(), err);
+            return ExitCode::FAILURE;
+        }
+    };
+    match format {
+        Format::Json => println!("{}", schema::to_json(&layout)),
+        Format::Toml => print!("{}", schema::to_toml(&layout)),
+        Format::Xkb | Format::Klc => {
+            eprintln!("Error: layouts are imported as json or toml");
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+fn lint(file: &Path, strict: bool) -> ExitCode {
+    let layout = match schema::load(file) {
+        Ok(layout) => layout,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            return ExitCode::from(2);
+        }
+    };
+
+    let issues = validate::validate(&layout);
+    for issue in &issues {
+        let label = match issue.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        println!("{}: {}: {}", file.display(), label, issue.message);
+    }
+
+    let errors = issues
+        .iter()
+        .filter(|issue| issue.severity == Severity::Error)
+        .count();
+    let warnings = issues.len() - errors;
+    println!(
+        "{}: {} error(s), {} warning(s)",
+        layout.name, errors, warnings
+    );
+
+    if errors > 0 || (strict && warnings > 0) {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+fn layout_diff(from: &str, to: &str, keyboard: bool) -> ExitCode {
+    let (Some(from), Some(to)) = (parse_layout(from), parse_layout(to)) else {
+        return ExitCode::FAILURE;
+    };
+
+    let changes = diff::diff(from, to);
+    let width = from.as_str().len().max(3) + 2;
+    println!("{:<5}{:<width$}{}", "key", from.as_str(), to.as_str());
+    for change in &changes {
+        println!("{:<5}{:<width$}{}", change.key, change.from, change.to);
+    }
+    println!("{} key(s) differ", changes.len());
+
+    if keyboard {
+        // Mark a key if either of its shift levels changed
+        let changed: HashSet<char> = changes.iter().map(|change| change.key).collect();
+        let marked = |key: char| {
+            changed.contains(&key)
+                || layouts::shift_pairs()
+                    .any(|(lower, upper)| lower == key && changed.contains(&upper))
+        };
+        println!();
+        println!("{}", visualize::ascii_keyboard(to, marked));
+    }
+    ExitCode::SUCCESS
+}
+
+fn drill(
+    layout: &str,
+    from: &str,
+    focus: FocusArg,
+    lang: Option<&str>,
+    lines: usize,
+    seed: u64,
+) -> ExitCode {
+    let (Some(layout), Some(from)) = (parse_layout(layout), parse_layout(from)) else {
+        return ExitCode::FAILURE;
+    };
+    let language = match lang {
+        Some(lang) => Language::from_str(lang).ok(),
+        None => Language::of(layout),
+    };
+    let Some(language) = language else {
+        eprintln!(
+            "Error: no language given for {}, use --lang",
+            layout.as_str()
+        );
+        return ExitCode::FAILURE;
+    };
+    let focus = match focus {
+        FocusArg::All => DrillFocus::All,
+        FocusArg::NumberRow => DrillFocus::NumberRow,
+        FocusArg::TopRow => DrillFocus::TopRow,
+        FocusArg::HomeRow => DrillFocus::HomeRow,
+        FocusArg::BottomRow => DrillFocus::BottomRow,
+    };
+    match drills::drill(layout, from, focus, language, lines, seed) {
+        Ok(drill) => {
+            for line in drill.lines {
+                println!("{}", line);
+            }
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn render(
+    layout: &str,
+    format: ImageFormatArg,
+    overlay: OverlayArg,
+    from: &str,
+    text: Option<&Path>,
+    lang: Option<&str>,
+    heat_scale: HeatScaleArg,
+    scale: f32,
+    output: Option<&Path>,
+) -> ExitCode {
+    let Some(layout) = parse_layout(layout) else {
+        return ExitCode::FAILURE;
+    };
+    let heat_scale = match heat_scale {
+        HeatScaleArg::Linear => HeatScale::Linear,
+        HeatScaleArg::Log => HeatScale::Log,
+    };
+    let overlay = match overlay {
+        OverlayArg::None => Overlay::None,
+        OverlayArg::Fingers => Overlay::Fingers,
+        OverlayArg::Diff => match parse_layout(from) {
+            Some(from) => Overlay::Diff(from),
+            None => return ExitCode::FAILURE,
+        },
+        OverlayArg::Heatmap => match text {
+            Some(path) => match std::fs::read_to_string(path) {
+                Ok(text) => Overlay::Heatmap(visualize::key_presses(&text, layout), heat_scale),
+                Err(err) => {
+                    eprintln!("Error: cannot read {}: {}", path.display(), err);
+                    return ExitCode::FAILURE;
+                }
+            },
+            None => {
+                let language = match lang {
+                    Some(lang) => Language::from_str(lang).ok(),
+                    None => Language::of(layout),
+                };
+                let Some(language) = language else {
+                    eprintln!("Error: a heatmap needs --text or --lang");
+                    return ExitCode::FAILURE;
+                };
+                Overlay::Heatmap(visualize::letter_presses(language, layout), heat_scale)
+            }
+        },
+    };
+
+    let svg = visualize::svg_keyboard(layout, &overlay);
+    let image = match format {
+        ImageFormatArg::Svg => svg.into_bytes(),
+        #[cfg(feature = "png")]
+        ImageFormatArg::Png => match visualize::png(&svg, scale) {
+            Ok(png) => png,
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                return ExitCode::FAILURE;
+            }
+        },
+        #[cfg(not(feature = "png"))]
+        ImageFormatArg::Png => {
+            let _ = scale;
+            eprintln!("Error: PNG images need keymorph built with the png feature");
+            return ExitCode::FAILURE;
+        }
+    };
+    let written = match output {
+        Some(path) => std::fs::write(path, image),
+        None => std::io::stdout().write_all(&image),
+    };
+    if let Err(err) = written {
+        eprintln!("Error: {}", err);
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn keymap(
+    layout: &str,
+    board: BoardArg,
+    firmware: FirmwareArg,
+    host: &str,
+    json: bool,
+    config: &Config,
+) -> ExitCode {
+    let exporter: &dyn Exporter = match (firmware, json) {
+        (FirmwareArg::Qmk, false) => &Qmk::KeymapC,
+        (FirmwareArg::Qmk, true) => &Qmk::KeymapJson,
+        (FirmwareArg::Zmk, false) => &Zmk,
+        (FirmwareArg::Kmonad, false) => &Remapper::Kmonad,
+        (FirmwareArg::Kanata, false) => &Remapper::Kanata,
+        (_, true) => {
+            eprintln!("Error: --json is only for QMK keymaps");
+            return ExitCode::FAILURE;
+        }
+    };
+    let board = match board {
+        BoardArg::Ansi60 => Board::Ansi60,
+        BoardArg::Planck => Board::Planck,
+        BoardArg::Corne => Board::Corne,
+        BoardArg::Glove80 => Board::Glove80,
+    };
+    if !exporter.supports(board) {
+        eprintln!(
+            "Error: {} has no keymaps for {}",
+            exporter.name(),
+            board.as_str()
+        );
+        return ExitCode::FAILURE;
+    }
+    let Some(layout) = resolve_layout(layout, config) else {
+        return ExitCode::FAILURE;
+    };
+    let Some(host) = parse_layout(host) else {
+        return ExitCode::FAILURE;
+    };
+    let keymap = firmware::keymap(&layout, board, host);
+    for problem in &keymap.problems {
+        eprintln!("Warning: {}", problem);
+    }
+    print!("{}", exporter.export(&layout, &keymap));
+    ExitCode::SUCCESS
+}
+
+pub fn analyze(
+    file: &Path,
+    layouts: Option<&[String]>,
+    json: bool,
+    tables: &AnalyzeTables,
+    config: &Config,
+) -> ExitCode {
+    let Some(codes) = layouts
+        .unwrap_or(&config.cli.analyze_layouts)
+        .iter()
+        .map(|layout| parse_layout(layout))
+        .collect::<Option<Vec<_>>>()
+    else {
+        return ExitCode::FAILURE;
+    };
+
+    let text = match read_input(file) {
+        Ok(text) => text,
+        Err(code) => return code,
+    };
+
+    let reports: Vec<analyze::Report> = codes
+        .iter()
+        .map(|&code| analyze::analyze(&text, code))
+        .collect();
+
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&reports).expect("reports always serialize to JSON")
+        );
+        return ExitCode::SUCCESS;
+    }
+
+    println!(
+        "{:<10}{:>12}{:>10}{:>14}{:>10}{:>12}",
+        "layout", "keystrokes", "untyped", "travel/key", "sfb %", "home row %"
+    );
+    for report in &reports {
+        let per_key = if report.keystrokes == 0 {
+            0.0
+        } else {
+            report.finger_travel / report.keystrokes as f64
+        };
+        println!(
+            "{:<10}{:>12}{:>10}{:>14.3}{:>10.2}{:>12.2}",
+            report.layout,
+            report.keystrokes,
+            report.untyped,
+            per_key,
+            report.same_finger_bigrams,
+            report.home_row
+        );
+    }
+    if tables.fingers {
+        println!(
+            "\n{:<10}{:>6}{:>6}{:>6}{:>6}{:>6}{:>6}{:>6}{:>6}{:>8}{:>8}",
+            "load %", "LP", "LR", "LM", "LI", "RI", "RM", "RR", "RP", "left", "right"
+        );
+        for report in &reports {
+            let loads: String = report
+                .finger_load
+                .iter()
+                .map(|finger| format!("{:>6.1}", finger.load))
+                .collect();
+            println!(
+                "{:<10}{}{:>8.1}{:>8.1}",
+                report.layout, loads, report.left_hand, report.right_hand
+            );
+        }
+    }
+    if tables.bigrams {
+        println!(
+            "\n{:<10}{:>8}{:>8}{:>12}{:>12}{:>12}",
+            "bigrams", "sfb %", "lsb %", "inward %", "outward %", "redirect %"
+        );
+        for report in &reports {
+            println!(
+                "{:<10}{:>8.2}{:>8.2}{:>12.2}{:>12.2}{:>12.2}",
+                report.layout,
+                report.same_finger_bigrams,
+                report.lateral_stretches,
+                report.inward_rolls,
+                report.outward_rolls,
+                report.redirects
+            );
+        }
+    }
+    if tables.rows {
+        println!(
+            "\n{:<10}{:>10}{:>10}{:>10}{:>10}",
+            "rows %", "number", "top", "home", "bottom"
+        );
+        for report in &reports {
+            let usage: String = report
+                .row_usage
+                .iter()
+                .map(|row| format!("{:>10.2}", row.usage))
+                .collect();
+            println!("{:<10}{}", report.layout, usage);
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+pub fn shortcut(shortcuts: &[String], from: &str, to: &str, by: SemanticsArg) -> ExitCode {
+    let (Some(from), Some(to)) = (parse_layout(from), parse_layout(to)) else {
+        return ExitCode::FAILURE;
+    };
+    let semantics = match by {
+        SemanticsArg::Character => KeySemantics::Character,
+        SemanticsArg::Position => KeySemantics::Position,
+    };
+    for shortcut in shortcuts {
+        println!(
+            "{}",
+            shortcuts::remap_shortcut(shortcut, from, to, semantics)
+        );
+    }
+    ExitCode::SUCCESS
+}
+
+pub fn remap_vim(from: &str, to: &str, output: &RewriteArgs) -> ExitCode {
+    let (Some(from), Some(to)) = (parse_layout(from), parse_layout(to)) else {
+        return ExitCode::FAILURE;
+    };
+    rewrite(output, |vimrc| vim::remap_vimrc(vimrc, from, to))
+}
+
+pub fn remap_emacs(from: &str, to: &str, output: &RewriteArgs) -> ExitCode {
+    let (Some(from), Some(to)) = (parse_layout(from), parse_layout(to)) else {
+        return ExitCode::FAILURE;
+    };
+    rewrite(output, |source| emacs::remap_elisp(source, from, to))
+}
+
+pub fn remap_profile(
+    from: &str,
+    to: &str,
+    format: Option<ProfileFormatArg>,
+    fields: &[String],
+    output: &RewriteArgs,
+) -> ExitCode {
+    let (Some(from), Some(to)) = (parse_layout(from), parse_layout(to)) else {
+        return ExitCode::FAILURE;
+    };
+    let format = match format {
+        Some(ProfileFormatArg::Json) => ProfileFormat::Json,
+        Some(ProfileFormatArg::Yaml) => ProfileFormat::Yaml,
+        None => ProfileFormat::of(&output.file).unwrap_or_default(),
+    };
+    let fields: Vec<&str> = if fields.is_empty() {
+        profiles::DEFAULT_FIELDS.to_vec()
+    } else {
+        fields.iter().map(String::as_str).collect()
+    };
+    try_rewrite(output, |profile| {
+        profiles::remap_profile(profile, format, from, to, &fields)
+    })
+}
+
+pub fn keys(command: KeysCommand) -> ExitCode {
+    let (KeysCommand::Type { layout, file } | KeysCommand::Events { layout, file }) = &command;
+    let Some(layout) = parse_layout(layout) else {
+        return ExitCode::FAILURE;
+    };
+    let input = match read_input(file) {
+        Ok(input) => input,
+        Err(code) => return code,
+    };
+
+    let result = match command {
+        KeysCommand::Type { .. } => parse_key_events(&input)
+            .and_then(|events| keys::type_keys(&events, layout))
+            .map(|text| print!("{}", text)),
+        KeysCommand::Events { .. } => keys::key_events(&input, layout).map(|events| {
+            for event in events {
+                println!(
+                    "{}",
+                    serde_json::to_string(&event).expect("key events always serialize to JSON")
+                );
+            }
+        }),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// Key events as one JSON object per line, or as a single JSON array
+fn parse_key_events(input: &str) -> Result<Vec<KeyEvent>, String> {
+    if input.trim_start().starts_with('[') {
+        return serde_json::from_str(input)
+            .map_err(|err| format!("invalid array of key events: {}", err));
+    }
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|err| {
+                format!(
+                    "line {}: invalid key event, expected one JSON object per line: {}",
+                    index + 1,
+                    err
+                )
+            })
+        })
+        .collect()
+}
+
+#[cfg(all(feature = "evdev", target_os = "linux"))]
+pub fn daemon(list_devices: bool, config: &Config) -> ExitCode {
+    use keymorph::{daemon, remap};
+
+    if list_devices {
+        for (path, name) in daemon::keyboards() {
+            println!("{}\t{}", path.display(), name);
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    let Some(layout) = &config.daemon.layout else {
+        eprintln!("Error: no layout to remap to, pass --layout or set daemon.layout");
+        return ExitCode::FAILURE;
+    };
+    let (Some(layout), Some(system)) = (
+        parse_layout(layout),
+        parse_layout(&config.daemon.system_layout),
+    ) else {
+        return ExitCode::FAILURE;
+    };
+    let Ok(panic_key) = evdev::Key::from_str(&config.daemon.panic_key) else {
+        eprintln!("Error: unknown key '{}'", config.daemon.panic_key);
+        return ExitCode::FAILURE;
+    };
+
+    let (path, device) = match daemon::open(config.daemon.device.as_deref()) {
+        Ok(device) => device,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+    log::info!(
+        "typing {} on {} via {}",
+        layout.as_str(),
+        system.as_str(),
+        path.display()
+    );
+
+    let codes = daemon::key_codes(&remap::key_remap(layout, system));
+    match daemon::run(device, &codes, panic_key) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(any(
+    all(feature = "winhook", windows),
+    all(feature = "mactap", target_os = "macos")
+))]
+pub fn hook(config: &Config) -> ExitCode {
+    let Some(codes) = config
+        .daemon
+        .layouts
+        .iter()
+        .map(|layout| parse_layout(layout))
+        .collect::<Option<Vec<_>>>()
+    else {
+        return ExitCode::FAILURE;
+    };
+
+    #[cfg(windows)]
+    let (run, target) = (keymorph::winhook::run, "the last word");
+    #[cfg(target_os = "macos")]
+    let (run, target) = (keymorph::mactap::run, "the selection");
+
+    log::info!("press {} to fix {}", config.daemon.hotkey, target);
+    match run(codes, &config.daemon.hotkey) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(all(feature = "ibus", target_os = "linux"))]
+pub fn ime(layouts: Option<&[String]>, xml: bool, config: &Config) -> ExitCode {
+    use keymorph::ibus;
+
+    if xml {
+        let exec = std::env::current_exe()
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|_| "keymorph".to_string());
+        print!("{}", ibus::component_xml(&exec));
+        return ExitCode::SUCCESS;
+    }
+
+    let Some(codes) = layouts
+        .unwrap_or(&config.daemon.layouts)
+        .iter()
+        .map(|layout| parse_layout(layout))
+        .collect::<Option<Vec<_>>>()
+    else {
+        return ExitCode::FAILURE;
+    };
+    match ibus::run(codes) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn selection(
+    layouts: Option<&[String]>,
+    clipboard: bool,
+    watch: bool,
+    config: &Config,
+) -> ExitCode {
+    use keymorph::selection::{self, Source};
+
+    let Some(codes) = layouts
+        .unwrap_or(&config.daemon.layouts)
+        .iter()
+        .map(|layout| parse_layout(layout))
+        .collect::<Option<Vec<_>>>()
+    else {
+        return ExitCode::FAILURE;
+    };
+    let source = if clipboard {
+        Source::Clipboard
+    } else {
+        Source::Primary
+    };
+    let fix = || match selection::fix_selection(source, &codes) {
+        Ok(Some(fixed)) => {
+            log::info!("replaced the selection with '{}'", fixed);
+            ExitCode::SUCCESS
+        }
+        Ok(None) => {
+            log::info!("nothing to fix");
+            ExitCode::SUCCESS
+        }
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            ExitCode::FAILURE
+        }
+    };
+
+    if !watch {
+        return fix();
+    }
+
+    #[cfg(feature = "evdev")]
+    {
+        use keymorph::daemon;
+
+        let Ok(hotkey) = evdev::Key::from_str(&config.daemon.hotkey) else {
+            eprintln!("Error: unknown key '{}'", config.daemon.hotkey);
+            return ExitCode::FAILURE;
+        };
+        let (path, mut device) = match daemon::open(config.daemon.device.as_deref()) {
+            Ok(device) => device,
+            Err(err) => {
+                eprintln!("Error: {}", err);
+                return ExitCode::FAILURE;
+            }
+        };
+        log::info!(
+            "press {} on {} to fix the selection",
+            config.daemon.hotkey,
+            path.display()
+        );
+        loop {
+            if let Err(err) = daemon::wait_for_key(&mut device, hotkey) {
+                eprintln!("Error: {}", err);
+                return ExitCode::FAILURE;
+            }
+            fix();
+        }
+    }
+    #[cfg(not(feature = "evdev"))]
+    unreachable!("--watch needs the evdev feature")
+}
+
+fn read_input(file: &Path) -> Result<String, ExitCode> {
+    let text = if file == Path::new("-") {
+        std::io::read_to_string(std::io::stdin())
+    } else {
+        std::fs::read_to_string(file)
+    };
+    text.map_err(|err| {
+        eprintln!("Error: cannot read {}: {}", file.display(), err);
+        ExitCode::from(2)
+    })
+}
+
+pub fn convert(
+    from: &str,
+    to: &str,
+    options: &ConvertArgs,
+    output: &RewriteArgs,
+    config: &Config,
+) -> ExitCode {
+    let (Some((from, from_options)), Some((to, to_options))) = (
+        resolve_layout_id(from, config),
+        resolve_layout_id(to, config),
+    ) else {
+        return ExitCode::FAILURE;
+    };
+    let layout_options = to_options.or(from_options);
+    let (&registry::Layout::Builtin(from), &registry::Layout::Builtin(to)) = (&from, &to) else {
+        return convert_custom(&from, &to, layout_options, options, output);
+    };
+    let converter = Converter::builder()
+        .from(from)
+        .to(to)
+        .yo_policy(options.yo_policy())
+        .strict(options.strict)
+        .skip_urls(options.skip_urls)
+        .digit_symbols(options.digit_symbols)
+        .keep_punctuation(options.keep_punctuation)
+        .case_policy(options.case_policy())
+        .options(layout_options)
+        .build();
+    let converter = match converter {
+        Ok(converter) => converter,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // Files written to another file or back to themselves are converted
+    // without reading them into memory. Standard input has no file to write
+    // back to, which `rewrite` reports.
+    let out_path = match &options.output_file {
+        Some(path) => path,
+        None if output.in_place
+            && output.file != Path::new("-")
+            && !output.diff
+            && !output.dry_run =>
+        {
+            &output.file
+        }
+        None => return rewrite(output, |text| converter.convert(text)),
+    };
+    let converted = if output.file == Path::new("-") {
+        match read_input(&output.file) {
+            Ok(text) => std::fs::write(out_path, converter.convert(&text))
+                .map_err(|err| format!("cannot write {}: {}", out_path.display(), err)),
+            Err(code) => return code,
+        }
+    } else {
+        files::convert_file_with(&converter, &output.file, out_path)
+    };
+    match converted {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// `convert` for custom layouts, which have no streaming converter
+fn convert_custom(
+    from: &registry::Layout,
+    to: &registry::Layout,
+    layout_options: LayoutOptions,
+    options: &ConvertArgs,
+    output: &RewriteArgs,
+) -> ExitCode {
+    if options.strict || options.skip_urls || options.digit_symbols {
+        eprintln!(
+            "Error: --strict, --skip-urls and --digit-symbols only apply to built-in layouts"
+        );
+        return ExitCode::from(2);
+    }
+    let convert_options = registry::ConvertOptions {
+        keep_punctuation: options.keep_punctuation,
+        yo_policy: options.yo_policy(),
+        digit_symbols: false,
+        case_policy: options.case_policy(),
+    }
+    .with(layout_options);
+    let convert = |text: &str| registry::convert_text_with(text, from, to, &convert_options);
+
+    let Some(out_path) = &options.output_file else {
+        return rewrite(output, convert);
+    };
+    let written = match read_input(&output.file) {
+        Ok(text) => std::fs::write(out_path, convert(&text)),
+        Err(code) => return code,
+    };
+    match written {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: cannot write {}: {}", out_path.display(), err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+pub fn fix(
+    layouts: &[String],
+    backend: Option<BackendArg>,
+    by_segment: Option<f64>,
+    output: &RewriteArgs,
+    config: &Config,
+) -> ExitCode {
+    if let Some(backend) = backend {
+        detect::set_backend(match backend {
+            BackendArg::Dictionary => detect::Backend::Dictionary,
+            BackendArg::Trigram => detect::Backend::Trigram,
+            BackendArg::Blend => detect::Backend::Blend,
+        });
+    }
+    let Some(codes) = layouts
+        .iter()
+        .map(|layout| match resolve_layout(layout, config)? {
+            registry::Layout::Builtin(code) => Some(code),
+            // Detection scores words against the languages of built-in layouts
+            registry::Layout::Custom(_) => {
+                eprintln!("Error: cannot detect custom layout '{}'", layout);
+                None
+            }
+        })
+        .collect::<Option<Vec<_>>>()
+    else {
+        return ExitCode::FAILURE;
+    };
+    match by_segment {
+        Some(min_confidence) if !(0.0..=1.0).contains(&min_confidence) => {
+            eprintln!("Error: --min-confidence must be between 0 and 1");
+            ExitCode::from(2)
+        }
+        Some(min_confidence) => rewrite(output, |text| {
+            document::fix(text, &codes, min_confidence).text
+        }),
+        None => rewrite(output, |text| detect::fix_text(text, &codes)),
+    }
+}
+
+// Reads the input, rewrites it and prints, previews or saves the result
+fn rewrite(args: &RewriteArgs, transform: impl Fn(&str) -> String) -> ExitCode {
+    try_rewrite(args, |text| Ok(transform(text)))
+}
+
+// `rewrite` for transforms that can fail, like those parsing their input
+fn try_rewrite(args: &RewriteArgs, transform: impl Fn(&str) -> Result<String, String>) -> ExitCode {
+    if args.in_place && args.file == Path::new("-") {
+        eprintln!("Error: --in-place needs an input file");
+        return ExitCode::from(2);
+    }
+
+    let original = match read_input(&args.file) {
+        Ok(text) => text,
+        Err(code) => return code,
+    };
+    let converted = match transform(&original) {
+        Ok(converted) => converted,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if args.diff || args.dry_run {
+        let color = match args.color {
+            Color::Auto => std::io::stdout().is_terminal(),
+            Color::Always => true,
+            Color::Never => false,
+        };
+        let edits = edits::word_edits(&original, &converted);
+        print_diff(&original, &edits, color);
+    } else if !args.in_place {
+        print!("{}", converted);
+    }
+
+    if args.in_place && !args.dry_run && converted != original {
+        if let Err(err) = std::fs::write(&args.file, &converted) {
+            eprintln!("Error: cannot write {}: {}", args.file.display(), err);
+            return ExitCode::FAILURE;
+        }
+    }
+    ExitCode::SUCCESS
+}
+
+// Prints the text with changed words marked, followed by their positions
+fn print_diff(original: &str, edits: &[Edit], color: bool) {
+    let (removed, added, reset) = if color {
+        ("\x1b[31;9m", "\x1b[32m", "\x1b[0m")
+    } else {
+        ("[-", "{+", "")
+    };
+    let (removed_end, added_end) = if color { (reset, reset) } else { ("-]", "+}") };
+
+    let chars: Vec<char> = original.chars().collect();
+    let mut text = String::new();
+    let mut position = 0;
+    for edit in edits {
+        text.extend(&chars[position..edit.start]);
+        text.push_str(&format!(
+            "{}{}{}{}{}{}",
+            removed, edit.original, removed_end, added, edit.replacement, added_end
+        ));
+        position = edit.end;
+    }
+    text.extend(&chars[position..]);
+    print!("{}", text);
+    if !text.ends_with('\n') {
+        println!();
+    }
+
+    println!();
+    for edit in edits {
+        println!(
+            "{}:{} {} -> {}",
+            edit.line, edit.column, edit.original, edit.replacement
+        );
+    }
+    println!("{} word(s) changed", edits.len());
+}
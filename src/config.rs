@@ -0,0 +1,310 @@
+// Settings shared by the server and the CLI.
+//
+// Values are taken from, in increasing order of precedence: built-in
+// defaults, the configuration file, `KEYMORPH_*` environment variables and
+// finally command-line flags (applied by the CLI itself). The configuration
+// file is the one given with `--config`, else `$KEYMORPH_CONFIG`, else
+// `./keymorph.toml` or `$XDG_CONFIG_HOME/keymorph/keymorph.toml` if present.
+use crate::detect::Backend;
+use crate::layouts::Parallelism;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub auth: AuthConfig,
+    pub limits: LimitsConfig,
+    pub cache: CacheConfig,
+    pub conversion: ConversionConfig,
+    pub detection: DetectionConfig,
+    pub layouts: LayoutsConfig,
+    pub logging: LoggingConfig,
+    pub cli: CliConfig,
+    pub daemon: DaemonConfig,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    // Number of worker threads, defaults to one per CPU core
+    pub workers: Option<usize>,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 8000,
+            workers: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    // When set, API requests need a `Bearer` JWT signed with this secret (HS256)
+    pub jwt_secret: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    // Largest accepted request body, in bytes
+    pub max_payload_bytes: usize,
+    // Longest text accepted for conversion, in characters
+    pub max_text_chars: usize,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        LimitsConfig {
+            max_payload_bytes: 4 * 1024 * 1024,
+            max_text_chars: 1_000_000,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    // Number of conversion results kept in memory, 0 disables the cache
+    pub capacity: usize,
+    // Texts longer than this many characters are never cached
+    pub max_text_chars: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            capacity: 1024,
+            max_text_chars: 4096,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConversionConfig {
+    // Texts longer than this many bytes are converted on several threads
+    pub parallel_threshold: Option<usize>,
+    // Characters per thread, defaults to an even split between the threads
+    pub chunk_size: Option<usize>,
+    // Defaults to one thread per CPU core
+    pub max_threads: Option<usize>,
+}
+
+impl ConversionConfig {
+    // The parallelism policy, with unset values auto-tuned
+    pub fn parallelism(&self) -> Parallelism {
+        let defaults = Parallelism::default();
+        Parallelism {
+            threshold: self.parallel_threshold.unwrap_or(defaults.threshold),
+            chunk_size: self.chunk_size.or(defaults.chunk_size),
+            max_threads: self.max_threads.unwrap_or(defaults.max_threads),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct DetectionConfig {
+    // How the fixer and detection score words
+    pub backend: Backend,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct LayoutsConfig {
+    // Directories of custom layout files
+    pub dirs: Vec<PathBuf>,
+    // Reload layout files when they change
+    pub watch: bool,
+}
+
+impl Default for LayoutsConfig {
+    fn default() -> Self {
+        LayoutsConfig {
+            dirs: Vec::new(),
+            watch: true,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    // env_logger filter, overridden by `RUST_LOG`
+    pub filter: String,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig {
+            filter: "actix_web=info,keymorph=info".to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct CliConfig {
+    // Layouts compared by `keymorph analyze` when `--layouts` is not given
+    pub analyze_layouts: Vec<String>,
+    // Format used by `keymorph layouts export` when `--format` is not given
+    pub export_format: String,
+}
+
+impl Default for CliConfig {
+    fn default() -> Self {
+        CliConfig {
+            analyze_layouts: vec![
+                "qwerty".to_string(),
+                "dvorak".to_string(),
+                "colemak".to_string(),
+            ],
+            export_format: "json".to_string(),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct DaemonConfig {
+    // Input device for `keymorph daemon`: a /dev/input path or part of the
+    // device name, defaults to the first keyboard found
+    pub device: Option<String>,
+    // Layout the user types in
+    pub layout: Option<String>,
+    // Layout the system is configured for
+    pub system_layout: String,
+    // evdev name of the key that stops remapping
+    pub panic_key: String,
+    // Layouts the word-fixing modes convert between
+    pub layouts: Vec<String>,
+    // Key that retypes the last word in the layout it was meant for, named
+    // like `panic_key`
+    pub hotkey: String,
+}
+
+impl Default for DaemonConfig {
+    fn default() -> Self {
+        DaemonConfig {
+            device: None,
+            layout: None,
+            system_layout: "qwerty".to_string(),
+            panic_key: "KEY_PAUSE".to_string(),
+            layouts: vec!["qwerty".to_string(), "russian".to_string()],
+            hotkey: "KEY_PAUSE".to_string(),
+        }
+    }
+}
+
+fn default_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("KEYMORPH_CONFIG") {
+        return Some(PathBuf::from(path));
+    }
+
+    let local = PathBuf::from("keymorph.toml");
+    if local.exists() {
+        return Some(local);
+    }
+
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    let user = config_home.join("keymorph").join("keymorph.toml");
+    user.exists().then_some(user)
+}
+
+fn env_var<T: FromStr>(name: &str) -> Result<Option<T>, String> {
+    match std::env::var(name) {
+        Ok(value) => value
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("invalid value '{}' for {}", value, name)),
+        Err(_) => Ok(None),
+    }
+}
+
+fn env_list(name: &str) -> Option<Vec<String>> {
+    let value = std::env::var(name).ok()?;
+    Some(
+        value
+            .split(',')
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect(),
+    )
+}
+
+impl Config {
+    // Reads the configuration file (if any) and applies the environment
+    // overlay. An explicitly given file must exist.
+    pub fn load(path: Option<&Path>) -> Result<Config, String> {
+        let path = path.map(Path::to_path_buf).or_else(default_path);
+
+        let mut config = match path {
+            Some(path) => {
+                let text = std::fs::read_to_string(&path)
+                    .map_err(|err| format!("cannot read {}: {}", path.display(), err))?;
+                toml::from_str(&text)
+                    .map_err(|err| format!("cannot parse {}: {}", path.display(), err))?
+            }
+            None => Config::default(),
+        };
+        config.apply_env()?;
+        Ok(config)
+    }
+
+    fn apply_env(&mut self) -> Result<(), String> {
+        if let Some(host) = env_var("KEYMORPH_HOST")? {
+            self.server.host = host;
+        }
+        if let Some(port) = env_var("KEYMORPH_PORT")? {
+            self.server.port = port;
+        }
+        if let Some(workers) = env_var("KEYMORPH_WORKERS")? {
+            self.server.workers = Some(workers);
+        }
+        if let Some(secret) = env_var("KEYMORPH_JWT_SECRET")? {
+            self.auth.jwt_secret = Some(secret);
+        }
+        if let Some(bytes) = env_var("KEYMORPH_MAX_PAYLOAD_BYTES")? {
+            self.limits.max_payload_bytes = bytes;
+        }
+        if let Some(chars) = env_var("KEYMORPH_MAX_TEXT_CHARS")? {
+            self.limits.max_text_chars = chars;
+        }
+        if let Some(capacity) = env_var("KEYMORPH_CACHE_CAPACITY")? {
+            self.cache.capacity = capacity;
+        }
+        if let Some(threshold) = env_var("KEYMORPH_PARALLEL_THRESHOLD")? {
+            self.conversion.parallel_threshold = Some(threshold);
+        }
+        if let Some(chunk_size) = env_var("KEYMORPH_CHUNK_SIZE")? {
+            self.conversion.chunk_size = Some(chunk_size);
+        }
+        if let Some(threads) = env_var("KEYMORPH_MAX_THREADS")? {
+            self.conversion.max_threads = Some(threads);
+        }
+        if let Some(backend) = env_var("KEYMORPH_DETECTION_BACKEND")? {
+            self.detection.backend = backend;
+        }
+        if let Some(dirs) = env_list("KEYMORPH_LAYOUTS_DIRS") {
+            self.layouts.dirs = dirs.into_iter().map(PathBuf::from).collect();
+        }
+        if let Some(watch) = env_var("KEYMORPH_LAYOUTS_WATCH")? {
+            self.layouts.watch = watch;
+        }
+        if let Some(filter) = env_var("RUST_LOG")? {
+            self.logging.filter = filter;
+        }
+        Ok(())
+    }
+}
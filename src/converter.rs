@@ -0,0 +1,271 @@
+// A conversion between two built-in layouts with its options, resolved once
+// up front so that converting many texts does no lookups of its own:
+//
+//     let converter = Converter::builder()
+//         .from(LayoutCode::Qwerty)
+//         .to(LayoutCode::Russian)
+//         .skip_urls(true)
+//         .build()?;
+//     converter.convert("ghbdtn https://example.com");
+use crate::edits::{self, Edit};
+use crate::layouts::{
+    self, CasePolicy, LayoutCode, LayoutOptions, Parallelism, SentenceCase, YoPolicy,
+};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug)]
+pub struct Converter {
+    from: LayoutCode,
+    to: LayoutCode,
+    // `None` when both layouts are the same
+    map: Option<Cow<'static, HashMap<char, char>>>,
+    // Characters the source layout can type, for strict conversions
+    typeable: Option<HashSet<char>>,
+    skip_urls: bool,
+    yo_policy: YoPolicy,
+    // Whether sentences are capitalized, for `CasePolicy::Sentence`
+    capitalize: bool,
+    parallelism: Parallelism,
+}
+
+#[derive(Default, Debug, Clone)]
+pub struct ConverterBuilder {
+    from: Option<LayoutCode>,
+    to: Option<LayoutCode>,
+    strict: bool,
+    skip_urls: bool,
+    yo_policy: YoPolicy,
+    digit_symbols: bool,
+    keep_punctuation: bool,
+    case_policy: CasePolicy,
+    parallelism: Option<Parallelism>,
+}
+
+impl ConverterBuilder {
+    pub fn from(mut self, layout: LayoutCode) -> Self {
+        self.from = Some(layout);
+        self
+    }
+
+    pub fn to(mut self, layout: LayoutCode) -> Self {
+        self.to = Some(layout);
+        self
+    }
+
+    // Leaves words with characters the source layout cannot type as they
+    // are, rather than converting the rest of their characters
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    // Leaves URLs and email addresses as they are
+    pub fn skip_urls(mut self, skip_urls: bool) -> Self {
+        self.skip_urls = skip_urls;
+        self
+    }
+
+    // What happens to ё in the result, kept as typed unless set
+    pub fn yo_policy(mut self, yo_policy: YoPolicy) -> Self {
+        self.yo_policy = yo_policy;
+        self
+    }
+
+    // Converts the symbols of the shifted digit keys on layouts whose maps
+    // leave them out, like Russian's № for #
+    pub fn digit_symbols(mut self, digit_symbols: bool) -> Self {
+        self.digit_symbols = digit_symbols;
+        self
+    }
+
+    // Leaves punctuation and symbols as they are where they would become
+    // other ones, see `layouts::letters_only`
+    pub fn keep_punctuation(mut self, keep_punctuation: bool) -> Self {
+        self.keep_punctuation = keep_punctuation;
+        self
+    }
+
+    // How capitals convert to and from caseless layouts, as typed unless set
+    pub fn case_policy(mut self, case_policy: CasePolicy) -> Self {
+        self.case_policy = case_policy;
+        self
+    }
+
+    // Sets the options given as layout parameters, see `LayoutId`
+    pub fn options(mut self, options: LayoutOptions) -> Self {
+        self.yo_policy = options.yo_policy.unwrap_or(self.yo_policy);
+        self.digit_symbols = options.digit_symbols.unwrap_or(self.digit_symbols);
+        self.keep_punctuation = options.keep_punctuation.unwrap_or(self.keep_punctuation);
+        self.case_policy = options.case_policy.unwrap_or(self.case_policy);
+        self
+    }
+
+    // How long texts are split between threads, `layouts::parallelism()`
+    // unless set
+    pub fn parallelism(mut self, parallelism: Parallelism) -> Self {
+        self.parallelism = Some(parallelism);
+        self
+    }
+
+    pub fn build(self) -> Result<Converter, String> {
+        let from = self.from.ok_or("no layout to convert from")?;
+        let to = self.to.ok_or("no layout to convert to")?;
+        let mut map = if self.digit_symbols {
+            layouts::keymap_with_digit_symbols(from, to).map(Cow::Owned)
+        } else {
+            layouts::keymap(from, to).map(Cow::Borrowed)
+        };
+        if self.keep_punctuation {
+            map = map.map(|map| Cow::Owned(layouts::letters_only(&map, from, to)));
+        }
+        if self.case_policy == CasePolicy::KeepCapitals {
+            map = map.map(|map| Cow::Owned(layouts::keep_capitals(&map)));
+        }
+        if map.is_none() && from != to {
+            return Err(format!(
+                "no conversion map found for {:?} to {:?}",
+                from, to
+            ));
+        }
+        let typeable = self.strict.then(|| {
+            let digit_symbols = layouts::digit_symbol_chars(from).filter(|_| self.digit_symbols);
+            layouts::typeable_chars(from).chain(digit_symbols).collect()
+        });
+        Ok(Converter {
+            from,
+            to,
+            map,
+            typeable,
+            skip_urls: self.skip_urls,
+            yo_policy: self.yo_policy,
+            capitalize: self.case_policy == CasePolicy::Sentence
+                && !layouts::is_cased(from)
+                && layouts::is_cased(to),
+            parallelism: self.parallelism.unwrap_or_else(layouts::parallelism),
+        })
+    }
+}
+
+impl Converter {
+    pub fn builder() -> ConverterBuilder {
+        ConverterBuilder::default()
+    }
+
+    pub fn from(&self) -> LayoutCode {
+        self.from
+    }
+
+    pub fn to(&self) -> LayoutCode {
+        self.to
+    }
+
+    pub fn parallelism(&self) -> Parallelism {
+        self.parallelism
+    }
+
+    pub fn convert(&self, text: &str) -> String {
+        let mut converted = String::with_capacity(text.len());
+        self.convert_to_buf(text, &mut converted);
+        converted
+    }
+
+    // The words of `text` the conversion changes, for applying it as edits
+    pub fn convert_spans(&self, text: &str) -> Vec<Edit> {
+        edits::word_edits(text, &self.convert(text))
+    }
+
+    // Appends the converted `text` to `out`
+    pub fn convert_to_buf(&self, text: &str, out: &mut String) {
+        self.convert_with(text, &self.parallelism, out);
+    }
+
+    // `convert` on the calling thread only, for callers that already split
+    // their work between threads
+    #[cfg(not(target_arch = "wasm32"))]
+    pub(crate) fn convert_serial(&self, text: &str) -> String {
+        let serial = Parallelism {
+            max_threads: 1,
+            ..self.parallelism
+        };
+        let mut converted = String::with_capacity(text.len());
+        self.convert_with(text, &serial, &mut converted);
+        converted
+    }
+
+    fn convert_with(&self, text: &str, parallelism: &Parallelism, out: &mut String) {
+        let mut sentences = self.capitalize.then(SentenceCase::default);
+        if self.typeable.is_none() && !self.skip_urls {
+            self.convert_run(text, parallelism, &mut sentences, out);
+            return;
+        }
+        // Text between the words that are kept is converted in one go, so
+        // that dead keys and composed letters are read as they were typed
+        let mut start = 0;
+        for word in text.split_whitespace() {
+            if !self.keeps(word) {
+                continue;
+            }
+            let offset = word.as_ptr() as usize - text.as_ptr() as usize;
+            self.convert_run(&text[start..offset], parallelism, &mut sentences, out);
+            if let Some(sentences) = &mut sentences {
+                sentences.skip(word);
+            }
+            out.push_str(word);
+            start = offset + word.len();
+        }
+        self.convert_run(&text[start..], parallelism, &mut sentences, out);
+    }
+
+    // Converts a run of text no word of which is kept, capitalizing its
+    // sentences if `sentences` is given
+    fn convert_run(
+        &self,
+        text: &str,
+        parallelism: &Parallelism,
+        sentences: &mut Option<SentenceCase>,
+        out: &mut String,
+    ) {
+        let converted;
+        let converted = match &self.map {
+            Some(map) => {
+                converted =
+                    layouts::parallel_convert_with_map(text, self.from, self.to, map, parallelism);
+                self.yo_policy.apply(&converted)
+            }
+            None => Cow::Borrowed(text),
+        };
+        match sentences {
+            Some(sentences) => sentences.push(&converted, out),
+            None => out.push_str(&converted),
+        }
+    }
+
+    // Whether `word` is left as it is
+    fn keeps(&self, word: &str) -> bool {
+        let untypeable = self
+            .typeable
+            .as_ref()
+            .is_some_and(|typeable| !word.chars().all(|c| typeable.contains(&c)));
+        untypeable || (self.skip_urls && is_url(word))
+    }
+}
+
+// Whether `word`, less any brackets, quotes or punctuation around it, is a
+// URL or an email address
+fn is_url(word: &str) -> bool {
+    let word = word.trim_matches(|c: char| "()[]<>\"',.;:!?".contains(c));
+    if word.contains("://") || word.starts_with("www.") {
+        return true;
+    }
+    match word.split_once('@') {
+        Some((user, domain)) => {
+            !user.is_empty()
+                && !domain.contains('@')
+                && domain
+                    .split_once('.')
+                    .is_some_and(|(host, tld)| !host.is_empty() && !tld.is_empty())
+        }
+        None => false,
+    }
+}
@@ -0,0 +1,204 @@
+// Live remapping of a keyboard through evdev/uinput: the physical device is
+// grabbed so the rest of the system no longer sees it, and every event is
+// re-emitted from a virtual keyboard with the layout keys swapped. Needs
+// read access to the device and write access to /dev/uinput (usually root or
+// the `input` group).
+use evdev::uinput::{VirtualDevice, VirtualDeviceBuilder};
+use evdev::{Device, EventType, InputEvent, Key};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+// evdev key codes of the keys in `layouts::QWERTY_ROWS`, by their unshifted
+// QWERTY character
+const KEY_CODES: [(char, Key); 47] = [
+    ('`', Key::KEY_GRAVE),
+    ('1', Key::KEY_1),
+    ('2', Key::KEY_2),
+    ('3', Key::KEY_3),
+    ('4', Key::KEY_4),
+    ('5', Key::KEY_5),
+    ('6', Key::KEY_6),
+    ('7', Key::KEY_7),
+    ('8', Key::KEY_8),
+    ('9', Key::KEY_9),
+    ('0', Key::KEY_0),
+    ('-', Key::KEY_MINUS),
+    ('=', Key::KEY_EQUAL),
+    ('q', Key::KEY_Q),
+    ('w', Key::KEY_W),
+    ('e', Key::KEY_E),
+    ('r', Key::KEY_R),
+    ('t', Key::KEY_T),
+    ('y', Key::KEY_Y),
+    ('u', Key::KEY_U),
+    ('i', Key::KEY_I),
+    ('o', Key::KEY_O),
+    ('p', Key::KEY_P),
+    ('[', Key::KEY_LEFTBRACE),
+    (']', Key::KEY_RIGHTBRACE),
+    ('\\', Key::KEY_BACKSLASH),
+    ('a', Key::KEY_A),
+    ('s', Key::KEY_S),
+    ('d', Key::KEY_D),
+    ('f', Key::KEY_F),
+    ('g', Key::KEY_G),
+    ('h', Key::KEY_H),
+    ('j', Key::KEY_J),
+    ('k', Key::KEY_K),
+    ('l', Key::KEY_L),
+    (';', Key::KEY_SEMICOLON),
+    ('\'', Key::KEY_APOSTROPHE),
+    ('z', Key::KEY_Z),
+    ('x', Key::KEY_X),
+    ('c', Key::KEY_C),
+    ('v', Key::KEY_V),
+    ('b', Key::KEY_B),
+    ('n', Key::KEY_N),
+    ('m', Key::KEY_M),
+    (',', Key::KEY_COMMA),
+    ('.', Key::KEY_DOT),
+    ('/', Key::KEY_SLASH),
+];
+
+// Turns a character-level key remap (see `remap::key_remap`) into one on
+// evdev key codes
+pub fn key_codes(remap: &HashMap<char, char>) -> HashMap<u16, u16> {
+    let codes: HashMap<char, Key> = KEY_CODES.into_iter().collect();
+    remap
+        .iter()
+        .filter_map(|(from, to)| Some((codes.get(from)?.code(), codes.get(to)?.code())))
+        .collect()
+}
+
+fn is_keyboard(device: &Device) -> bool {
+    device.supported_keys().is_some_and(|keys| {
+        keys.contains(Key::KEY_A) && keys.contains(Key::KEY_Z) && keys.contains(Key::KEY_ENTER)
+    })
+}
+
+// Input devices that look like keyboards, with their names
+pub fn keyboards() -> Vec<(PathBuf, String)> {
+    let mut keyboards: Vec<(PathBuf, String)> = evdev::enumerate()
+        .filter(|(_, device)| is_keyboard(device))
+        .map(|(path, device)| (path, device.name().unwrap_or("unnamed").to_string()))
+        .collect();
+    keyboards.sort();
+    keyboards
+}
+
+// Opens the device at `selector` if it is a path, else the first keyboard
+// whose name contains it (case-insensitively), else the first keyboard found
+pub fn open(selector: Option<&str>) -> Result<(PathBuf, Device), String> {
+    if let Some(path) = selector.filter(|selector| selector.starts_with('/')) {
+        let device = Device::open(path).map_err(|err| format!("cannot open {}: {}", path, err))?;
+        return Ok((PathBuf::from(path), device));
+    }
+
+    let needle = selector.map(str::to_lowercase);
+    let (path, _) = keyboards()
+        .into_iter()
+        .find(|(_, name)| {
+            needle
+                .as_ref()
+                .is_none_or(|needle| name.to_lowercase().contains(needle))
+        })
+        .ok_or_else(|| match selector {
+            Some(selector) => format!("no keyboard named like '{}'", selector),
+            None => "no keyboard found".to_string(),
+        })?;
+    let device =
+        Device::open(&path).map_err(|err| format!("cannot open {}: {}", path.display(), err))?;
+    Ok((path, device))
+}
+
+// Blocks until `key` is pressed on `device`, without grabbing it
+pub fn wait_for_key(device: &mut Device, key: Key) -> Result<(), String> {
+    loop {
+        let pressed = device
+            .fetch_events()
+            .map_err(|err| format!("cannot read events: {}", err))?
+            .any(|event| {
+                event.event_type() == EventType::KEY
+                    && event.code() == key.code()
+                    && event.value() == 1
+            });
+        if pressed {
+            return Ok(());
+        }
+    }
+}
+
+fn virtual_keyboard(device: &Device) -> std::io::Result<VirtualDevice> {
+    let mut builder = VirtualDeviceBuilder::new()?.name("keymorph virtual keyboard");
+    if let Some(keys) = device.supported_keys() {
+        builder = builder.with_keys(keys)?;
+    }
+    builder.build()
+}
+
+// Grabs `device` and re-emits its events with key codes swapped according to
+// `codes` until `panic_key` is pressed, which releases the device again
+pub fn run(mut device: Device, codes: &HashMap<u16, u16>, panic_key: Key) -> Result<(), String> {
+    let mut output = virtual_keyboard(&device)
+        .map_err(|err| format!("cannot create a uinput device: {}", err))?;
+    device
+        .grab()
+        .map_err(|err| format!("cannot grab the device: {}", err))?;
+    log::info!(
+        "remapping {} keys, press {:?} to stop",
+        codes.len(),
+        panic_key
+    );
+
+    // Keys are remapped on press, and the release goes to the same key even
+    // if that press happened before the remap started
+    let mut pressed: HashMap<u16, u16> = HashMap::new();
+    loop {
+        let events: Vec<InputEvent> = device
+            .fetch_events()
+            .map_err(|err| format!("cannot read events: {}", err))?
+            .collect();
+
+        let mut batch = Vec::with_capacity(events.len());
+        for event in events {
+            if event.event_type() == EventType::SYNCHRONIZATION {
+                continue;
+            }
+            if event.event_type() != EventType::KEY {
+                batch.push(event);
+                continue;
+            }
+            if event.code() == panic_key.code() {
+                log::info!("panic key pressed, releasing the device");
+                // Let go of anything still held so no key stays stuck
+                let releases: Vec<InputEvent> = pressed
+                    .values()
+                    .map(|&code| InputEvent::new(EventType::KEY, code, 0))
+                    .collect();
+                output.emit(&releases).ok();
+                device.ungrab().ok();
+                return Ok(());
+            }
+
+            let code = match event.value() {
+                // Press
+                1 => {
+                    let code = codes.get(&event.code()).copied().unwrap_or(event.code());
+                    pressed.insert(event.code(), code);
+                    code
+                }
+                // Release
+                0 => pressed.remove(&event.code()).unwrap_or(event.code()),
+                // Autorepeat
+                _ => pressed.get(&event.code()).copied().unwrap_or(event.code()),
+            };
+            batch.push(InputEvent::new(EventType::KEY, code, event.value()));
+        }
+
+        if !batch.is_empty() {
+            output
+                .emit(&batch)
+                .map_err(|err| format!("cannot write events: {}", err))?;
+        }
+    }
+}
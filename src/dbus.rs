@@ -0,0 +1,146 @@
+// Session bus service exposing the converter as `org.keymorph.Converter`, for
+// desktop applets and scripts that would rather not talk HTTP. Layouts are
+// named as in the HTTP API; custom layouts work for conversion, detection
+// and fixing use the built-in ones.
+//
+//   Convert(s text, s from, s to) -> s
+//   Detect(s text, as layouts) -> (b found, s from, s to, d confidence)
+//   Fix(s text, as layouts) -> s
+//   ListLayouts() -> as
+//   Reload()
+//   signal LayoutsChanged(t generation)
+//   signal ConfigReloaded()
+//
+// An empty layout list means the configured `daemon.layouts`.
+use crate::config::Config;
+use crate::detect;
+use crate::layouts::LayoutCode;
+use crate::registry;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+use zbus::fdo;
+use zbus::object_server::SignalContext;
+use zbus::{blocking, interface};
+
+pub const BUS_NAME: &str = "org.keymorph.Converter";
+pub const OBJECT_PATH: &str = "/org/keymorph/Converter";
+
+// How often the registry is checked for layout changes to signal
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+pub struct Converter {
+    config_path: Option<PathBuf>,
+    layouts: Vec<String>,
+}
+
+impl Converter {
+    pub fn new(config: &Config, config_path: Option<PathBuf>) -> Self {
+        Converter {
+            config_path,
+            layouts: config.daemon.layouts.clone(),
+        }
+    }
+
+    fn layout_codes(&self, layouts: Vec<String>) -> fdo::Result<Vec<LayoutCode>> {
+        let layouts = if layouts.is_empty() {
+            self.layouts.clone()
+        } else {
+            layouts
+        };
+        layouts
+            .iter()
+            .map(|layout| {
+                LayoutCode::from_str(layout)
+                    .map_err(|_| fdo::Error::InvalidArgs(format!("unknown layout '{}'", layout)))
+            })
+            .collect()
+    }
+}
+
+fn resolve(name: &str) -> fdo::Result<registry::Layout> {
+    registry::resolve(name)
+        .ok_or_else(|| fdo::Error::InvalidArgs(format!("unknown layout '{}'", name)))
+}
+
+#[interface(name = "org.keymorph.Converter")]
+impl Converter {
+    fn convert(&self, text: String, from: &str, to: &str) -> fdo::Result<String> {
+        let (from, to) = (resolve(from)?, resolve(to)?);
+        Ok(registry::convert_text(&text, &from, &to))
+    }
+
+    fn detect(&self, text: &str, layouts: Vec<String>) -> fdo::Result<(bool, String, String, f64)> {
+        let layouts = self.layout_codes(layouts)?;
+        Ok(match detect::detect(text, &layouts) {
+            Some(detection) => (
+                true,
+                detection.from.as_str().to_string(),
+                detection.to.as_str().to_string(),
+                detection.confidence,
+            ),
+            None => (false, String::new(), String::new(), 0.0),
+        })
+    }
+
+    fn fix(&self, text: &str, layouts: Vec<String>) -> fdo::Result<String> {
+        let layouts = self.layout_codes(layouts)?;
+        Ok(detect::fix_text(text, &layouts))
+    }
+
+    fn list_layouts(&self) -> Vec<String> {
+        let mut layouts: Vec<String> = LayoutCode::ALL
+            .iter()
+            .map(|layout| layout.as_str().to_string())
+            .collect();
+        layouts.extend(registry::names());
+        layouts
+    }
+
+    // Re-reads the configuration file and the layout directories it lists
+    async fn reload(&mut self, #[zbus(signal_context)] ctxt: SignalContext<'_>) -> fdo::Result<()> {
+        let config = Config::load(self.config_path.as_deref()).map_err(fdo::Error::Failed)?;
+        for dir in &config.layouts.dirs {
+            registry::load_dir(dir).map_err(|err| {
+                fdo::Error::Failed(format!("cannot read {}: {}", dir.display(), err))
+            })?;
+        }
+        self.layouts = config.daemon.layouts;
+        log::info!("configuration reloaded");
+        Converter::config_reloaded(&ctxt).await?;
+        Ok(())
+    }
+
+    #[zbus(signal)]
+    async fn layouts_changed(ctxt: &SignalContext<'_>, generation: u64) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn config_reloaded(ctxt: &SignalContext<'_>) -> zbus::Result<()>;
+}
+
+// Claims the bus name and serves requests until the process is stopped
+pub fn run(converter: Converter) -> Result<(), String> {
+    let connection = blocking::connection::Builder::session()
+        .and_then(|builder| builder.name(BUS_NAME))
+        .and_then(|builder| builder.serve_at(OBJECT_PATH, converter))
+        .and_then(|builder| builder.build())
+        .map_err(|err| format!("cannot register {} on the session bus: {}", BUS_NAME, err))?;
+    let interface = connection
+        .object_server()
+        .interface::<_, Converter>(OBJECT_PATH)
+        .map_err(|err| err.to_string())?;
+    log::info!("serving {} at {}", BUS_NAME, OBJECT_PATH);
+
+    let mut generation = registry::generation();
+    loop {
+        std::thread::sleep(POLL_INTERVAL);
+        let current = registry::generation();
+        if current != generation {
+            generation = current;
+            let signal = Converter::layouts_changed(interface.signal_context(), generation);
+            if let Err(err) = zbus::block_on(signal) {
+                log::error!("cannot signal a layout change: {}", err);
+            }
+        }
+    }
+}
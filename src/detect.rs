@@ -0,0 +1,427 @@
+use crate::layouts::{self, LayoutCode};
+use crate::trigram;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
+use std::sync::RwLock;
+
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+pub enum Language {
+    English,
+    Russian,
+}
+
+impl Language {
+    // Language usually typed on `layout`, if a dictionary exists for it
+    pub fn of(layout: LayoutCode) -> Option<Language> {
+        match layout {
+            LayoutCode::Qwerty
+            | LayoutCode::Dvorak
+            | LayoutCode::Colemak
+            | LayoutCode::Uk
+            | LayoutCode::ProgrammerDvorak
+            | LayoutCode::DvorakLeft
+            | LayoutCode::DvorakRight
+            | LayoutCode::ColemakDh
+            | LayoutCode::Norman
+            | LayoutCode::Halmak
+            | LayoutCode::Engram => Some(Language::English),
+            LayoutCode::Russian | LayoutCode::RussianTypewriter | LayoutCode::RussianMac => {
+                Some(Language::Russian)
+            }
+            LayoutCode::German
+            | LayoutCode::Azerty
+            | LayoutCode::Bepo
+            | LayoutCode::Spanish
+            | LayoutCode::Abnt2
+            | LayoutCode::Norwegian
+            | LayoutCode::Danish
+            | LayoutCode::Ukrainian
+            | LayoutCode::Belarusian
+            | LayoutCode::Kazakh
+            | LayoutCode::Bulgarian
+            | LayoutCode::BulgarianPhonetic
+            | LayoutCode::Serbian
+            | LayoutCode::Macedonian
+            | LayoutCode::Greek
+            | LayoutCode::Hebrew
+            | LayoutCode::Arabic
+            | LayoutCode::Persian
+            | LayoutCode::Turkish
+            | LayoutCode::Georgian
+            | LayoutCode::Armenian
+            | LayoutCode::ArmenianPhonetic
+            | LayoutCode::Thai
+            | LayoutCode::Telex
+            | LayoutCode::Korean
+            | LayoutCode::JisKana
+            | LayoutCode::JisKatakana
+            | LayoutCode::Hindi
+            | LayoutCode::Czech
+            | LayoutCode::Slovak
+            | LayoutCode::Polish
+            | LayoutCode::Hungarian
+            | LayoutCode::Romanian
+            | LayoutCode::Lithuanian
+            | LayoutCode::Latvian
+            | LayoutCode::LatvianErgonomic
+            | LayoutCode::Estonian
+            | LayoutCode::Icelandic
+            | LayoutCode::Mongolian => None,
+        }
+    }
+}
+
+impl FromStr for Language {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "en" | "english" => Ok(Language::English),
+            "ru" | "russian" => Ok(Language::Russian),
+            _ => Err(()),
+        }
+    }
+}
+
+lazy_static! {
+    static ref DICTIONARIES: HashMap<Language, HashSet<&'static str>> = {
+        let mut dictionaries = HashMap::new();
+        dictionaries.insert(
+            Language::English,
+            words(include_str!("dictionaries/en.txt")),
+        );
+        dictionaries.insert(
+            Language::Russian,
+            words(include_str!("dictionaries/ru.txt")),
+        );
+        dictionaries
+    };
+    // Words always spelled with ё, by their spelling with е. Words that mean
+    // something else with е (все and всё) are left out.
+    static ref YO_WORDS: HashMap<String, &'static str> = words(include_str!("dictionaries/ru_yo.txt"))
+        .into_iter()
+        .map(|word| (layouts::fold_yo(word), word))
+        .collect();
+    static ref LETTERS: HashMap<LayoutCode, HashSet<char>> = LayoutCode::ALL
+        .iter()
+        .map(|&layout| (layout, letters(layout)))
+        .collect();
+    static ref BACKEND: RwLock<Backend> = RwLock::new(Backend::default());
+}
+
+// Words of the dictionary of `language`, in no particular order
+pub(crate) fn dictionary(language: Language) -> impl Iterator<Item = &'static str> {
+    DICTIONARIES[&language].iter().copied()
+}
+
+// How words are scored. The dictionaries know the common words exactly; the
+// trigram models also recognise names, slang and word forms they do not
+// list, by how much each looks like the language.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    #[default]
+    Dictionary,
+    Trigram,
+    // The average of the two scores
+    Blend,
+}
+
+impl FromStr for Backend {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dictionary" => Ok(Backend::Dictionary),
+            "trigram" => Ok(Backend::Trigram),
+            "blend" => Ok(Backend::Blend),
+            _ => Err(()),
+        }
+    }
+}
+
+// The backend `word_score` and everything built on it use
+pub fn backend() -> Backend {
+    *BACKEND.read().unwrap()
+}
+
+pub fn set_backend(backend: Backend) {
+    *BACKEND.write().unwrap() = backend;
+}
+
+fn words(list: &'static str) -> HashSet<&'static str> {
+    list.lines()
+        .map(str::trim)
+        .filter(|w| !w.is_empty())
+        .collect()
+}
+
+// Letters `layout` can produce
+fn letters(layout: LayoutCode) -> HashSet<char> {
+    layouts::typeable_chars(layout)
+        .filter(|c| c.is_alphabetic())
+        .collect()
+}
+
+// Writes ё where е was typed in words that are always spelled with ё,
+// keeping the case of each letter
+pub fn restore_yo(text: &str) -> String {
+    let mut restored = String::with_capacity(text.len());
+    let mut word = String::new();
+
+    let flush = |word: &mut String, restored: &mut String| {
+        match YO_WORDS.get(&word.to_lowercase()) {
+            Some(spelling) => {
+                for (typed, spelled) in word.chars().zip(spelling.chars()) {
+                    restored.push(match (typed, spelled) {
+                        ('е', 'ё') => 'ё',
+                        ('Е', 'ё') => 'Ё',
+                        _ => typed,
+                    });
+                }
+            }
+            None => restored.push_str(word),
+        }
+        word.clear();
+    };
+
+    for c in text.chars() {
+        if c.is_alphabetic() {
+            word.push(c);
+        } else {
+            flush(&mut word, &mut restored);
+            restored.push(c);
+        }
+    }
+    flush(&mut word, &mut restored);
+    restored
+}
+
+// Scores for a single word
+const NOT_TYPEABLE: f64 = 0.0;
+const UNKNOWN_WORD: f64 = 0.4;
+// Within `max_typos` of a dictionary word
+const MISSPELLED_WORD: f64 = 0.8;
+const DICTIONARY_WORD: f64 = 1.0;
+
+// Edits a word of `len` letters may be away from a dictionary word and still
+// count as it misspelled. Short words are too easily one edit from another.
+fn max_typos(len: usize) -> usize {
+    match len {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+// Whether the Levenshtein distance between `a` and `b` is at most `max`
+fn within_distance(a: &[char], b: &[char], max: usize) -> bool {
+    if a.len().abs_diff(b.len()) > max {
+        return false;
+    }
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, &ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        if current.iter().all(|&distance| distance > max) {
+            return false;
+        }
+        previous = current;
+    }
+    previous[b.len()] <= max
+}
+
+// How well `word` (in lower case) matches `dictionary`, allowing for typos
+fn dictionary_score(word: &str, dictionary: &HashSet<&'static str>) -> f64 {
+    if dictionary.contains(word) {
+        return DICTIONARY_WORD;
+    }
+    let letters: Vec<char> = word.chars().collect();
+    let max = max_typos(letters.len());
+    let misspelled = max > 0
+        && dictionary.iter().any(|entry| {
+            let entry: Vec<char> = entry.chars().collect();
+            within_distance(&letters, &entry, max)
+        });
+    if misspelled {
+        MISSPELLED_WORD
+    } else {
+        UNKNOWN_WORD
+    }
+}
+
+// How much better a conversion has to score than the text as typed before
+// the fixer applies it
+const FIX_MARGIN: f64 = 0.3;
+
+// How plausible `word` is as something typed on `layout`: 0 if the layout
+// cannot produce its letters, else up to 1 as `backend()` scores it. The
+// dictionary gives 1 for a dictionary word and a little less for one that is
+// a typo or two away from one. Punctuation around the word is ignored;
+// `None` means there is nothing to score.
+pub fn word_score(word: &str, layout: LayoutCode) -> Option<f64> {
+    let word = word.trim_matches(|c: char| !c.is_alphabetic());
+    if word.is_empty() {
+        return None;
+    }
+
+    if !word.chars().all(|c| LETTERS[&layout].contains(&c)) {
+        return Some(NOT_TYPEABLE);
+    }
+
+    let Some(language) = Language::of(layout) else {
+        return Some(UNKNOWN_WORD);
+    };
+    let dictionary = || dictionary_score(&word.to_lowercase(), &DICTIONARIES[&language]);
+    let trigram = || trigram::model(language).score(word);
+    Some(match backend() {
+        Backend::Dictionary => dictionary(),
+        Backend::Trigram => trigram(),
+        Backend::Blend => (dictionary() + trigram()) / 2.0,
+    })
+}
+
+// Best score of `word` as typed on any of `layouts`
+fn as_typed_score(word: &str, layouts: &[LayoutCode]) -> Option<f64> {
+    layouts
+        .iter()
+        .filter_map(|&layout| word_score(word, layout))
+        .reduce(f64::max)
+}
+
+// Converts `word` from one layout to another, trying both with and without
+// its trailing punctuation converted
+fn candidates(word: &str, from: LayoutCode, to: LayoutCode) -> [String; 2] {
+    let core = word.trim_end_matches(|c: char| !c.is_alphanumeric());
+    let trailing = &word[core.len()..];
+    [
+        layouts::convert_text(word, from, to),
+        layouts::convert_text(core, from, to) + trailing,
+    ]
+}
+
+// Replacement for a single word if it looks like it was typed with the wrong
+// one of `layouts` active
+pub fn fix_word(word: &str, layouts: &[LayoutCode]) -> Option<String> {
+    let current = as_typed_score(word, layouts).unwrap_or(NOT_TYPEABLE);
+
+    let mut best: Option<(f64, String)> = None;
+    for &from in layouts {
+        for &to in layouts {
+            if from == to {
+                continue;
+            }
+            for candidate in candidates(word, from, to) {
+                let Some(score) = word_score(&candidate, to) else {
+                    continue;
+                };
+                if score > current + FIX_MARGIN
+                    && best.as_ref().is_none_or(|(best, _)| score > *best)
+                {
+                    best = Some((score, candidate));
+                }
+            }
+        }
+    }
+    best.map(|(_, candidate)| candidate)
+}
+
+// What `word` would read as on another of `layouts`: the fix if it looks
+// mistyped, else the same keys on the layout after the first one that can
+// produce it
+pub fn alternative(word: &str, layouts: &[LayoutCode]) -> Option<String> {
+    fix_word(word, layouts).or_else(|| {
+        let from = layouts.iter().position(|&layout| {
+            word_score(word, layout).is_some_and(|score| score > NOT_TYPEABLE)
+        })?;
+        let to = layouts[(from + 1) % layouts.len()];
+        (to != layouts[from]).then(|| layouts::convert_text(word, layouts[from], to))
+    })
+}
+
+// Layouts the bindings detect among when the caller names none: the common
+// mix-up of an English and a Russian keyboard. Detecting among every built-in
+// layout would find, for almost any word, some other layout turning it into
+// another word, and take a hundred times as long.
+pub const DEFAULT_LAYOUTS: [LayoutCode; 2] = [LayoutCode::Qwerty, LayoutCode::Russian];
+
+// The layout a text was most likely typed in by mistake, and the one that
+// was meant. `confidence` is the share of scored words the conversion fixes.
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct Detection {
+    pub from: LayoutCode,
+    pub to: LayoutCode,
+    pub confidence: f64,
+}
+
+// Finds the pair of `layouts` whose conversion fixes the most words of
+// `text`, or `None` if the text looks correctly typed
+pub fn detect(text: &str, layouts: &[LayoutCode]) -> Option<Detection> {
+    let words: Vec<(&str, f64)> = text
+        .split_whitespace()
+        .filter_map(|word| Some((word, as_typed_score(word, layouts)?)))
+        .collect();
+    if words.is_empty() {
+        return None;
+    }
+
+    let mut best: Option<Detection> = None;
+    for &from in layouts {
+        for &to in layouts {
+            if from == to {
+                continue;
+            }
+            let fixed = words
+                .iter()
+                .filter(|(word, current)| {
+                    candidates(word, from, to).iter().any(|candidate| {
+                        word_score(candidate, to).is_some_and(|score| score > current + FIX_MARGIN)
+                    })
+                })
+                .count();
+            let confidence = fixed as f64 / words.len() as f64;
+            if confidence > 0.0 && best.is_none_or(|best| confidence > best.confidence) {
+                best = Some(Detection {
+                    from,
+                    to,
+                    confidence,
+                });
+            }
+        }
+    }
+    best
+}
+
+// Converts the words of `text` that look like they were typed with the wrong
+// one of `layouts` active, leaving everything else (including whitespace)
+// untouched
+pub fn fix_text(text: &str, layouts: &[LayoutCode]) -> String {
+    let mut fixed = String::with_capacity(text.len());
+    let mut word = String::new();
+
+    let flush = |word: &mut String, fixed: &mut String| {
+        if !word.is_empty() {
+            match fix_word(word, layouts) {
+                Some(replacement) => fixed.push_str(&replacement),
+                None => fixed.push_str(word),
+            }
+            word.clear();
+        }
+    };
+
+    for c in text.chars() {
+        if c.is_whitespace() {
+            flush(&mut word, &mut fixed);
+            fixed.push(c);
+        } else {
+            word.push(c);
+        }
+    }
+    flush(&mut word, &mut fixed);
+    fixed
+}
@@ -0,0 +1,123 @@
+use crate::geometry::{self, Row};
+use crate::layouts::{self, LayoutCode};
+use serde::Serialize;
+
+// A key that produces different characters on two layouts, identified by
+// the character it produces on QWERTY
+#[derive(Serialize, Clone, Copy, Debug)]
+pub struct KeyChange {
+    pub key: char,
+    pub from: char,
+    pub to: char,
+}
+
+// Lists every key (unshifted and shifted) whose character differs between
+// `from` and `to`, row by row in keyboard order
+pub fn diff(from: LayoutCode, to: LayoutCode) -> Vec<KeyChange> {
+    layouts::QWERTY_ROWS
+        .iter()
+        .flat_map(|(unshifted, shifted)| unshifted.chars().chain(shifted.chars()))
+        .filter_map(|key| {
+            let change = KeyChange {
+                key,
+                from: layouts::char_at(from, key),
+                to: layouts::char_at(to, key),
+            };
+            (change.from != change.to).then_some(change)
+        })
+        .collect()
+}
+
+// How much of one layout is kept on another, for picking the layout that
+// is least work to move to
+#[derive(Serialize, Clone, Debug)]
+pub struct Similarity {
+    // From 0 (every character moves to another finger) to 1 (the same
+    // layout): characters count fully where they stay on their key, and
+    // half where they move to another key of the same finger
+    pub score: f64,
+    pub keys: usize,
+    // Characters on the same key on both layouts
+    pub same_key: usize,
+    // Characters moved to another key of the same finger
+    pub same_finger: usize,
+    // Characters the second layout does not type at all
+    pub missing: usize,
+    pub rows: Vec<RowAgreement>,
+}
+
+// How a row of the first layout fares on the second
+#[derive(Serialize, Clone, Debug)]
+pub struct RowAgreement {
+    pub row: Row,
+    pub keys: usize,
+    pub same_key: usize,
+    // Characters staying on this row, on their key or another
+    pub same_row: usize,
+    // Share of the row's characters on the same key
+    pub agreement: f64,
+}
+
+// Compares where `a` and `b` put the characters `a` types, unshifted and
+// shifted
+pub fn similarity(a: LayoutCode, b: LayoutCode) -> Similarity {
+    let keys_of = |unshifted: &str, shifted: &str| -> Vec<char> {
+        unshifted.chars().chain(shifted.chars()).collect()
+    };
+    let all_keys: Vec<char> = layouts::QWERTY_ROWS
+        .iter()
+        .flat_map(|(unshifted, shifted)| keys_of(unshifted, shifted))
+        .collect();
+
+    let (mut same_key, mut same_finger, mut missing) = (0, 0, 0);
+    let mut rows = Vec::new();
+    for (unshifted, shifted) in layouts::QWERTY_ROWS {
+        let row_keys = keys_of(unshifted, shifted);
+        let row = geometry::position(row_keys[0])
+            .expect("QWERTY keys have a position")
+            .row;
+        let mut agreement = RowAgreement {
+            row,
+            keys: row_keys.len(),
+            same_key: 0,
+            same_row: 0,
+            agreement: 0.0,
+        };
+        for key in row_keys {
+            let c = layouts::char_at(a, key);
+            if layouts::char_at(b, key) == c {
+                same_key += 1;
+                agreement.same_key += 1;
+                agreement.same_row += 1;
+                continue;
+            }
+            let moved = all_keys
+                .iter()
+                .find(|&&other| layouts::char_at(b, other) == c)
+                .and_then(|&other| geometry::position(other));
+            let Some(moved) = moved else {
+                missing += 1;
+                continue;
+            };
+            let position = geometry::position(key).expect("QWERTY keys have a position");
+            if moved.row == row {
+                agreement.same_row += 1;
+            }
+            if moved.finger == position.finger {
+                same_finger += 1;
+            }
+        }
+        agreement.agreement = agreement.same_key as f64 / agreement.keys as f64;
+        rows.push(agreement);
+    }
+
+    let keys = all_keys.len();
+    Similarity {
+        score: (same_key as f64 + same_finger as f64 / 2.0) / keys as f64,
+        keys,
+        same_key,
+        same_finger,
+        missing,
+        rows,
+    }
+}
@@ -0,0 +1,124 @@
+// Fixing whole documents, like chat exports, that mix correctly typed text
+// with text typed in the wrong layout. Rather than one guess for the whole
+// text, or one per word as `detect::fix_text` does, the document is split
+// into paragraphs and sentences and each is detected on its own; only those
+// detected with enough confidence are converted, as a whole.
+use crate::detect::{self, Detection};
+use crate::layouts::{self, LayoutCode};
+use serde::{Deserialize, Serialize};
+
+// Least `Detection::confidence` a sentence is converted with by default
+pub const DEFAULT_MIN_CONFIDENCE: f64 = 0.5;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Decision {
+    // Nothing in the segment looks typed in the wrong layout
+    Unchanged,
+    // Some words do, too few to convert the segment
+    Uncertain,
+    Converted,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Segment {
+    // Byte range of the segment in the document
+    pub start: usize,
+    pub end: usize,
+    // Index of the paragraph, and of the sentence within it, from 0
+    pub paragraph: usize,
+    pub sentence: usize,
+    pub text: String,
+    pub decision: Decision,
+    pub detection: Option<Detection>,
+    // The segment as it is in the fixed document
+    pub fixed: String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Document {
+    pub text: String,
+    pub segments: Vec<Segment>,
+}
+
+// Byte ranges of the sentences of `text`, with the paragraph and sentence
+// index of each. Paragraphs are separated by blank lines; sentences end at a
+// line break, or at `.`, `!`, `?` or `…` followed by whitespace. Whitespace
+// around sentences belongs to none of them.
+fn sentences(text: &str) -> Vec<(usize, usize, usize, usize)> {
+    let mut sentences = Vec::new();
+    let mut paragraph = 0;
+    let mut sentence = 0;
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        let start = offset;
+        offset += line.len();
+        if line.trim().is_empty() {
+            if sentence > 0 {
+                paragraph += 1;
+                sentence = 0;
+            }
+            continue;
+        }
+
+        let mut begin: Option<usize> = None;
+        let mut chars = line.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            if c.is_whitespace() {
+                continue;
+            }
+            let from = *begin.get_or_insert(i);
+            let next = chars.peek().map(|&(_, next)| next);
+            let ends =
+                matches!(c, '.' | '!' | '?' | '…') && next.is_none_or(|next| next.is_whitespace());
+            let line_ends = line[i + c.len_utf8()..].trim().is_empty();
+            if ends || line_ends {
+                sentences.push((start + from, start + i + c.len_utf8(), paragraph, sentence));
+                sentence += 1;
+                begin = None;
+            }
+        }
+    }
+    sentences
+}
+
+// Detects every sentence of `text` among `layouts` and converts those
+// detected with a confidence of at least `min_confidence`, leaving the rest
+// of the text as it is
+pub fn fix(text: &str, layouts: &[LayoutCode], min_confidence: f64) -> Document {
+    let mut fixed = String::with_capacity(text.len());
+    let mut segments = Vec::new();
+    let mut copied = 0;
+    for (start, end, paragraph, sentence) in sentences(text) {
+        let segment = &text[start..end];
+        let detection = detect::detect(segment, layouts);
+        let (decision, replacement) = match detection {
+            None => (Decision::Unchanged, segment.to_string()),
+            Some(detection) if detection.confidence < min_confidence => {
+                (Decision::Uncertain, segment.to_string())
+            }
+            Some(detection) => (
+                Decision::Converted,
+                layouts::convert_text(segment, detection.from, detection.to),
+            ),
+        };
+        fixed.push_str(&text[copied..start]);
+        fixed.push_str(&replacement);
+        copied = end;
+        segments.push(Segment {
+            start,
+            end,
+            paragraph,
+            sentence,
+            text: segment.to_string(),
+            decision,
+            detection,
+            fixed: replacement,
+        });
+    }
+    fixed.push_str(&text[copied..]);
+    Document {
+        text: fixed,
+        segments,
+    }
+}
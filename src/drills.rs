@@ -0,0 +1,242 @@
+// Practice lines for learning a layout, weighted towards the letters that
+// are on other keys than on the layout the user types now. Lines mix words
+// of the dictionary with pseudo-words drawn from the trigram model, which
+// look like the language and can be made of any letters, so that a drill
+// only uses the rows it focuses on.
+use crate::detect::{self, Language};
+use crate::diff::{self, KeyChange};
+use crate::geometry::{self, Row};
+use crate::layouts::{self, LayoutCode};
+use crate::trigram;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::str::FromStr;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DrillFocus {
+    #[default]
+    All,
+    NumberRow,
+    TopRow,
+    HomeRow,
+    BottomRow,
+}
+
+impl FromStr for DrillFocus {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(DrillFocus::All),
+            "number_row" => Ok(DrillFocus::NumberRow),
+            "top_row" => Ok(DrillFocus::TopRow),
+            "home_row" => Ok(DrillFocus::HomeRow),
+            "bottom_row" => Ok(DrillFocus::BottomRow),
+            _ => Err(()),
+        }
+    }
+}
+
+impl DrillFocus {
+    fn includes(self, row: Row) -> bool {
+        match self {
+            DrillFocus::All => true,
+            DrillFocus::NumberRow => row == Row::Number,
+            DrillFocus::TopRow => row == Row::Top,
+            DrillFocus::HomeRow => row == Row::Home,
+            DrillFocus::BottomRow => row == Row::Bottom,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Drill {
+    // The keys practised, with what they type on both layouts
+    pub keys: Vec<KeyChange>,
+    pub lines: Vec<String>,
+}
+
+// Characters a drill line is filled up to
+const LINE_WIDTH: usize = 60;
+// Share of the words of a line taken from the dictionary, when it has
+// enough words for the drill
+const WORD_SHARE: f64 = 0.5;
+// Dictionary words needed before they are mixed in at all
+const MIN_WORDS: usize = 5;
+// How much more often pseudo-words go on to a practised letter than the
+// trigram model would
+const EMPHASIS: f64 = 4.0;
+const PSEUDO_WORD_LEN: (usize, usize) = (2, 7);
+
+// A small deterministic generator (SplitMix64), so that the same seed gives
+// the same drill
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [0, 1)
+    fn unit(&mut self) -> f64 {
+        (self.next() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // An index into `weights`, picked in proportion to them
+    fn weighted(&mut self, weights: &[f64]) -> Option<usize> {
+        let total: f64 = weights.iter().sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut target = self.unit() * total;
+        for (index, &weight) in weights.iter().enumerate() {
+            if target < weight {
+                return Some(index);
+            }
+            target -= weight;
+        }
+        Some(weights.len() - 1)
+    }
+}
+
+// A word of `letters` that looks like `language`, with at least one of
+// `practised`
+fn pseudo_word(
+    rng: &mut Rng,
+    language: Language,
+    letters: &[char],
+    practised: &HashSet<char>,
+) -> Option<String> {
+    let model = trigram::model(language);
+    let (min, max) = PSEUDO_WORD_LEN;
+    for _ in 0..20 {
+        let mut word = vec![' ', ' '];
+        loop {
+            let len = word.len() - 2;
+            let context = [word[word.len() - 2], word[word.len() - 1]];
+            let mut choices: Vec<char> = letters.to_vec();
+            if len >= min {
+                choices.push(' ');
+            }
+            let weights: Vec<f64> = choices
+                .iter()
+                .map(|&c| {
+                    let p = model.probability(&[context[0], context[1], c]);
+                    if practised.contains(&c) {
+                        p * EMPHASIS
+                    } else {
+                        p
+                    }
+                })
+                .collect();
+            match rng.weighted(&weights).map(|index| choices[index]) {
+                Some(' ') | None => break,
+                Some(c) => word.push(c),
+            }
+            if len + 1 == max {
+                break;
+            }
+        }
+        let word: String = word[2..].iter().collect();
+        if word.chars().count() >= min && word.chars().any(|c| practised.contains(&c)) {
+            return Some(word);
+        }
+    }
+    None
+}
+
+// Practice lines for a user of `from` learning `layout`, typed in `language`.
+// `lines` lines are made of the letters on the `focus` rows, favouring those
+// on keys that changed; `seed` picks the words.
+pub fn drill(
+    layout: LayoutCode,
+    from: LayoutCode,
+    focus: DrillFocus,
+    language: Language,
+    lines: usize,
+    seed: u64,
+) -> Result<Drill, String> {
+    let on_focus = |key: char| geometry::position(key).is_some_and(|pos| focus.includes(pos.row));
+    let keys: Vec<KeyChange> = diff::diff(from, layout)
+        .into_iter()
+        .filter(|change| on_focus(change.key))
+        .collect();
+
+    // Letters of the focus rows, as the language writes them
+    let model_letters: HashSet<char> = detect::dictionary(language).flat_map(str::chars).collect();
+    let mut letters: Vec<char> = Vec::new();
+    for key in layouts::QWERTY_ROWS
+        .iter()
+        .flat_map(|(unshifted, _)| unshifted.chars())
+        .filter(|&key| on_focus(key))
+    {
+        let c = layouts::char_at(layout, key);
+        if model_letters.contains(&c) && !letters.contains(&c) {
+            letters.push(c);
+        }
+    }
+    let practised: HashSet<char> = keys
+        .iter()
+        .map(|change| change.to)
+        .filter(|c| letters.contains(c))
+        .collect();
+    if practised.is_empty() {
+        return Err(format!(
+            "no {:?} letters move between {} and {} on the rows drilled",
+            language,
+            from.as_str(),
+            layout.as_str()
+        ));
+    }
+
+    // Dictionary words of the focus letters, weighted by their share of
+    // practised letters
+    let mut words: Vec<&str> = detect::dictionary(language)
+        .filter(|word| word.chars().all(|c| letters.contains(&c)))
+        .filter(|word| word.chars().any(|c| practised.contains(&c)))
+        .collect();
+    words.sort_unstable();
+    let weights: Vec<f64> = words
+        .iter()
+        .map(|word| {
+            let practised = word.chars().filter(|c| practised.contains(c)).count();
+            practised as f64 / word.chars().count() as f64
+        })
+        .collect();
+
+    let mut rng = Rng(seed);
+    let mut drill = Vec::with_capacity(lines);
+    for _ in 0..lines {
+        let mut line = String::new();
+        let mut previous = String::new();
+        let mut repeats = 0;
+        while line.chars().count() < LINE_WIDTH {
+            let word = if words.len() >= MIN_WORDS && rng.unit() < WORD_SHARE {
+                rng.weighted(&weights).map(|index| words[index].to_string())
+            } else {
+                pseudo_word(&mut rng, language, &letters, &practised)
+            };
+            let Some(word) = word else {
+                break;
+            };
+            // The same word twice in a row is no practice, unless the
+            // letters leave no other
+            if word == previous && repeats < 10 {
+                repeats += 1;
+                continue;
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(&word);
+            previous = word;
+        }
+        drill.push(line);
+    }
+    Ok(Drill { keys, lines: drill })
+}
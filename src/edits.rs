@@ -0,0 +1,93 @@
+use serde::Serialize;
+
+// A word that differs between a text and its converted version. Offsets
+// count characters of the original text; `line` and `column` are 1-based.
+#[derive(Serialize, Clone, Debug)]
+pub struct Edit {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+    pub original: String,
+    pub replacement: String,
+}
+
+struct Word<'a> {
+    start: usize,
+    end: usize,
+    line: usize,
+    column: usize,
+    text: &'a str,
+}
+
+// Splits `text` into whitespace-separated words with their positions
+fn words(text: &str) -> Vec<Word<'_>> {
+    let mut words = Vec::new();
+    let (mut line, mut column) = (1, 1);
+    let mut current: Option<(usize, usize, usize, usize)> = None;
+
+    for (index, (offset, c)) in text.char_indices().enumerate() {
+        if c.is_whitespace() {
+            if let Some((start, byte, line, column)) = current.take() {
+                words.push(Word {
+                    start,
+                    end: index,
+                    line,
+                    column,
+                    text: &text[byte..offset],
+                });
+            }
+        } else if current.is_none() {
+            current = Some((index, offset, line, column));
+        }
+
+        if c == '\n' {
+            line += 1;
+            column = 1;
+        } else {
+            column += 1;
+        }
+    }
+    if let Some((start, byte, line, column)) = current {
+        words.push(Word {
+            start,
+            end: start + text[byte..].chars().count(),
+            line,
+            column,
+            text: &text[byte..],
+        });
+    }
+    words
+}
+
+// Lists the words changed by a conversion. Conversions never touch
+// whitespace, so words are matched up by position.
+pub fn word_edits(original: &str, converted: &str) -> Vec<Edit> {
+    let before = words(original);
+    let after = words(converted);
+
+    if before.len() != after.len() {
+        return vec![Edit {
+            start: 0,
+            end: original.chars().count(),
+            line: 1,
+            column: 1,
+            original: original.to_string(),
+            replacement: converted.to_string(),
+        }];
+    }
+
+    before
+        .iter()
+        .zip(after.iter())
+        .filter(|(before, after)| before.text != after.text)
+        .map(|(before, after)| Edit {
+            start: before.start,
+            end: before.end,
+            line: before.line,
+            column: before.column,
+            original: before.text.to_string(),
+            replacement: after.text.to_string(),
+        })
+        .collect()
+}
@@ -0,0 +1,261 @@
+// Rewrites the key specs of Emacs Lisp key bindings for a new layout, so that
+// every binding stays on the keys it was pressed with before. Key specs are
+// the strings given to `kbd`, the `keymap-*` and `bind-key` functions and
+// use-package's `:bind`, like "C-c p" or "M-<f5>"; the rest of the source,
+// comments included, is copied as it is. Old-style strings such as "\C-cp"
+// and key vectors are left alone.
+use crate::layouts::{self, LayoutCode};
+
+// Functions taking a key spec, and which of their arguments it is
+const KEY_ARGUMENTS: [(&str, usize); 10] = [
+    ("kbd", 0),
+    ("keymap-set", 1),
+    ("keymap-unset", 1),
+    ("keymap-global-set", 0),
+    ("keymap-local-set", 0),
+    ("keymap-global-unset", 0),
+    ("keymap-local-unset", 0),
+    ("bind-key", 0),
+    ("bind-key*", 0),
+    ("unbind-key", 0),
+];
+
+// Modifier prefixes of a key, as in C-M-x
+const MODIFIERS: &str = "ACHMSs";
+
+fn remap_char(c: char, from: LayoutCode, to: LayoutCode) -> char {
+    layouts::convert_text(c.encode_utf8(&mut [0; 4]), from, to)
+        .chars()
+        .next()
+        .unwrap_or(c)
+}
+
+// Remaps the keys of a spec like "C-x C-f". Named keys such as RET, SPC or
+// <f5> are the same on every layout.
+pub fn remap_key_spec(spec: &str, from: LayoutCode, to: LayoutCode) -> String {
+    spec.split(' ')
+        .map(|chord| {
+            let bytes = chord.as_bytes();
+            let mut modifiers = 0;
+            while chord.len() > modifiers + 2
+                && MODIFIERS.contains(bytes[modifiers] as char)
+                && bytes[modifiers + 1] == b'-'
+            {
+                modifiers += 2;
+            }
+            let mut key = chord[modifiers..].chars();
+            match (key.next(), key.next()) {
+                (Some(c), None) => match remap_char(c, from, to) {
+                    ' ' => format!("{}SPC", &chord[..modifiers]),
+                    remapped => format!("{}{}", &chord[..modifiers], remapped),
+                },
+                _ => chord.to_string(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// A string literal remapped as a key spec, or `None` if it uses escapes other
+// than \" and \\
+fn remap_string(literal: &str, from: LayoutCode, to: LayoutCode) -> Option<String> {
+    let content = literal.strip_prefix('"')?.strip_suffix('"')?;
+    let mut spec = String::with_capacity(content.len());
+    let mut chars = content.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next()? {
+                escaped @ ('"' | '\\') => spec.push(escaped),
+                _ => return None,
+            },
+            _ => spec.push(c),
+        }
+    }
+    let mut remapped = String::from('"');
+    for c in remap_key_spec(&spec, from, to).chars() {
+        if c == '"' || c == '\\' {
+            remapped.push('\\');
+        }
+        remapped.push(c);
+    }
+    remapped.push('"');
+    Some(remapped)
+}
+
+// End of the string literal starting at `start`
+fn string_end(source: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+    while i < source.len() {
+        match source[i] {
+            b'\\' => i += 2,
+            b'"' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    source.len()
+}
+
+// End of the whitespace and comments starting at `start`
+fn blank_end(source: &[u8], start: usize) -> usize {
+    let mut i = start;
+    while i < source.len() {
+        match source[i] {
+            b';' => {
+                while i < source.len() && source[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            c if c.is_ascii_whitespace() => i += 1,
+            _ => break,
+        }
+    }
+    i
+}
+
+fn is_delimiter(c: u8) -> bool {
+    c.is_ascii_whitespace() || matches!(c, b'(' | b')' | b'[' | b']' | b'"' | b';' | b'\'')
+}
+
+// End of the expression starting at `start`
+fn sexp_end(source: &[u8], start: usize) -> usize {
+    let mut i = start;
+    // Quotes in front of an expression belong to it
+    while i < source.len() && matches!(source[i], b'\'' | b'`' | b',' | b'#') {
+        i += 1;
+    }
+    match source.get(i) {
+        Some(b'"') => string_end(source, i),
+        Some(b'?') => char_end(source, i),
+        Some(b'(' | b'[') => {
+            let mut depth = 0;
+            while i < source.len() {
+                match source[i] {
+                    b'(' | b'[' => depth += 1,
+                    b')' | b']' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return i + 1;
+                        }
+                    }
+                    b'"' => {
+                        i = string_end(source, i);
+                        continue;
+                    }
+                    b'?' => {
+                        i = char_end(source, i);
+                        continue;
+                    }
+                    b';' => {
+                        i = blank_end(source, i);
+                        continue;
+                    }
+                    _ => {}
+                }
+                i += 1;
+            }
+            source.len()
+        }
+        _ => symbol_end(source, i),
+    }
+}
+
+fn symbol_end(source: &[u8], start: usize) -> usize {
+    let mut i = start;
+    while i < source.len() && !is_delimiter(source[i]) {
+        i += if source[i] == b'\\' { 2 } else { 1 };
+    }
+    i.min(source.len())
+}
+
+// End of a character literal like ?a, ?\" or ?\C-c
+fn char_end(source: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+    while i < source.len() && source[i] == b'\\' {
+        i += 2;
+    }
+    // The character itself, which may take several bytes
+    i += 1;
+    while i < source.len() && (source[i] & 0xC0) == 0x80 {
+        i += 1;
+    }
+    i.min(source.len())
+}
+
+// Rewrites the key bindings in `source`, written for `from`, to be pressed
+// with the same keys on `to`
+pub fn remap_elisp(source: &str, from: LayoutCode, to: LayoutCode) -> String {
+    let bytes = source.as_bytes();
+    let mut remapped = String::with_capacity(source.len());
+    let mut copied = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b';' => i = blank_end(bytes, i),
+            b'"' => i = string_end(bytes, i),
+            b'?' => i = char_end(bytes, i),
+            b'(' => {
+                i += 1;
+                let head_start = blank_end(bytes, i);
+                let head_end = symbol_end(bytes, head_start);
+                let Some(&(_, index)) = KEY_ARGUMENTS
+                    .iter()
+                    .find(|(name, _)| *name == &source[head_start..head_end])
+                else {
+                    continue;
+                };
+                let mut argument = blank_end(bytes, head_end);
+                for _ in 0..index {
+                    argument = blank_end(bytes, sexp_end(bytes, argument));
+                }
+                if bytes.get(argument) != Some(&b'"') {
+                    i = head_end;
+                    continue;
+                }
+                let end = string_end(bytes, argument);
+                if let Some(spec) = remap_string(&source[argument..end], from, to) {
+                    remapped.push_str(&source[copied..argument]);
+                    remapped.push_str(&spec);
+                    copied = end;
+                }
+                i = end;
+            }
+            c if !is_delimiter(c) && !matches!(c, b'`' | b',' | b'#') => {
+                let end = symbol_end(bytes, i);
+                if !matches!(&source[i..end], ":bind" | ":bind*") {
+                    i = end;
+                    continue;
+                }
+                // use-package bindings, ("C-c p" . command) or a list of them
+                let start = blank_end(bytes, end);
+                let bindings_end = sexp_end(bytes, start);
+                let mut j = start;
+                while j < bindings_end {
+                    match bytes[j] {
+                        b';' => j = blank_end(bytes, j),
+                        b'"' => j = string_end(bytes, j),
+                        b'?' => j = char_end(bytes, j),
+                        b'(' => {
+                            let key = blank_end(bytes, j + 1);
+                            j += 1;
+                            if bytes.get(key) != Some(&b'"') {
+                                continue;
+                            }
+                            let key_end = string_end(bytes, key);
+                            if let Some(spec) = remap_string(&source[key..key_end], from, to) {
+                                remapped.push_str(&source[copied..key]);
+                                remapped.push_str(&spec);
+                                copied = key_end;
+                            }
+                            j = key_end;
+                        }
+                        _ => j += 1,
+                    }
+                }
+                i = bindings_end.max(end);
+            }
+            _ => i += 1,
+        }
+    }
+    remapped.push_str(&source[copied.min(source.len())..]);
+    remapped
+}
@@ -0,0 +1,122 @@
+// Converts files too large to read into memory comfortably: the input is
+// memory-mapped and cut into windows that end at a line break, a batch of
+// windows is converted in parallel (one thread each, up to the converter's
+// `max_threads`) and the output is written as each batch is done, so only
+// the batch in flight is held in memory.
+use crate::converter::Converter;
+use crate::layouts::LayoutCode;
+use memmap2::Mmap;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+
+// Bytes per window. A window without a line break is cut at a character
+// boundary instead, which may split a dead key from the letter it accents.
+pub const WINDOW: usize = 8 << 20;
+
+pub fn convert_file(
+    path: &Path,
+    out_path: &Path,
+    from: LayoutCode,
+    to: LayoutCode,
+) -> Result<(), String> {
+    let converter = Converter::builder().from(from).to(to).build()?;
+    convert_file_with(&converter, path, out_path)
+}
+
+// Converts `path` into `out_path` with the options of `converter`. The output
+// goes to a temporary file next to `out_path` that replaces it once complete,
+// so `out_path` can be the input itself.
+pub fn convert_file_with(
+    converter: &Converter,
+    path: &Path,
+    out_path: &Path,
+) -> Result<(), String> {
+    let temp = temp_path(out_path);
+    let converted = write_converted(converter, path, &temp).and_then(|()| {
+        fs::rename(&temp, out_path)
+            .map_err(|err| format!("cannot write {}: {}", out_path.display(), err))
+    });
+    if converted.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    converted
+}
+
+fn temp_path(out_path: &Path) -> PathBuf {
+    let name = out_path
+        .file_name()
+        .map_or_else(|| "output".into(), |name| name.to_string_lossy());
+    out_path.with_file_name(format!(".{}.keymorph-tmp", name))
+}
+
+fn write_converted(converter: &Converter, path: &Path, temp: &Path) -> Result<(), String> {
+    let read_error = |err: std::io::Error| format!("cannot read {}: {}", path.display(), err);
+    let write_error = |err: std::io::Error| format!("cannot write {}: {}", temp.display(), err);
+
+    let input = File::open(path).map_err(read_error)?;
+    let metadata = input.metadata().map_err(read_error)?;
+    let output = File::create(temp).map_err(write_error)?;
+    // The temporary file replaces the output, which keeps the input's mode
+    output
+        .set_permissions(metadata.permissions())
+        .map_err(write_error)?;
+    let mut writer = BufWriter::new(output);
+    // Empty files cannot be mapped on every platform
+    if metadata.len() > 0 {
+        // SAFETY: the map is only read. Another process truncating the file
+        // while it is converted is not guarded against, as with any mmap.
+        let map = unsafe { Mmap::map(&input) }.map_err(read_error)?;
+        let threads = converter.parallelism().max_threads.max(1);
+        for batch in windows(&map, WINDOW).chunks(threads) {
+            let converted: Vec<Result<String, String>> = thread::scope(|scope| {
+                let handles: Vec<_> = batch
+                    .iter()
+                    .map(|window| {
+                        scope.spawn(move || {
+                            let text = std::str::from_utf8(window)
+                                .map_err(|_| format!("{} is not valid UTF-8", path.display()))?;
+                            Ok(converter.convert_serial(text))
+                        })
+                    })
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().unwrap())
+                    .collect()
+            });
+            for window in converted {
+                writer.write_all(window?.as_bytes()).map_err(write_error)?;
+            }
+        }
+    }
+    writer.flush().map_err(write_error)
+}
+
+// Cuts `bytes` into windows of at most `window` bytes, each ending after a
+// line break where there is one and never inside a UTF-8 sequence. Empty
+// input has no windows.
+pub fn windows(bytes: &[u8], window: usize) -> Vec<&[u8]> {
+    let mut windows = Vec::new();
+    let mut rest = bytes;
+    while rest.len() > window {
+        let cut = rest[..window]
+            .iter()
+            .rposition(|&byte| byte == b'\n')
+            .map(|newline| newline + 1)
+            .unwrap_or_else(|| {
+                (1..=window)
+                    .rev()
+                    .find(|&i| rest[i] & 0xC0 != 0x80)
+                    .unwrap_or(window)
+            });
+        let (head, tail) = rest.split_at(cut);
+        windows.push(head);
+        rest = tail;
+    }
+    if !rest.is_empty() {
+        windows.push(rest);
+    }
+    windows
+}
@@ -0,0 +1,276 @@
+// Keymaps for keyboards running their own firmware, so that a board types a
+// layout while the computer stays set to another one, the host layout
+// (usually QWERTY). Boards are described here once, key by key, and the
+// bindings worked out for them are written by an `Exporter` for each
+// firmware, like `qmk` and `zmk`, or for remappers doing the same in software
+// (`remapper`).
+use crate::layouts::{self, LayoutCode};
+use crate::registry::Layout;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+// Keys that do the same on every layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Special {
+    Escape,
+    Tab,
+    CapsLock,
+    Backspace,
+    Enter,
+    Space,
+    LeftShift,
+    RightShift,
+    LeftCtrl,
+    RightCtrl,
+    LeftAlt,
+    RightAlt,
+    LeftGui,
+    RightGui,
+    Menu,
+    Left,
+    Down,
+    Up,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Delete,
+    // F1 to F12
+    Function(u8),
+}
+
+impl FromStr for Special {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "esc" => Ok(Special::Escape),
+            "tab" => Ok(Special::Tab),
+            "caps" => Ok(Special::CapsLock),
+            "bspc" => Ok(Special::Backspace),
+            "ent" => Ok(Special::Enter),
+            "spc" => Ok(Special::Space),
+            "lsft" => Ok(Special::LeftShift),
+            "rsft" => Ok(Special::RightShift),
+            "lctl" => Ok(Special::LeftCtrl),
+            "rctl" => Ok(Special::RightCtrl),
+            "lalt" => Ok(Special::LeftAlt),
+            "ralt" => Ok(Special::RightAlt),
+            "lgui" => Ok(Special::LeftGui),
+            "rgui" => Ok(Special::RightGui),
+            "menu" => Ok(Special::Menu),
+            "left" => Ok(Special::Left),
+            "down" => Ok(Special::Down),
+            "up" => Ok(Special::Up),
+            "rght" => Ok(Special::Right),
+            "home" => Ok(Special::Home),
+            "end" => Ok(Special::End),
+            "pgup" => Ok(Special::PageUp),
+            "pgdn" => Ok(Special::PageDown),
+            "del" => Ok(Special::Delete),
+            _ => match s.strip_prefix('f').map(u8::from_str) {
+                Some(Ok(n)) if (1..=12).contains(&n) => Ok(Special::Function(n)),
+                _ => Err(()),
+            },
+        }
+    }
+}
+
+// A key of a board
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Slot {
+    // A key of the main block, named by what it types on QWERTY as in
+    // `layouts::QWERTY_ROWS`
+    Key(char),
+    Special(Special),
+    // A key the keymap leaves unbound
+    None,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Board {
+    // Row-staggered 60% board, as the geometry of the other modules
+    Ansi60,
+    // Ortholinear 4x12 board
+    Planck,
+    // Split 3x6 board with three thumb keys a side
+    Corne,
+    // Split ergonomic board with a function row and six thumb keys a side
+    Glove80,
+}
+
+impl FromStr for Board {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ansi_60" => Ok(Board::Ansi60),
+            "planck" => Ok(Board::Planck),
+            "corne" => Ok(Board::Corne),
+            "glove80" => Ok(Board::Glove80),
+            _ => Err(()),
+        }
+    }
+}
+
+impl Board {
+    pub const ALL: [Board; 4] = [Board::Ansi60, Board::Planck, Board::Corne, Board::Glove80];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Board::Ansi60 => "ansi_60",
+            Board::Planck => "planck",
+            Board::Corne => "corne",
+            Board::Glove80 => "glove80",
+        }
+    }
+
+    // Keys of each row, left to right, both halves of split boards: main
+    // block keys as their QWERTY character, special keys by name and `_`
+    // for keys left unbound
+    fn rows(self) -> &'static [&'static str] {
+        match self {
+            Board::Ansi60 => &[
+                "` 1 2 3 4 5 6 7 8 9 0 - = bspc",
+                "tab q w e r t y u i o p [ ] \\",
+                "caps a s d f g h j k l ; ' ent",
+                "lsft z x c v b n m , . / rsft",
+                "lctl lgui lalt spc ralt rgui menu rctl",
+            ],
+            Board::Planck => &[
+                "tab q w e r t y u i o p bspc",
+                "esc a s d f g h j k l ; '",
+                "lsft z x c v b n m , . / ent",
+                "lctl _ lgui lalt _ spc spc _ left down up rght",
+            ],
+            Board::Corne => &[
+                "tab q w e r t y u i o p bspc",
+                "lctl a s d f g h j k l ; '",
+                "lsft z x c v b n m , . / esc",
+                "lgui spc lalt ralt ent spc",
+            ],
+            // The bottom rows run across both thumb clusters
+            Board::Glove80 => &[
+                "f1 f2 f3 f4 f5 f6 f7 f8 f9 f10",
+                "= 1 2 3 4 5 6 7 8 9 0 -",
+                "tab q w e r t y u i o p \\",
+                "esc a s d f g h j k l ; '",
+                "` z x c v b lsft lctl _ lgui rctl rsft n m , . / pgup",
+                "_ home end left rght bspc del lalt ralt ent spc up down [ ] pgdn",
+            ],
+        }
+    }
+
+    // The keys of the board row by row
+    pub fn slots(self) -> Vec<Vec<Slot>> {
+        self.rows()
+            .iter()
+            .map(|row| {
+                row.split(' ')
+                    .map(|name| match (name, Special::from_str(name)) {
+                        ("_", _) => Slot::None,
+                        (_, Ok(special)) => Slot::Special(special),
+                        _ => Slot::Key(name.chars().next().expect("keys have a name")),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+// What a key of a board sends
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    // The main block key typing `key` on QWERTY, with Shift held if `shift`
+    Key { key: char, shift: bool },
+    Special(Special),
+    None,
+}
+
+// A firmware, or software remapper, keymaps are written for
+pub trait Exporter {
+    // Name of the firmware, for messages
+    fn name(&self) -> &'static str;
+
+    // Whether the firmware has keymaps for `board`
+    fn supports(&self, board: Board) -> bool;
+
+    // The keymap file making the board type `layout`
+    fn export(&self, layout: &Layout, keymap: &Keymap) -> String;
+}
+
+// Bindings of every key of a board, and what they cannot type as wanted
+pub struct Keymap {
+    pub board: Board,
+    pub rows: Vec<Vec<Binding>>,
+    pub problems: Vec<String>,
+}
+
+// The keymap making `board` type `layout` with the host set to `host`. A
+// key sends the host key typing its unshifted character, with Shift if the
+// host only types that shifted; Shift on top of it then types what it does
+// on the host, which is reported when the layout wants something else.
+pub fn keymap(layout: &Layout, board: Board, host: LayoutCode) -> Keymap {
+    let mut problems = Vec::new();
+    let rows = board
+        .slots()
+        .into_iter()
+        .map(|row| {
+            row.into_iter()
+                .map(|slot| match slot {
+                    Slot::Key(key) => {
+                        let (binding, problem) = bind(layout, key, host);
+                        problems.extend(problem);
+                        binding
+                    }
+                    Slot::Special(special) => Binding::Special(special),
+                    Slot::None => Binding::None,
+                })
+                .collect()
+        })
+        .collect();
+    Keymap {
+        board,
+        rows,
+        problems,
+    }
+}
+
+fn bind(layout: &Layout, key: char, host: LayoutCode) -> (Binding, Option<String>) {
+    let shifted = layouts::shift_pairs()
+        .find(|&(lower, _)| lower == key)
+        .map_or(key, |(_, upper)| upper);
+    let (lower, upper) = (layout.char_at(key), layout.char_at(shifted));
+    let sent = layouts::shift_pairs().find_map(|(host_lower, host_upper)| {
+        let (typed_lower, typed_upper) = (
+            layouts::char_at(host, host_lower),
+            layouts::char_at(host, host_upper),
+        );
+        if typed_lower == lower {
+            Some((host_lower, false, typed_upper))
+        } else if typed_upper == lower {
+            Some((host_lower, true, typed_upper))
+        } else {
+            None
+        }
+    });
+    let Some((sent, shift, typed_upper)) = sent else {
+        return (
+            Binding::None,
+            Some(format!(
+                "'{}' cannot be typed with the host set to {}",
+                lower,
+                host.as_str()
+            )),
+        );
+    };
+    let problem = (typed_upper != upper).then(|| {
+        format!(
+            "Shift+'{}' types '{}' instead of '{}'",
+            lower, typed_upper, upper
+        )
+    });
+    (Binding::Key { key: sent, shift }, problem)
+}
@@ -0,0 +1,177 @@
+use crate::layouts;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Finger {
+    LeftPinky,
+    LeftRing,
+    LeftMiddle,
+    LeftIndex,
+    RightIndex,
+    RightMiddle,
+    RightRing,
+    RightPinky,
+}
+
+impl Finger {
+    pub const ALL: [Finger; 8] = [
+        Finger::LeftPinky,
+        Finger::LeftRing,
+        Finger::LeftMiddle,
+        Finger::LeftIndex,
+        Finger::RightIndex,
+        Finger::RightMiddle,
+        Finger::RightRing,
+        Finger::RightPinky,
+    ];
+
+    pub fn hand(self) -> Hand {
+        match self {
+            Finger::LeftPinky | Finger::LeftRing | Finger::LeftMiddle | Finger::LeftIndex => {
+                Hand::Left
+            }
+            _ => Hand::Right,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Hand {
+    Left,
+    Right,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Hash, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Row {
+    Number,
+    Top,
+    Home,
+    Bottom,
+}
+
+// Physical placement of a key on a row-staggered ANSI keyboard
+#[derive(Clone, Copy, Debug)]
+pub struct KeyPosition {
+    pub row: Row,
+    // Horizontal centre of the key, in key widths from the left edge
+    pub x: f64,
+    // Vertical centre of the key, in key heights from the number row
+    pub y: f64,
+    pub finger: Finger,
+}
+
+impl Row {
+    pub const ALL: [Row; 4] = [Row::Number, Row::Top, Row::Home, Row::Bottom];
+}
+
+// How far each row is shifted right relative to the number row
+const ROW_OFFSETS: [f64; 4] = [0.0, 1.5, 1.75, 2.25];
+
+// Finger for each key of the corresponding `layouts::QWERTY_ROWS` row
+const FINGERS: [&[Finger]; 4] = {
+    use Finger::*;
+    [
+        &[
+            LeftPinky,
+            LeftPinky,
+            LeftRing,
+            LeftMiddle,
+            LeftIndex,
+            LeftIndex,
+            RightIndex,
+            RightIndex,
+            RightMiddle,
+            RightRing,
+            RightPinky,
+            RightPinky,
+            RightPinky,
+        ],
+        &[
+            LeftPinky,
+            LeftRing,
+            LeftMiddle,
+            LeftIndex,
+            LeftIndex,
+            RightIndex,
+            RightIndex,
+            RightMiddle,
+            RightRing,
+            RightPinky,
+            RightPinky,
+            RightPinky,
+            RightPinky,
+        ],
+        &[
+            LeftPinky,
+            LeftRing,
+            LeftMiddle,
+            LeftIndex,
+            LeftIndex,
+            RightIndex,
+            RightIndex,
+            RightMiddle,
+            RightRing,
+            RightPinky,
+            RightPinky,
+        ],
+        &[
+            LeftPinky,
+            LeftRing,
+            LeftMiddle,
+            LeftIndex,
+            LeftIndex,
+            RightIndex,
+            RightIndex,
+            RightMiddle,
+            RightRing,
+            RightPinky,
+        ],
+    ]
+};
+
+// QWERTY characters of the keys each finger rests on
+const HOME_KEYS: [(Finger, char); 8] = [
+    (Finger::LeftPinky, 'a'),
+    (Finger::LeftRing, 's'),
+    (Finger::LeftMiddle, 'd'),
+    (Finger::LeftIndex, 'f'),
+    (Finger::RightIndex, 'j'),
+    (Finger::RightMiddle, 'k'),
+    (Finger::RightRing, 'l'),
+    (Finger::RightPinky, ';'),
+];
+
+// Looks up the key that produces `key` (unshifted or shifted) on QWERTY
+pub fn position(key: char) -> Option<KeyPosition> {
+    layouts::QWERTY_ROWS
+        .iter()
+        .enumerate()
+        .find_map(|(row, (unshifted, shifted))| {
+            let column = unshifted
+                .chars()
+                .position(|c| c == key)
+                .or_else(|| shifted.chars().position(|c| c == key))?;
+            Some(KeyPosition {
+                row: Row::ALL[row],
+                x: ROW_OFFSETS[row] + column as f64,
+                y: row as f64,
+                finger: FINGERS[row][column],
+            })
+        })
+}
+
+// Position of the key `finger` rests on
+pub fn home(finger: Finger) -> KeyPosition {
+    let (_, key) = HOME_KEYS
+        .iter()
+        .find(|(f, _)| *f == finger)
+        .expect("every finger has a home key");
+    position(*key).expect("home keys are on the keyboard")
+}
+
+pub fn distance(a: &KeyPosition, b: &KeyPosition) -> f64 {
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt()
+}
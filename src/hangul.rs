@@ -0,0 +1,157 @@
+// Hangul syllable composition for the 2-set Korean layout. Its keys type
+// single jamo; a syllable is an initial consonant, a vowel and optionally a
+// final consonant, and vowels and finals can be compounds of two keystrokes
+// (ㅗ + ㅏ → ㅘ, ㄹ + ㄱ → ㄺ). A consonant between two vowels starts the
+// second syllable, so "ㅇㅏㄴㄴㅕㅇ" is 안녕.
+
+const INITIALS: &str = "ㄱㄲㄴㄷㄸㄹㅁㅂㅃㅅㅆㅇㅈㅉㅊㅋㅌㅍㅎ";
+const MEDIALS: &str = "ㅏㅐㅑㅒㅓㅔㅕㅖㅗㅘㅙㅚㅛㅜㅝㅞㅟㅠㅡㅢㅣ";
+// Finals follow the syllable without one
+const FINALS: &str = "ㄱㄲㄳㄴㄵㄶㄷㄹㄺㄻㄼㄽㄾㄿㅀㅁㅂㅄㅅㅆㅇㅈㅊㅋㅌㅍㅎ";
+
+const FIRST_SYLLABLE: u32 = 0xAC00;
+const LAST_SYLLABLE: u32 = 0xD7A3;
+
+// (first keystroke, second keystroke, the jamo they type together)
+const COMPOUNDS: [(char, char, char); 18] = [
+    ('ㅗ', 'ㅏ', 'ㅘ'),
+    ('ㅗ', 'ㅐ', 'ㅙ'),
+    ('ㅗ', 'ㅣ', 'ㅚ'),
+    ('ㅜ', 'ㅓ', 'ㅝ'),
+    ('ㅜ', 'ㅔ', 'ㅞ'),
+    ('ㅜ', 'ㅣ', 'ㅟ'),
+    ('ㅡ', 'ㅣ', 'ㅢ'),
+    ('ㄱ', 'ㅅ', 'ㄳ'),
+    ('ㄴ', 'ㅈ', 'ㄵ'),
+    ('ㄴ', 'ㅎ', 'ㄶ'),
+    ('ㄹ', 'ㄱ', 'ㄺ'),
+    ('ㄹ', 'ㅁ', 'ㄻ'),
+    ('ㄹ', 'ㅂ', 'ㄼ'),
+    ('ㄹ', 'ㅅ', 'ㄽ'),
+    ('ㄹ', 'ㅌ', 'ㄾ'),
+    ('ㄹ', 'ㅍ', 'ㄿ'),
+    ('ㄹ', 'ㅎ', 'ㅀ'),
+    ('ㅂ', 'ㅅ', 'ㅄ'),
+];
+
+fn index(table: &str, c: char) -> Option<u32> {
+    table.chars().position(|entry| entry == c).map(|i| i as u32)
+}
+
+fn is_vowel(c: char) -> bool {
+    MEDIALS.contains(c)
+}
+
+fn compound(first: char, second: char) -> Option<char> {
+    COMPOUNDS
+        .iter()
+        .find(|&&(a, b, _)| a == first && b == second)
+        .map(|&(_, _, compound)| compound)
+}
+
+fn keystrokes(c: char) -> Option<(char, char)> {
+    COMPOUNDS
+        .iter()
+        .find(|&&(_, _, compound)| compound == c)
+        .map(|&(a, b, _)| (a, b))
+}
+
+// Syllables and compound jamo, which the layout has no single key for
+pub fn letters() -> impl Iterator<Item = char> {
+    (FIRST_SYLLABLE..=LAST_SYLLABLE)
+        .filter_map(char::from_u32)
+        .chain(COMPOUNDS.iter().map(|&(_, _, compound)| compound))
+}
+
+// Joins jamo into syllables. Jamo that cannot start or complete a syllable
+// are kept as they are, except that two vowels forming a compound are joined.
+pub fn compose(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut composed = String::with_capacity(text.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        let (Some(initial), Some(vowel)) = (index(INITIALS, c), next.filter(|&v| is_vowel(v)))
+        else {
+            match next
+                .and_then(|next| compound(c, next))
+                .filter(|&v| is_vowel(v))
+            {
+                Some(vowel) => {
+                    composed.push(vowel);
+                    i += 2;
+                }
+                None => {
+                    composed.push(c);
+                    i += 1;
+                }
+            }
+            continue;
+        };
+        i += 2;
+
+        let mut vowel = vowel;
+        if let Some(joined) = chars.get(i).and_then(|&next| compound(vowel, next)) {
+            vowel = joined;
+            i += 1;
+        }
+        let medial = index(MEDIALS, vowel).unwrap_or(0);
+
+        // A consonant followed by a vowel starts the next syllable instead
+        let starts_syllable = |at: usize| chars.get(at + 1).is_some_and(|&c| is_vowel(c));
+        let mut final_index = 0;
+        if let Some(first) = chars
+            .get(i)
+            .and_then(|&c| index(FINALS, c))
+            .filter(|_| !starts_syllable(i))
+        {
+            final_index = first + 1;
+            i += 1;
+            let joined = chars
+                .get(i)
+                .and_then(|&second| compound(chars[i - 1], second))
+                .filter(|_| !starts_syllable(i));
+            if let Some(joined) = joined {
+                final_index = index(FINALS, joined).unwrap_or(first) + 1;
+                i += 1;
+            }
+        }
+
+        let syllable = FIRST_SYLLABLE + (initial * 21 + medial) * 28 + final_index;
+        composed.extend(char::from_u32(syllable));
+    }
+    composed
+}
+
+// Splits syllables and compound jamo into the jamo typed for them
+pub fn decompose(text: &str) -> String {
+    let mut decomposed = String::with_capacity(text.len() * 3);
+    let push = |decomposed: &mut String, jamo: char| match keystrokes(jamo) {
+        Some((first, second)) => {
+            decomposed.push(first);
+            decomposed.push(second);
+        }
+        None => decomposed.push(jamo),
+    };
+    for c in text.chars() {
+        let code = c as u32;
+        if !(FIRST_SYLLABLE..=LAST_SYLLABLE).contains(&code) {
+            push(&mut decomposed, c);
+            continue;
+        }
+        let offset = code - FIRST_SYLLABLE;
+        let jamo = [
+            INITIALS.chars().nth((offset / 28 / 21) as usize),
+            MEDIALS.chars().nth((offset / 28 % 21) as usize),
+            (offset % 28)
+                .checked_sub(1)
+                .and_then(|final_index| FINALS.chars().nth(final_index as usize)),
+        ];
+        for jamo in jamo.into_iter().flatten() {
+            push(&mut decomposed, jamo);
+        }
+    }
+    decomposed
+}
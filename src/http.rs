@@ -0,0 +1,597 @@
+use crate::cache::ConversionCache;
+use crate::config::Config;
+use crate::detect::Language;
+use crate::layouts::{self, LayoutCode, LayoutId, LayoutOptions, YoPolicy};
+#[cfg(unix)]
+use crate::systemd;
+use crate::visualize::{self, ImageFormat, Overlay};
+use crate::{
+    analyze, canonical, diff, document, drills, edits, emacs, keys, models, profiles, registry,
+    shortcuts, undo,
+};
+use actix_web::dev::Payload;
+use actix_web::error::InternalError;
+use actix_web::middleware::Logger;
+use actix_web::{
+    get, post, web, App, FromRequest, HttpRequest, HttpResponse, HttpServer, Responder, Scope,
+};
+use jsonwebtoken::{DecodingKey, Validation};
+use std::collections::HashSet;
+use std::future::{ready, Ready};
+use std::str::FromStr;
+
+pub struct AppState {
+    jwt_secret: Option<String>,
+    max_payload_bytes: usize,
+    max_text_chars: usize,
+    cache: ConversionCache,
+}
+
+impl AppState {
+    pub fn new(config: &Config) -> Self {
+        AppState {
+            jwt_secret: config.auth.jwt_secret.clone(),
+            max_payload_bytes: config.limits.max_payload_bytes,
+            max_text_chars: config.limits.max_text_chars,
+            cache: ConversionCache::new(&config.cache),
+        }
+    }
+}
+
+fn error_response(mut builder: actix_web::HttpResponseBuilder, message: &str) -> HttpResponse {
+    builder.json(serde_json::json!({"status": "error", "message": message}))
+}
+
+// A built-in layout for the endpoints other than `/convert`. Parameters
+// picking a variant, like `latvian?variant=ergonomic`, work as they do there;
+// conversion options like `yo` do not apply and are rejected rather than
+// ignored.
+fn parse_layout(id: &str) -> Result<LayoutCode, HttpResponse> {
+    match LayoutId::from_str(id) {
+        Ok(id) if id.options == LayoutOptions::default() => Ok(id.layout),
+        Ok(_) => Err(error_response(
+            HttpResponse::BadRequest(),
+            &format!("Layout options only apply to /convert, not to '{}'.", id),
+        )),
+        Err(()) => Err(error_response(
+            HttpResponse::BadRequest(),
+            "Invalid layout code provided.",
+        )),
+    }
+}
+
+// The error response for texts over the configured length limit
+fn check_text_len(state: &AppState, text: &str) -> Result<(), HttpResponse> {
+    if text.chars().count() > state.max_text_chars {
+        return Err(error_response(
+            HttpResponse::PayloadTooLarge(),
+            &format!("Text is longer than {} characters.", state.max_text_chars),
+        ));
+    }
+    Ok(())
+}
+
+// Extracting this succeeds when auth is disabled or the request carries a
+// valid `Authorization: Bearer <jwt>` header
+pub struct Authorized;
+
+impl FromRequest for Authorized {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let secret = req
+            .app_data::<web::Data<AppState>>()
+            .and_then(|state| state.jwt_secret.as_deref());
+        let Some(secret) = secret else {
+            return ready(Ok(Authorized));
+        };
+
+        let token = req
+            .headers()
+            .get("Authorization")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        let valid = token.is_some_and(|token| {
+            jsonwebtoken::decode::<serde_json::Value>(
+                token,
+                &DecodingKey::from_secret(secret.as_bytes()),
+                &Validation::default(),
+            )
+            .is_ok()
+        });
+
+        if valid {
+            ready(Ok(Authorized))
+        } else {
+            let response = error_response(
+                HttpResponse::Unauthorized(),
+                "Missing or invalid bearer token.",
+            );
+            ready(Err(
+                InternalError::from_response("unauthorized", response).into()
+            ))
+        }
+    }
+}
+
+#[get("/healthchecker")]
+async fn health_checker_handler() -> impl Responder {
+    const MESSAGE: &str = "JWT Authentication in Rust using Actix-web, Postgres, and SQLX";
+
+    HttpResponse::Ok().json(serde_json::json!({"status": "success", "message": MESSAGE}))
+}
+
+#[post("/convert")]
+async fn convert_text_handler(
+    _: Authorized,
+    state: web::Data<AppState>,
+    text_schema: web::Json<models::TextSchema>,
+) -> impl Responder {
+    if let Err(response) = check_text_len(&state, &text_schema.text) {
+        return response;
+    }
+
+    let from_result = registry::resolve_with_options(&text_schema.from);
+    let to_result = registry::resolve_with_options(&text_schema.to);
+
+    if let (Some((from, from_options)), Some((to, to_options))) = (from_result, to_result) {
+        let yo_policy = if text_schema.fold_yo {
+            YoPolicy::FoldToE
+        } else {
+            text_schema.yo_policy
+        };
+        let options = registry::ConvertOptions {
+            keep_punctuation: text_schema.keep_punctuation,
+            yo_policy,
+            ..Default::default()
+        }
+        .with(to_options.or(from_options));
+        let converted_text = state.cache.get_or_convert(
+            &text_schema.from,
+            &text_schema.to,
+            &options,
+            &text_schema.text,
+            || registry::convert_text_with(&text_schema.text, &from, &to, &options),
+        );
+        let data = match text_schema.format {
+            models::ResultFormat::Text => serde_json::json!(converted_text),
+            models::ResultFormat::Spans => {
+                serde_json::json!(edits::word_edits(&text_schema.text, &converted_text))
+            }
+        };
+        let mut response = serde_json::json!({"status": "success", "data": data});
+        if text_schema.undo_token {
+            match undo::token(
+                &text_schema.text,
+                &converted_text,
+                &text_schema.from,
+                &text_schema.to,
+            ) {
+                Ok(token) => response["undo_token"] = serde_json::json!(token),
+                Err(message) => {
+                    return error_response(HttpResponse::InternalServerError(), &message)
+                }
+            }
+        }
+        HttpResponse::Ok().json(response)
+    } else {
+        error_response(HttpResponse::BadRequest(), "Invalid layout codes provided.")
+    }
+}
+
+#[post("/undo")]
+async fn undo_handler(_: Authorized, undo_schema: web::Json<models::UndoSchema>) -> impl Responder {
+    match undo::undo(&undo_schema.text, &undo_schema.undo_token) {
+        Ok(reverted) => {
+            HttpResponse::Ok().json(serde_json::json!({"status": "success", "data": reverted}))
+        }
+        Err(message) => error_response(HttpResponse::BadRequest(), &message),
+    }
+}
+
+#[post("/keys/type")]
+async fn type_keys_handler(
+    _: Authorized,
+    schema: web::Json<models::KeyEventsSchema>,
+) -> impl Responder {
+    let layout = match parse_layout(&schema.layout) {
+        Ok(layout) => layout,
+        Err(response) => return response,
+    };
+    match keys::type_keys(&schema.events, layout) {
+        Ok(text) => HttpResponse::Ok().json(serde_json::json!({"status": "success", "data": text})),
+        Err(message) => error_response(HttpResponse::BadRequest(), &message),
+    }
+}
+
+#[post("/keys/events")]
+async fn key_events_handler(
+    _: Authorized,
+    state: web::Data<AppState>,
+    schema: web::Json<models::LayoutTextSchema>,
+) -> impl Responder {
+    if let Err(response) = check_text_len(&state, &schema.text) {
+        return response;
+    }
+    let layout = match parse_layout(&schema.layout) {
+        Ok(layout) => layout,
+        Err(response) => return response,
+    };
+    match keys::key_events(&schema.text, layout) {
+        Ok(events) => {
+            HttpResponse::Ok().json(serde_json::json!({"status": "success", "data": events}))
+        }
+        Err(message) => error_response(HttpResponse::BadRequest(), &message),
+    }
+}
+
+#[post("/shortcuts")]
+async fn shortcuts_handler(
+    _: Authorized,
+    schema: web::Json<models::ShortcutsSchema>,
+) -> impl Responder {
+    let (from, to) = match (parse_layout(&schema.from), parse_layout(&schema.to)) {
+        (Ok(from), Ok(to)) => (from, to),
+        (Err(response), _) | (_, Err(response)) => return response,
+    };
+    let remapped: Vec<String> = schema
+        .shortcuts
+        .iter()
+        .map(|shortcut| shortcuts::remap_shortcut(shortcut, from, to, schema.semantics))
+        .collect();
+    HttpResponse::Ok().json(serde_json::json!({"status": "success", "data": remapped}))
+}
+
+#[post("/remap/emacs")]
+async fn remap_emacs_handler(
+    _: Authorized,
+    schema: web::Json<models::RemapSchema>,
+) -> impl Responder {
+    let (from, to) = match (parse_layout(&schema.from), parse_layout(&schema.to)) {
+        (Ok(from), Ok(to)) => (from, to),
+        (Err(response), _) | (_, Err(response)) => return response,
+    };
+    let remapped = emacs::remap_elisp(&schema.source, from, to);
+    HttpResponse::Ok().json(serde_json::json!({"status": "success", "data": remapped}))
+}
+
+#[post("/remap/profile")]
+async fn remap_profile_handler(
+    _: Authorized,
+    schema: web::Json<models::ProfileSchema>,
+) -> impl Responder {
+    let (from, to) = match (parse_layout(&schema.from), parse_layout(&schema.to)) {
+        (Ok(from), Ok(to)) => (from, to),
+        (Err(response), _) | (_, Err(response)) => return response,
+    };
+    let fields: Vec<&str> = match &schema.fields {
+        Some(fields) => fields.iter().map(String::as_str).collect(),
+        None => profiles::DEFAULT_FIELDS.to_vec(),
+    };
+    match profiles::remap_profile(&schema.profile, schema.format, from, to, &fields) {
+        Ok(remapped) => {
+            HttpResponse::Ok().json(serde_json::json!({"status": "success", "data": remapped}))
+        }
+        Err(message) => error_response(HttpResponse::BadRequest(), &message),
+    }
+}
+
+#[post("/analyze")]
+async fn analyze_handler(
+    _: Authorized,
+    state: web::Data<AppState>,
+    analyze_schema: web::Json<models::AnalyzeSchema>,
+) -> impl Responder {
+    if let Err(response) = check_text_len(&state, &analyze_schema.text) {
+        return response;
+    }
+    let layout = match parse_layout(&analyze_schema.layout) {
+        Ok(layout) => layout,
+        Err(response) => return response,
+    };
+    let report = analyze::analyze(&analyze_schema.text, layout);
+    HttpResponse::Ok().json(serde_json::json!({"status": "success", "data": report}))
+}
+
+#[post("/analyze/compare")]
+async fn compare_handler(
+    _: Authorized,
+    state: web::Data<AppState>,
+    compare_schema: web::Json<models::CompareSchema>,
+) -> impl Responder {
+    if let Err(response) = check_text_len(&state, &compare_schema.text) {
+        return response;
+    }
+    let mut layouts = match compare_schema
+        .layouts
+        .iter()
+        .map(|layout| parse_layout(layout))
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(layouts) => layouts,
+        Err(response) => return response,
+    };
+    let mut seen = HashSet::new();
+    layouts.retain(|&layout| seen.insert(layout));
+    if layouts.is_empty() {
+        return error_response(HttpResponse::BadRequest(), "No layouts to compare.");
+    }
+    let ranked = analyze::compare(&compare_schema.text, &layouts, compare_schema.rank_by);
+    HttpResponse::Ok().json(serde_json::json!({
+        "status": "success",
+        "data": {"rank_by": compare_schema.rank_by, "layouts": ranked},
+    }))
+}
+
+#[post("/document")]
+async fn document_handler(
+    _: Authorized,
+    state: web::Data<AppState>,
+    document_schema: web::Json<models::DocumentSchema>,
+) -> impl Responder {
+    if let Err(response) = check_text_len(&state, &document_schema.text) {
+        return response;
+    }
+    let layouts = match document_schema
+        .layouts
+        .iter()
+        .map(|layout| parse_layout(layout))
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(layouts) => layouts,
+        Err(response) => return response,
+    };
+    let min_confidence = document_schema
+        .min_confidence
+        .unwrap_or(document::DEFAULT_MIN_CONFIDENCE);
+    if !(0.0..=1.0).contains(&min_confidence) {
+        return error_response(
+            HttpResponse::BadRequest(),
+            "Minimum confidence must be between 0 and 1.",
+        );
+    }
+    let document = document::fix(&document_schema.text, &layouts, min_confidence);
+    HttpResponse::Ok().json(serde_json::json!({"status": "success", "data": document}))
+}
+
+// Registered before `/layouts/{id}`, which would match it too
+#[get("/layouts/similarity")]
+async fn similarity_handler(
+    _: Authorized,
+    query: web::Query<models::SimilarityQuery>,
+) -> impl Responder {
+    let (a, b) = match (parse_layout(&query.a), parse_layout(&query.b)) {
+        (Ok(a), Ok(b)) => (a, b),
+        (Err(response), _) | (_, Err(response)) => return response,
+    };
+    HttpResponse::Ok()
+        .json(serde_json::json!({"status": "success", "data": diff::similarity(a, b)}))
+}
+
+#[get("/layouts/{id}")]
+async fn layout_handler(_: Authorized, req: HttpRequest, id: web::Path<String>) -> impl Responder {
+    // Layout options arrive as the query string, like `russian?yo=false`
+    let id = match req.query_string() {
+        "" => id.into_inner(),
+        query => format!("{}?{}", id, query),
+    };
+    match registry::resolve_with_options(&id) {
+        Some((layout, options)) if options == LayoutOptions::default() => HttpResponse::Ok()
+            .json(serde_json::json!({"status": "success", "data": canonical::export(&layout)})),
+        Some(_) => error_response(
+            HttpResponse::BadRequest(),
+            &format!("Layout options only apply to /convert, not to '{}'.", id),
+        ),
+        None => error_response(HttpResponse::NotFound(), "Unknown layout."),
+    }
+}
+
+// Most lines a drill may have
+const MAX_DRILL_LINES: usize = 100;
+
+#[get("/layouts/{id}/drills")]
+async fn drills_handler(
+    _: Authorized,
+    id: web::Path<String>,
+    query: web::Query<models::DrillQuery>,
+) -> impl Responder {
+    let (layout, from) = match (
+        parse_layout(&id),
+        parse_layout(query.from.as_deref().unwrap_or("qwerty")),
+    ) {
+        (Ok(layout), Ok(from)) => (layout, from),
+        (Err(response), _) | (_, Err(response)) => return response,
+    };
+    let language = match &query.lang {
+        Some(lang) => Language::from_str(lang).ok(),
+        None => Language::of(layout),
+    };
+    let Some(language) = language else {
+        return error_response(HttpResponse::BadRequest(), "Invalid or missing language.");
+    };
+    let lines = query.lines.unwrap_or(5);
+    if lines > MAX_DRILL_LINES {
+        return error_response(
+            HttpResponse::BadRequest(),
+            &format!("A drill has at most {} lines.", MAX_DRILL_LINES),
+        );
+    }
+    match drills::drill(
+        layout,
+        from,
+        query.focus,
+        language,
+        lines,
+        query.seed.unwrap_or(0),
+    ) {
+        Ok(drill) => {
+            HttpResponse::Ok().json(serde_json::json!({"status": "success", "data": drill}))
+        }
+        Err(message) => error_response(HttpResponse::BadRequest(), &message),
+    }
+}
+
+// Largest `scale` of a PNG keyboard image
+#[cfg(feature = "png")]
+const MAX_IMAGE_SCALE: f32 = 8.0;
+
+#[get("/layouts/{id}/keyboard")]
+async fn keyboard_handler(
+    _: Authorized,
+    state: web::Data<AppState>,
+    id: web::Path<String>,
+    query: web::Query<models::KeyboardQuery>,
+) -> impl Responder {
+    let layout = match parse_layout(&id) {
+        Ok(layout) => layout,
+        Err(response) => return response,
+    };
+    let overlay = match query.overlay {
+        models::OverlayKind::None => Overlay::None,
+        models::OverlayKind::Fingers => Overlay::Fingers,
+        models::OverlayKind::Diff => {
+            match parse_layout(query.from.as_deref().unwrap_or("qwerty")) {
+                Ok(from) => Overlay::Diff(from),
+                Err(response) => return response,
+            }
+        }
+        models::OverlayKind::Heatmap => match (&query.text, &query.lang) {
+            (Some(text), _) => {
+                if let Err(response) = check_text_len(&state, text) {
+                    return response;
+                }
+                Overlay::Heatmap(visualize::key_presses(text, layout), query.heat_scale)
+            }
+            (None, lang) => {
+                let language = match lang {
+                    Some(lang) => Language::from_str(lang).ok(),
+                    None => Language::of(layout),
+                };
+                let Some(language) = language else {
+                    return error_response(
+                        HttpResponse::BadRequest(),
+                        "A heatmap needs a text or a language.",
+                    );
+                };
+                Overlay::Heatmap(
+                    visualize::letter_presses(language, layout),
+                    query.heat_scale,
+                )
+            }
+        },
+    };
+
+    let svg = visualize::svg_keyboard(layout, &overlay);
+    match query.format {
+        ImageFormat::Svg => HttpResponse::Ok().content_type("image/svg+xml").body(svg),
+        #[cfg(feature = "png")]
+        ImageFormat::Png => {
+            let scale = query.scale.unwrap_or(1.0);
+            if !(scale > 0.0 && scale <= MAX_IMAGE_SCALE) {
+                return error_response(
+                    HttpResponse::BadRequest(),
+                    &format!("The scale must be above 0 and at most {}.", MAX_IMAGE_SCALE),
+                );
+            }
+            match visualize::png(&svg, scale) {
+                Ok(png) => HttpResponse::Ok().content_type("image/png").body(png),
+                Err(message) => error_response(HttpResponse::InternalServerError(), &message),
+            }
+        }
+        #[cfg(not(feature = "png"))]
+        ImageFormat::Png => error_response(
+            HttpResponse::BadRequest(),
+            "PNG images need keymorph built with the png feature.",
+        ),
+    }
+}
+
+#[post("/heatmap")]
+async fn heatmap_handler(
+    _: Authorized,
+    state: web::Data<AppState>,
+    heatmap_schema: web::Json<models::HeatmapSchema>,
+) -> impl Responder {
+    if let Err(response) = check_text_len(&state, &heatmap_schema.text) {
+        return response;
+    }
+    let layout = match parse_layout(&heatmap_schema.layout) {
+        Ok(layout) => layout,
+        Err(response) => return response,
+    };
+    let presses = visualize::key_presses(&heatmap_schema.text, layout);
+    let overlay = Overlay::Heatmap(presses, heatmap_schema.heat_scale);
+    HttpResponse::Ok()
+        .content_type("image/svg+xml")
+        .body(visualize::svg_keyboard(layout, &overlay))
+}
+
+// The API routes under `path`, for mounting in another actix application.
+// Create the state once, outside the `HttpServer::new` factory, so the
+// workers share one cache:
+//
+//     let state = web::Data::new(AppState::new(&config));
+//     HttpServer::new(move || App::new().service(http::scope("/tools/keymorph", state.clone())))
+pub fn scope(path: &str, state: web::Data<AppState>) -> Scope {
+    let max_payload_bytes = state.max_payload_bytes;
+    web::scope(path)
+        .app_data(state)
+        .app_data(web::JsonConfig::default().limit(max_payload_bytes))
+        .service(health_checker_handler)
+        .service(convert_text_handler)
+        .service(undo_handler)
+        .service(type_keys_handler)
+        .service(key_events_handler)
+        .service(shortcuts_handler)
+        .service(remap_emacs_handler)
+        .service(remap_profile_handler)
+        .service(analyze_handler)
+        .service(compare_handler)
+        .service(document_handler)
+        .service(similarity_handler)
+        .service(layout_handler)
+        .service(drills_handler)
+        .service(keyboard_handler)
+        .service(heatmap_handler)
+}
+
+pub async fn serve(config: &Config) -> std::io::Result<()> {
+    let state = web::Data::new(AppState::new(config));
+
+    let mut server = HttpServer::new(move || {
+        App::new()
+            .wrap(Logger::default())
+            .service(scope("/api", state.clone()))
+    });
+    if let Some(workers) = config.server.workers {
+        server = server.workers(workers);
+    }
+
+    #[cfg(unix)]
+    let listeners = systemd::listeners();
+    #[cfg(not(unix))]
+    let listeners: Vec<()> = Vec::new();
+
+    if listeners.is_empty() {
+        server = server.bind((config.server.host.as_str(), config.server.port))?;
+    }
+    #[cfg(unix)]
+    for listener in listeners {
+        server = match listener {
+            systemd::Listener::Tcp(listener) => server.listen(listener)?,
+            systemd::Listener::Unix(listener) => server.listen_uds(listener)?,
+        };
+    }
+
+    // Build the keymaps before telling systemd we are ready, so the first
+    // request does not pay for it
+    layouts::keymap(LayoutCode::Qwerty, LayoutCode::Russian);
+    #[cfg(unix)]
+    if let Err(err) = systemd::notify("READY=1") {
+        log::warn!("cannot notify systemd: {}", err);
+    }
+
+    println!("🚀 Server started successfully");
+    server.run().await
+}
@@ -0,0 +1,379 @@
+// IBus input method engine. Typed words are held as preedit text with a
+// candidate popup offering the word as typed and as it reads on the other
+// layout, the likelier one first; space, enter and other non-letter keys
+// commit the selected candidate. The engine keeps the system keyboard layout
+// and reads characters from the keysyms it produces, so switching layouts
+// still works as usual.
+//
+// ibus-daemon starts the engine from the component file printed by
+// `keymorph ime --xml`. Fcitx5 cannot host IBus engines and is not
+// supported.
+use crate::detect;
+use crate::layouts::LayoutCode;
+use std::collections::HashMap;
+use std::sync::Arc;
+use zbus::object_server::{ObjectServer, SignalContext};
+use zbus::zvariant::{ObjectPath, OwnedObjectPath, StructureBuilder, Value};
+use zbus::{blocking, interface};
+
+pub const BUS_NAME: &str = "org.freedesktop.IBus.Keymorph";
+const FACTORY_PATH: &str = "/org/freedesktop/IBus/Factory";
+
+// Keysyms and modifier masks, from X11 keysymdef.h and IBus
+const KEY_BACKSPACE: u32 = 0xff08;
+const KEY_ESCAPE: u32 = 0xff1b;
+const KEY_UP: u32 = 0xff52;
+const KEY_DOWN: u32 = 0xff54;
+const SHIFT_MASK: u32 = 1 << 0;
+const LOCK_MASK: u32 = 1 << 1;
+const RELEASE_MASK: u32 = 1 << 30;
+
+const CYRILLIC_LOWER: &str = "юабцдефгхийклмнопярстужвьызшэщчъ";
+const CYRILLIC_UPPER: &str = "ЮАБЦДЕФГХИЙКЛМНОПЯРСТУЖВЬЫЗШЭЩЧЪ";
+
+// Character for a keysym: Latin-1 keysyms are their code points, Unicode
+// keysyms are offset by 0x01000000, and the Cyrillic block has its own
+// ordering
+fn keysym_char(keyval: u32) -> Option<char> {
+    match keyval {
+        0x20..=0x7e | 0xa0..=0xff => char::from_u32(keyval),
+        0x6a3 => Some('ё'),
+        0x6b3 => Some('Ё'),
+        0x6c0..=0x6df => CYRILLIC_LOWER.chars().nth((keyval - 0x6c0) as usize),
+        0x6e0..=0x6ff => CYRILLIC_UPPER.chars().nth((keyval - 0x6e0) as usize),
+        0x0100_0000..=0x0110_ffff => char::from_u32(keyval - 0x0100_0000),
+        _ => None,
+    }
+}
+
+fn attributes() -> HashMap<String, Value<'static>> {
+    HashMap::new()
+}
+
+// An `IBusText` serialized the way ibus-daemon expects it
+fn ibus_text(text: &str) -> Value<'static> {
+    let attrs = StructureBuilder::new()
+        .add_field("IBusAttrList")
+        .add_field(attributes())
+        .add_field(Vec::<Value>::new())
+        .build();
+    Value::from(
+        StructureBuilder::new()
+            .add_field("IBusText")
+            .add_field(attributes())
+            .add_field(text.to_string())
+            .add_field(Value::from(attrs))
+            .build(),
+    )
+}
+
+fn lookup_table(candidates: &[String], cursor: usize) -> Value<'static> {
+    let candidates: Vec<Value> = candidates.iter().map(|c| ibus_text(c)).collect();
+    let labels: Vec<Value> = (1..=candidates.len())
+        .map(|label| ibus_text(&label.to_string()))
+        .collect();
+    Value::from(
+        StructureBuilder::new()
+            .add_field("IBusLookupTable")
+            .add_field(attributes())
+            .add_field(candidates.len() as u32)
+            .add_field(cursor as u32)
+            .add_field(true)
+            .add_field(true)
+            // Vertical
+            .add_field(1i32)
+            .add_field(candidates)
+            .add_field(labels)
+            .build(),
+    )
+}
+
+struct Engine {
+    layouts: Arc<Vec<LayoutCode>>,
+    word: String,
+    candidates: Vec<String>,
+    cursor: usize,
+}
+
+impl Engine {
+    fn new(layouts: Arc<Vec<LayoutCode>>) -> Self {
+        Engine {
+            layouts,
+            word: String::new(),
+            candidates: Vec::new(),
+            cursor: 0,
+        }
+    }
+
+    fn update_candidates(&mut self) {
+        self.cursor = 0;
+        self.candidates = vec![self.word.clone()];
+        if let Some(alternative) = detect::alternative(&self.word, &self.layouts) {
+            if detect::fix_word(&self.word, &self.layouts).is_some() {
+                self.candidates.insert(0, alternative);
+            } else {
+                self.candidates.push(alternative);
+            }
+        }
+    }
+
+    async fn show(&self, ctxt: &SignalContext<'_>) -> zbus::Result<()> {
+        if self.word.is_empty() {
+            Engine::hide_lookup_table(ctxt).await?;
+            return Engine::update_preedit_text(ctxt, ibus_text(""), 0, false, 0).await;
+        }
+        let length = self.word.chars().count() as u32;
+        Engine::update_preedit_text(ctxt, ibus_text(&self.word), length, true, 0).await?;
+        if self.candidates.len() > 1 {
+            Engine::update_lookup_table(ctxt, lookup_table(&self.candidates, self.cursor), true)
+                .await
+        } else {
+            Engine::hide_lookup_table(ctxt).await
+        }
+    }
+
+    // Commits the selected candidate, or the word as typed
+    async fn commit(&mut self, ctxt: &SignalContext<'_>, as_typed: bool) -> zbus::Result<()> {
+        if self.word.is_empty() {
+            return Ok(());
+        }
+        let text = if as_typed {
+            std::mem::take(&mut self.word)
+        } else {
+            self.candidates
+                .get(self.cursor)
+                .cloned()
+                .unwrap_or_else(|| self.word.clone())
+        };
+        self.word.clear();
+        self.candidates.clear();
+        Engine::commit_text(ctxt, ibus_text(&text)).await?;
+        self.show(ctxt).await
+    }
+}
+
+#[interface(name = "org.freedesktop.IBus.Engine")]
+impl Engine {
+    async fn process_key_event(
+        &mut self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+        keyval: u32,
+        _keycode: u32,
+        state: u32,
+    ) -> bool {
+        if state & RELEASE_MASK != 0 {
+            return false;
+        }
+
+        let modifiers = state & !(SHIFT_MASK | LOCK_MASK);
+        let result = match keysym_char(keyval) {
+            // Letters, digits and punctuation are all part of a word, since
+            // they sit on letter keys in some layout
+            Some(c) if modifiers == 0 && !c.is_whitespace() => {
+                self.word.push(c);
+                self.update_candidates();
+                self.show(&ctxt).await.map(|()| true)
+            }
+            _ if self.word.is_empty() => Ok(false),
+            _ if modifiers != 0 => self.commit(&ctxt, true).await.map(|()| false),
+            _ => match keyval {
+                KEY_BACKSPACE => {
+                    self.word.pop();
+                    self.update_candidates();
+                    self.show(&ctxt).await.map(|()| true)
+                }
+                KEY_ESCAPE => self.commit(&ctxt, true).await.map(|()| true),
+                KEY_UP | KEY_DOWN if self.candidates.len() > 1 => {
+                    self.cursor = (self.cursor + 1) % self.candidates.len();
+                    self.show(&ctxt).await.map(|()| true)
+                }
+                // Space, enter, arrows and the like commit and then do their
+                // usual thing
+                _ => self.commit(&ctxt, false).await.map(|()| false),
+            },
+        };
+        result.unwrap_or_else(|err| {
+            log::error!("cannot update the input method: {}", err);
+            false
+        })
+    }
+
+    async fn candidate_clicked(
+        &mut self,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+        index: u32,
+        _button: u32,
+        _state: u32,
+    ) {
+        if (index as usize) < self.candidates.len() {
+            self.cursor = index as usize;
+            if let Err(err) = self.commit(&ctxt, false).await {
+                log::error!("cannot update the input method: {}", err);
+            }
+        }
+    }
+
+    async fn focus_out(&mut self, #[zbus(signal_context)] ctxt: SignalContext<'_>) {
+        if let Err(err) = self.commit(&ctxt, true).await {
+            log::error!("cannot update the input method: {}", err);
+        }
+    }
+
+    async fn reset(&mut self, #[zbus(signal_context)] ctxt: SignalContext<'_>) {
+        self.word.clear();
+        self.candidates.clear();
+        if let Err(err) = self.show(&ctxt).await {
+            log::error!("cannot update the input method: {}", err);
+        }
+    }
+
+    fn focus_in(&self) {}
+
+    fn enable(&self) {}
+
+    fn disable(&mut self) {
+        self.word.clear();
+        self.candidates.clear();
+    }
+
+    fn set_capabilities(&self, _caps: u32) {}
+
+    fn set_cursor_location(&self, _x: i32, _y: i32, _w: i32, _h: i32) {}
+
+    fn set_surrounding_text(&self, _text: Value<'_>, _cursor_pos: u32, _anchor_pos: u32) {}
+
+    fn page_up(&self) {}
+
+    fn page_down(&self) {}
+
+    fn cursor_up(&self) {}
+
+    fn cursor_down(&self) {}
+
+    fn property_activate(&self, _name: &str, _state: u32) {}
+
+    fn property_show(&self, _name: &str) {}
+
+    fn property_hide(&self, _name: &str) {}
+
+    async fn destroy(
+        &self,
+        #[zbus(object_server)] server: &ObjectServer,
+        #[zbus(header)] header: zbus::message::Header<'_>,
+    ) {
+        if let Some(path) = header.path() {
+            server.remove::<Engine, _>(path.to_owned()).await.ok();
+        }
+    }
+
+    #[zbus(signal)]
+    async fn commit_text(ctxt: &SignalContext<'_>, text: Value<'_>) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn update_preedit_text(
+        ctxt: &SignalContext<'_>,
+        text: Value<'_>,
+        cursor_pos: u32,
+        visible: bool,
+        mode: u32,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn update_lookup_table(
+        ctxt: &SignalContext<'_>,
+        table: Value<'_>,
+        visible: bool,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn hide_lookup_table(ctxt: &SignalContext<'_>) -> zbus::Result<()>;
+}
+
+struct Factory {
+    layouts: Arc<Vec<LayoutCode>>,
+    engines: u32,
+}
+
+#[interface(name = "org.freedesktop.IBus.Factory")]
+impl Factory {
+    async fn create_engine(
+        &mut self,
+        #[zbus(object_server)] server: &ObjectServer,
+        _name: &str,
+    ) -> zbus::fdo::Result<OwnedObjectPath> {
+        self.engines += 1;
+        let path = ObjectPath::try_from(format!(
+            "/org/freedesktop/IBus/Engine/Keymorph/{}",
+            self.engines
+        ))
+        .map_err(|err| zbus::fdo::Error::Failed(err.to_string()))?;
+        server
+            .at(&path, Engine::new(self.layouts.clone()))
+            .await
+            .map_err(|err| zbus::fdo::Error::Failed(err.to_string()))?;
+        Ok(path.into())
+    }
+}
+
+// Address of the IBus bus, which is not the session bus
+fn address() -> Result<String, String> {
+    if let Ok(address) = std::env::var("IBUS_ADDRESS") {
+        return Ok(address);
+    }
+    let output = std::process::Command::new("ibus")
+        .arg("address")
+        .output()
+        .map_err(|err| format!("cannot run `ibus address`: {}", err))?;
+    let address = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || address.is_empty() || address == "(null)" {
+        return Err("ibus-daemon is not running".to_string());
+    }
+    Ok(address)
+}
+
+// Component description to install as /usr/share/ibus/component/keymorph.xml
+pub fn component_xml(exec: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<component>
+  <name>{name}</name>
+  <description>Keymorph wrong layout fixer</description>
+  <exec>{exec} ime --ibus</exec>
+  <version>{version}</version>
+  <license>MIT</license>
+  <textdomain>keymorph</textdomain>
+  <engines>
+    <engine>
+      <name>keymorph</name>
+      <language>other</language>
+      <license>MIT</license>
+      <longname>Keymorph</longname>
+      <description>Offers words typed in the wrong keyboard layout converted</description>
+      <layout>default</layout>
+      <rank>0</rank>
+    </engine>
+  </engines>
+</component>
+"#,
+        name = BUS_NAME,
+        exec = exec,
+        version = env!("CARGO_PKG_VERSION"),
+    )
+}
+
+// Connects to ibus-daemon and serves engines until the process is stopped
+pub fn run(layouts: Vec<LayoutCode>) -> Result<(), String> {
+    let factory = Factory {
+        layouts: Arc::new(layouts),
+        engines: 0,
+    };
+    let _connection = blocking::connection::Builder::address(address()?.as_str())
+        .and_then(|builder| builder.serve_at(FACTORY_PATH, factory))
+        .and_then(|builder| builder.name(BUS_NAME))
+        .and_then(|builder| builder.build())
+        .map_err(|err| format!("cannot connect to ibus-daemon: {}", err))?;
+    log::info!("input method engine ready");
+    loop {
+        std::thread::park();
+    }
+}
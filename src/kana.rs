@@ -0,0 +1,92 @@
+// Voiced kana for the JIS kana layouts. The dakuten key typed after a kana
+// voices it (か ゛ → が) and the handakuten key gives the half-voiced forms
+// of the h-row (は ゜ → ぱ). Katakana compose the same way as hiragana.
+
+const DAKUTEN: char = '゛';
+const HANDAKUTEN: char = '゜';
+
+// The voicing marks, typed after the kana they voice
+pub const MARKS: [char; 2] = [DAKUTEN, HANDAKUTEN];
+
+// (mark, kana it follows, the voiced kana), in hiragana
+const VOICED: [(char, &str, &str); 2] = [
+    (
+        DAKUTEN,
+        "うかきくけこさしすせそたちつてとはひふへほ",
+        "ゔがぎぐげござじずぜぞだぢづでどばびぶべぼ",
+    ),
+    (HANDAKUTEN, "はひふへほ", "ぱぴぷぺぽ"),
+];
+
+// Distance from a hiragana to the same katakana
+const KATAKANA_OFFSET: u32 = 0x60;
+
+// Katakana for a hiragana, or the character itself
+pub fn katakana(c: char) -> char {
+    match c {
+        '\u{3041}'..='\u{3096}' => char::from_u32(c as u32 + KATAKANA_OFFSET).unwrap_or(c),
+        _ => c,
+    }
+}
+
+fn hiragana(c: char) -> char {
+    match c {
+        '\u{30A1}'..='\u{30F6}' => char::from_u32(c as u32 - KATAKANA_OFFSET).unwrap_or(c),
+        _ => c,
+    }
+}
+
+// Voiced kana, which take two keystrokes
+pub fn voiced(katakana: bool) -> impl Iterator<Item = char> {
+    VOICED
+        .iter()
+        .flat_map(|(_, _, voiced)| voiced.chars())
+        .map(move |c| if katakana { self::katakana(c) } else { c })
+}
+
+// Joins each kana followed by a voicing mark into the voiced kana. Marks
+// after anything else are kept as typed.
+pub fn compose(text: &str) -> String {
+    let mut composed = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let is_katakana = hiragana(c) != c;
+        let joined = chars.peek().and_then(|&mark| {
+            let (_, bases, voiced) = VOICED.iter().find(|(m, _, _)| *m == mark)?;
+            let index = bases.chars().position(|base| base == hiragana(c))?;
+            voiced.chars().nth(index)
+        });
+        match joined {
+            Some(joined) => {
+                composed.push(if is_katakana {
+                    katakana(joined)
+                } else {
+                    joined
+                });
+                chars.next();
+            }
+            None => composed.push(c),
+        }
+    }
+    composed
+}
+
+// Splits voiced kana into the kana and the mark typed after it
+pub fn decompose(text: &str) -> String {
+    let mut decomposed = String::with_capacity(text.len());
+    for c in text.chars() {
+        let is_katakana = hiragana(c) != c;
+        let split = VOICED.iter().find_map(|&(mark, bases, voiced)| {
+            let index = voiced.chars().position(|v| v == hiragana(c))?;
+            Some((bases.chars().nth(index)?, mark))
+        });
+        match split {
+            Some((base, mark)) => {
+                decomposed.push(if is_katakana { katakana(base) } else { base });
+                decomposed.push(mark);
+            }
+            None => decomposed.push(c),
+        }
+    }
+    decomposed
+}
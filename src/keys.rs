@@ -0,0 +1,199 @@
+// Key events in and out of layouts, for test harnesses and typing tutors
+// that need to know what a sequence of key presses types on a layout, or
+// which presses type a text. Keys are named by their `KeyboardEvent.code`
+// (KeyA, Digit1, BracketLeft, ...), which names the physical key the way
+// QWERTY labels it whatever layout is active.
+use crate::layouts::{self, LayoutCode};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub key: String,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub shift: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub alt_gr: bool,
+    // Presses with Ctrl, Alt or Meta held are shortcuts and type nothing
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub ctrl: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub alt: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub meta: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+impl KeyEvent {
+    fn new(key: &str, shift: bool, alt_gr: bool) -> Self {
+        KeyEvent {
+            key: key.to_string(),
+            shift,
+            alt_gr,
+            ctrl: false,
+            alt: false,
+            meta: false,
+        }
+    }
+}
+
+// The code of each key and what it types unshifted on QWERTY
+pub(crate) const CODES: [(&str, char); 47] = [
+    ("Backquote", '`'),
+    ("Digit1", '1'),
+    ("Digit2", '2'),
+    ("Digit3", '3'),
+    ("Digit4", '4'),
+    ("Digit5", '5'),
+    ("Digit6", '6'),
+    ("Digit7", '7'),
+    ("Digit8", '8'),
+    ("Digit9", '9'),
+    ("Digit0", '0'),
+    ("Minus", '-'),
+    ("Equal", '='),
+    ("KeyQ", 'q'),
+    ("KeyW", 'w'),
+    ("KeyE", 'e'),
+    ("KeyR", 'r'),
+    ("KeyT", 't'),
+    ("KeyY", 'y'),
+    ("KeyU", 'u'),
+    ("KeyI", 'i'),
+    ("KeyO", 'o'),
+    ("KeyP", 'p'),
+    ("BracketLeft", '['),
+    ("BracketRight", ']'),
+    ("Backslash", '\\'),
+    ("KeyA", 'a'),
+    ("KeyS", 's'),
+    ("KeyD", 'd'),
+    ("KeyF", 'f'),
+    ("KeyG", 'g'),
+    ("KeyH", 'h'),
+    ("KeyJ", 'j'),
+    ("KeyK", 'k'),
+    ("KeyL", 'l'),
+    ("Semicolon", ';'),
+    ("Quote", '\''),
+    ("KeyZ", 'z'),
+    ("KeyX", 'x'),
+    ("KeyC", 'c'),
+    ("KeyV", 'v'),
+    ("KeyB", 'b'),
+    ("KeyN", 'n'),
+    ("KeyM", 'm'),
+    ("Comma", ','),
+    ("Period", '.'),
+    ("Slash", '/'),
+];
+
+// Keys that type the same on every layout
+const WHITESPACE: [(&str, char); 3] = [("Space", ' '), ("Enter", '\n'), ("Tab", '\t')];
+
+// Keys that type nothing by themselves
+const MODIFIERS: [&str; 11] = [
+    "ShiftLeft",
+    "ShiftRight",
+    "ControlLeft",
+    "ControlRight",
+    "AltLeft",
+    "AltRight",
+    "MetaLeft",
+    "MetaRight",
+    "CapsLock",
+    "Escape",
+    "ContextMenu",
+];
+
+// What `event` types on `layout` before composition, if anything
+fn keystroke(event: &KeyEvent, layout: LayoutCode) -> Result<Option<char>, String> {
+    if event.ctrl || event.alt || event.meta || MODIFIERS.contains(&event.key.as_str()) {
+        return Ok(None);
+    }
+    if let Some(&(_, c)) = WHITESPACE.iter().find(|(code, _)| *code == event.key) {
+        return Ok(Some(c));
+    }
+    let &(_, unshifted) = CODES
+        .iter()
+        .find(|(code, _)| *code == event.key)
+        .ok_or(format!("unknown key '{}'", event.key))?;
+    let key = if event.shift {
+        layouts::shift_pairs()
+            .find(|&(lower, _)| lower == unshifted)
+            .map_or(unshifted, |(_, upper)| upper)
+    } else {
+        unshifted
+    };
+    if event.alt_gr {
+        let altgr = layouts::altgr_keys(layout)
+            .iter()
+            .find(|altgr| altgr.key == unshifted)
+            .map(|altgr| {
+                if event.shift {
+                    altgr.shifted
+                } else {
+                    altgr.unshifted
+                }
+            });
+        // Keys without an AltGr character type what they do without it
+        if altgr.is_some() {
+            return Ok(altgr);
+        }
+    }
+    Ok(Some(layouts::char_at(layout, key)))
+}
+
+// The text `events` type on `layout`. Backspace takes back the keystroke
+// before it, so a dead key or a jamo can be corrected before it composes.
+pub fn type_keys(events: &[KeyEvent], layout: LayoutCode) -> Result<String, String> {
+    let mut keystrokes = String::new();
+    for event in events {
+        if event.key == "Backspace" && !(event.ctrl || event.alt || event.meta) {
+            keystrokes.pop();
+        } else if let Some(c) = keystroke(event, layout)? {
+            keystrokes.push(c);
+        }
+    }
+    Ok(layouts::compose(keystrokes, layout))
+}
+
+// The key events that type `text` on `layout`, preferring keys without AltGr
+pub fn key_events(text: &str, layout: LayoutCode) -> Result<Vec<KeyEvent>, String> {
+    let mut keys: HashMap<char, KeyEvent> = HashMap::new();
+    for altgr in layouts::altgr_keys(layout) {
+        let Some(&(code, _)) = CODES.iter().find(|&&(_, c)| c == altgr.key) else {
+            continue;
+        };
+        keys.insert(altgr.unshifted, KeyEvent::new(code, false, true));
+        keys.insert(altgr.shifted, KeyEvent::new(code, true, true));
+    }
+    for &(code, unshifted) in &CODES {
+        let shifted = layouts::shift_pairs()
+            .find(|&(lower, _)| lower == unshifted)
+            .map_or(unshifted, |(_, upper)| upper);
+        keys.insert(
+            layouts::char_at(layout, shifted),
+            KeyEvent::new(code, true, false),
+        );
+        keys.insert(
+            layouts::char_at(layout, unshifted),
+            KeyEvent::new(code, false, false),
+        );
+    }
+    for (code, c) in WHITESPACE {
+        keys.insert(c, KeyEvent::new(code, false, false));
+    }
+
+    layouts::decompose(text, layout)
+        .chars()
+        .map(|c| {
+            keys.get(&c)
+                .cloned()
+                .ok_or(format!("{:?} cannot be typed on {:?}", c, layout))
+        })
+        .collect()
+}
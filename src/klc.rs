@@ -0,0 +1,337 @@
+// Microsoft Keyboard Layout Creator source files (.klc), to build and
+// install a layout on Windows. The main block of the layout is written with
+// its AltGr levels; MSKLC reads the files as UTF-16 with CRLF line endings,
+// see `to_utf16`.
+use crate::detect::Language;
+use crate::layouts;
+use crate::registry::Layout;
+
+// Scan code and US virtual key of each key of each `layouts::QWERTY_ROWS`
+// row
+const KEYS: [&[(&str, &str)]; 4] = [
+    &[
+        ("29", "OEM_3"),
+        ("02", "1"),
+        ("03", "2"),
+        ("04", "3"),
+        ("05", "4"),
+        ("06", "5"),
+        ("07", "6"),
+        ("08", "7"),
+        ("09", "8"),
+        ("0a", "9"),
+        ("0b", "0"),
+        ("0c", "OEM_MINUS"),
+        ("0d", "OEM_PLUS"),
+    ],
+    &[
+        ("10", "Q"),
+        ("11", "W"),
+        ("12", "E"),
+        ("13", "R"),
+        ("14", "T"),
+        ("15", "Y"),
+        ("16", "U"),
+        ("17", "I"),
+        ("18", "O"),
+        ("19", "P"),
+        ("1a", "OEM_4"),
+        ("1b", "OEM_6"),
+        ("2b", "OEM_5"),
+    ],
+    &[
+        ("1e", "A"),
+        ("1f", "S"),
+        ("20", "D"),
+        ("21", "F"),
+        ("22", "G"),
+        ("23", "H"),
+        ("24", "J"),
+        ("25", "K"),
+        ("26", "L"),
+        ("27", "OEM_1"),
+        ("28", "OEM_7"),
+    ],
+    &[
+        ("2c", "Z"),
+        ("2d", "X"),
+        ("2e", "C"),
+        ("2f", "V"),
+        ("30", "B"),
+        ("31", "N"),
+        ("32", "M"),
+        ("33", "OEM_COMMA"),
+        ("34", "OEM_PERIOD"),
+        ("35", "OEM_2"),
+    ],
+];
+
+// Characters typing Ctrl codes with Ctrl held, on top of the letters
+const CONTROL_CHARS: [(&str, char); 3] = [
+    ("OEM_4", '\u{1b}'),
+    ("OEM_6", '\u{1d}'),
+    ("OEM_5", '\u{1c}'),
+];
+
+// Names MSKLC gives the keys outside the main block
+const KEY_NAMES: &str = "KEYNAME
+
+01\tEsc
+0e\tBackspace
+0f\tTab
+1c\tEnter
+1d\tCtrl
+2a\tShift
+36\t\"Right Shift\"
+37\t\"Num *\"
+38\tAlt
+39\tSpace
+3a\t\"Caps Lock\"
+3b\tF1
+3c\tF2
+3d\tF3
+3e\tF4
+3f\tF5
+40\tF6
+41\tF7
+42\tF8
+43\tF9
+44\tF10
+45\tPause
+46\t\"Scroll Lock\"
+47\t\"Num 7\"
+48\t\"Num 8\"
+49\t\"Num 9\"
+4a\t\"Num -\"
+4b\t\"Num 4\"
+4c\t\"Num 5\"
+4d\t\"Num 6\"
+4e\t\"Num +\"
+4f\t\"Num 1\"
+50\t\"Num 2\"
+51\t\"Num 3\"
+52\t\"Num 0\"
+53\t\"Num Del\"
+54\t\"Sys Req\"
+57\tF11
+58\tF12
+7c\tF13
+7d\tF14
+7e\tF15
+7f\tF16
+80\tF17
+81\tF18
+82\tF19
+83\tF20
+84\tF21
+85\tF22
+86\tF23
+87\tF24
+
+KEYNAME_EXT
+
+1c\t\"Num Enter\"
+1d\t\"Right Ctrl\"
+35\t\"Num /\"
+37\t\"Prnt Scrn\"
+38\t\"Right Alt\"
+45\t\"Num Lock\"
+46\tBreak
+47\tHome
+48\tUp
+49\t\"Page Up\"
+4b\tLeft
+4d\tRight
+4f\tEnd
+50\tDown
+51\t\"Page Down\"
+52\tInsert
+53\tDelete
+54\t<00>
+56\tHelp
+5b\t\"Left Windows\"
+5c\t\"Right Windows\"
+5d\tApplication
+";
+
+// Locale name, id and language name of the layouts of each language
+fn locale(layout: &Layout) -> (&'static str, &'static str, &'static str) {
+    let language = match layout {
+        Layout::Builtin(code) => Language::of(*code),
+        Layout::Custom(_) => None,
+    };
+    match language {
+        Some(Language::Russian) => ("ru-RU", "00000419", "Russian (Russia)"),
+        _ => ("en-US", "00000409", "English (United States)"),
+    }
+}
+
+// A character as MSKLC writes it: ASCII letters and digits as themselves,
+// everything else as four hex digits. Characters outside the Basic
+// Multilingual Plane do not fit and are left out.
+fn cell(c: Option<char>) -> String {
+    match c {
+        Some(c) if c.is_ascii_alphanumeric() => c.to_string(),
+        Some(c) if (c as u32) < 0x10000 => format!("{:04x}", c as u32),
+        _ => "-1".to_string(),
+    }
+}
+
+// The virtual key of each key, by row. On Latin layouts, keys typing an
+// ASCII letter, digit or punctuation get the virtual key US QWERTY has for
+// it, so that shortcuts like Ctrl+C follow the character; the other keys,
+// and every key of other scripts, keep the one of their position, or take
+// one left free.
+fn virtual_keys(layout: &Layout) -> Vec<Vec<&'static str>> {
+    let latin = ('a'..='z')
+        .filter(|&key| layout.char_at(key).is_ascii_alphabetic())
+        .count()
+        > 20;
+    let us = |c: char| {
+        if !latin {
+            return None;
+        }
+        layouts::QWERTY_ROWS
+            .iter()
+            .zip(KEYS)
+            .find_map(|((unshifted, _), keys)| {
+                Some(keys[unshifted.chars().position(|u| u == c)?].1)
+            })
+    };
+    let mut taken: Vec<&str> = Vec::new();
+    let mut vks: Vec<Vec<Option<&str>>> = layouts::QWERTY_ROWS
+        .iter()
+        .map(|(unshifted, _)| {
+            unshifted
+                .chars()
+                .map(|key| {
+                    let c = layout.char_at(key).to_ascii_lowercase();
+                    let vk = us(c).filter(|vk| !taken.contains(vk))?;
+                    taken.push(vk);
+                    Some(vk)
+                })
+                .collect()
+        })
+        .collect();
+    for (row, keys) in vks.iter_mut().zip(KEYS) {
+        for (vk, &(_, own)) in row.iter_mut().zip(keys) {
+            if vk.is_none() && !taken.contains(&own) {
+                *vk = Some(own);
+                taken.push(own);
+            }
+        }
+    }
+    let mut free = KEYS
+        .iter()
+        .flat_map(|keys| keys.iter().map(|&(_, vk)| vk))
+        .filter(|vk| !taken.contains(vk))
+        .collect::<Vec<_>>()
+        .into_iter();
+    vks.into_iter()
+        .map(|row| {
+            row.into_iter()
+                .map(|vk| {
+                    vk.or_else(|| free.next())
+                        .expect("a virtual key for every key")
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// The name MSKLC builds the layout's DLL as: eight letters or digits at most
+fn dll_name(name: &str) -> String {
+    let name: String = name
+        .chars()
+        .filter(char::is_ascii_alphanumeric)
+        .take(8)
+        .collect();
+    if name.is_empty() {
+        "keymorph".to_string()
+    } else {
+        name
+    }
+}
+
+// The source of `layout` as a KLC file, for MSKLC to build an installer
+// from. Layouts that compose keystrokes, like Korean or Telex, type the
+// keystrokes only.
+pub fn export(layout: &Layout) -> String {
+    let altgr = match layout {
+        Layout::Builtin(code) => layouts::altgr_keys(*code),
+        Layout::Custom(_) => &[],
+    };
+    let (locale_name, locale_id, language_name) = locale(layout);
+    let description = format!("{} (keymorph)", layout.name());
+    let language_id = &locale_id[4..];
+
+    let mut klc = format!(
+        "KBD\t{}\t\"{}\"\n\n\
+         COPYRIGHT\t\"(c) keymorph\"\n\n\
+         COMPANY\t\"keymorph\"\n\n\
+         LOCALENAME\t\"{}\"\n\n\
+         LOCALEID\t\"{}\"\n\n\
+         VERSION\t1.0\n\n\
+         SHIFTSTATE\n\n\
+         0\t//Column 4\n\
+         1\t//Column 5 : Shft\n\
+         2\t//Column 6 :       Ctrl\n\
+         6\t//Column 7 :       Ctrl Alt\n\
+         7\t//Column 8 : Shft  Ctrl Alt\n\n\
+         LAYOUT\t\t;an extra '@' at the end is a dead key\n\n\
+         //SC\tVK_\t\tCap\t0\t1\t2\t6\t7\n\
+         //--\t----\t\t----\t----\t----\t----\t----\t----\n\n",
+        dll_name(layout.name()),
+        description,
+        locale_name,
+        locale_id
+    );
+    let vks = virtual_keys(layout);
+    for (((unshifted, shifted), keys), vks) in layouts::QWERTY_ROWS.iter().zip(KEYS).zip(vks) {
+        for (((key, shifted_key), &(scan_code, _)), vk) in
+            unshifted.chars().zip(shifted.chars()).zip(keys).zip(vks)
+        {
+            let lower = layout.char_at(key);
+            let upper = layout.char_at(shifted_key);
+            let caps = layouts::is_case_pair(lower, upper);
+            let control = if vk.len() == 1 && vk.chars().all(|c| c.is_ascii_uppercase()) {
+                vk.chars().next().map(|c| char::from(c as u8 - b'@'))
+            } else {
+                CONTROL_CHARS
+                    .iter()
+                    .find(|&&(control_vk, _)| control_vk == vk)
+                    .map(|&(_, c)| c)
+            };
+            let altgr = altgr.iter().find(|altgr| altgr.key == key);
+            klc += &format!(
+                "{}\t{}\t\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                scan_code,
+                vk,
+                u8::from(caps),
+                cell(Some(lower)),
+                cell(Some(upper)),
+                cell(control),
+                cell(altgr.map(|altgr| altgr.unshifted)),
+                cell(altgr.map(|altgr| altgr.shifted))
+            );
+        }
+    }
+    klc += "39\tSPACE\t\t0\t0020\t0020\t0020\t-1\t-1\n";
+    klc += "53\tDECIMAL\t\t0\t002e\t002e\t-1\t-1\t-1\n\n";
+    klc += KEY_NAMES;
+    klc += &format!(
+        "\nDESCRIPTIONS\n\n{}\t{}\n\nLANGUAGENAMES\n\n{}\t{}\n\nENDKBD\n",
+        language_id, description, language_id, language_name
+    );
+    klc
+}
+
+// `klc` as MSKLC reads it: UTF-16LE with a byte order mark and CRLF line
+// endings
+pub fn to_utf16(klc: &str) -> Vec<u8> {
+    let mut bytes = vec![0xFF, 0xFE];
+    for unit in klc.replace('\n', "\r\n").encode_utf16() {
+        bytes.extend(unit.to_le_bytes());
+    }
+    bytes
+}
@@ -1,29 +1,385 @@
+use crate::{detect, hangul, kana, telex};
 use lazy_static::lazy_static;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::str::FromStr;
+use std::sync::RwLock;
 
-#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug)]
+#[derive(PartialEq, Eq, Hash, Clone, Copy, Debug, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum LayoutCode {
     Dvorak,
     Qwerty,
     Colemak,
     Russian,
+    German,
+    Azerty,
+    Bepo,
+    Spanish,
+    #[serde(rename = "abnt2")]
+    Abnt2,
+    Norwegian,
+    Danish,
+    Uk,
+    #[serde(rename = "programmer-dvorak")]
+    ProgrammerDvorak,
+    #[serde(rename = "dvorak-left")]
+    DvorakLeft,
+    #[serde(rename = "dvorak-right")]
+    DvorakRight,
+    #[serde(rename = "colemak-dh")]
+    ColemakDh,
+    Norman,
+    Halmak,
+    Engram,
+    Ukrainian,
+    Belarusian,
+    Kazakh,
+    Bulgarian,
+    #[serde(rename = "bulgarian-phonetic")]
+    BulgarianPhonetic,
+    Serbian,
+    Macedonian,
+    Greek,
+    Hebrew,
+    Arabic,
+    Persian,
+    Turkish,
+    Georgian,
+    Armenian,
+    #[serde(rename = "armenian-phonetic")]
+    ArmenianPhonetic,
+    Thai,
+    Telex,
+    Korean,
+    #[serde(rename = "jis-kana")]
+    JisKana,
+    #[serde(rename = "jis-katakana")]
+    JisKatakana,
+    Hindi,
+    Czech,
+    Slovak,
+    Polish,
+    Hungarian,
+    Romanian,
+    Lithuanian,
+    Latvian,
+    #[serde(rename = "latvian-ergonomic")]
+    LatvianErgonomic,
+    Estonian,
+    Icelandic,
+    Mongolian,
+    #[serde(rename = "russian-typewriter")]
+    RussianTypewriter,
+    #[serde(rename = "russian-mac")]
+    RussianMac,
 }
 
+// Accepts a `variant` parameter as in `russian?variant=typewriter`, see
+// `LayoutId`. Other parameters are options and need a `LayoutId`.
 impl FromStr for LayoutCode {
     type Err = ();
 
     fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let id = LayoutId::from_str(input)?;
+        if id.options != LayoutOptions::default() {
+            return Err(());
+        }
+        Ok(id.layout)
+    }
+}
+
+impl LayoutCode {
+    fn from_name(input: &str) -> Result<Self, ()> {
         match input.to_lowercase().as_str() {
             "dvorak" => Ok(LayoutCode::Dvorak),
             "qwerty" => Ok(LayoutCode::Qwerty),
             "colemak" => Ok(LayoutCode::Colemak),
             "russian" => Ok(LayoutCode::Russian),
+            "german" | "qwertz" => Ok(LayoutCode::German),
+            "azerty" | "french" => Ok(LayoutCode::Azerty),
+            "bepo" | "bépo" => Ok(LayoutCode::Bepo),
+            "spanish" => Ok(LayoutCode::Spanish),
+            "abnt2" | "brazilian" => Ok(LayoutCode::Abnt2),
+            "norwegian" => Ok(LayoutCode::Norwegian),
+            "danish" => Ok(LayoutCode::Danish),
+            "uk" | "gb" | "british" => Ok(LayoutCode::Uk),
+            "programmer-dvorak" | "dvp" => Ok(LayoutCode::ProgrammerDvorak),
+            "dvorak-left" | "left-dvorak" => Ok(LayoutCode::DvorakLeft),
+            "dvorak-right" | "right-dvorak" => Ok(LayoutCode::DvorakRight),
+            "colemak-dh" | "colemak_dh" => Ok(LayoutCode::ColemakDh),
+            "norman" => Ok(LayoutCode::Norman),
+            "halmak" => Ok(LayoutCode::Halmak),
+            "engram" => Ok(LayoutCode::Engram),
+            "ukrainian" => Ok(LayoutCode::Ukrainian),
+            "belarusian" => Ok(LayoutCode::Belarusian),
+            "kazakh" => Ok(LayoutCode::Kazakh),
+            "bulgarian" | "bds" => Ok(LayoutCode::Bulgarian),
+            "bulgarian-phonetic" => Ok(LayoutCode::BulgarianPhonetic),
+            "serbian" => Ok(LayoutCode::Serbian),
+            "macedonian" => Ok(LayoutCode::Macedonian),
+            "greek" => Ok(LayoutCode::Greek),
+            "hebrew" => Ok(LayoutCode::Hebrew),
+            "arabic" => Ok(LayoutCode::Arabic),
+            "persian" | "farsi" => Ok(LayoutCode::Persian),
+            "turkish" | "turkish-q" => Ok(LayoutCode::Turkish),
+            "georgian" => Ok(LayoutCode::Georgian),
+            "armenian" | "armenian-typewriter" => Ok(LayoutCode::Armenian),
+            "armenian-phonetic" => Ok(LayoutCode::ArmenianPhonetic),
+            "thai" | "kedmanee" => Ok(LayoutCode::Thai),
+            "telex" | "vietnamese" | "vietnamese-telex" => Ok(LayoutCode::Telex),
+            "korean" | "dubeolsik" => Ok(LayoutCode::Korean),
+            "jis-kana" | "kana" | "japanese" => Ok(LayoutCode::JisKana),
+            "jis-katakana" | "katakana" => Ok(LayoutCode::JisKatakana),
+            "hindi" | "inscript" | "devanagari" => Ok(LayoutCode::Hindi),
+            "czech" | "cz" => Ok(LayoutCode::Czech),
+            "slovak" | "sk" => Ok(LayoutCode::Slovak),
+            "polish" | "pl" | "polish-programmers" => Ok(LayoutCode::Polish),
+            "hungarian" | "hu" => Ok(LayoutCode::Hungarian),
+            "romanian" | "ro" => Ok(LayoutCode::Romanian),
+            "lithuanian" | "lt" => Ok(LayoutCode::Lithuanian),
+            "latvian" | "lv" | "latvian-apostrophe" => Ok(LayoutCode::Latvian),
+            "latvian-ergonomic" | "ugjrmv" => Ok(LayoutCode::LatvianErgonomic),
+            "estonian" | "ee" => Ok(LayoutCode::Estonian),
+            "icelandic" | "is" => Ok(LayoutCode::Icelandic),
+            "mongolian" | "mn" => Ok(LayoutCode::Mongolian),
+            "russian-typewriter" => Ok(LayoutCode::RussianTypewriter),
+            "russian-mac" | "russian-apple" => Ok(LayoutCode::RussianMac),
             _ => Err(()),
         }
     }
 }
 
+// A layout name with parameters, like `russian?variant=typewriter&yo=false`,
+// so that clients can pick variants and options through the one field they
+// already send. `variant` selects a variant of the layout by the suffix or
+// prefix of its name (`latvian?variant=ergonomic`,
+// `dvorak?variant=programmer`); the other parameters are conversion options.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LayoutId {
+    pub layout: LayoutCode,
+    pub options: LayoutOptions,
+}
+
+// Conversion options given as layout parameters, `None` where not given
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LayoutOptions {
+    // `yo=true` keeps ё, `yo=false` folds it, or a `YoPolicy` by name
+    pub yo_policy: Option<YoPolicy>,
+    pub digit_symbols: Option<bool>,
+    pub keep_punctuation: Option<bool>,
+    pub case_policy: Option<CasePolicy>,
+}
+
+impl LayoutOptions {
+    // These options, with those of `other` where these are not given
+    pub fn or(self, other: LayoutOptions) -> LayoutOptions {
+        LayoutOptions {
+            yo_policy: self.yo_policy.or(other.yo_policy),
+            digit_symbols: self.digit_symbols.or(other.digit_symbols),
+            keep_punctuation: self.keep_punctuation.or(other.keep_punctuation),
+            case_policy: self.case_policy.or(other.case_policy),
+        }
+    }
+}
+
+// Splits `name?key=value&...` into the name, the variant and the options
+pub fn parse_params(input: &str) -> Option<(&str, Option<&str>, LayoutOptions)> {
+    let Some((name, query)) = input.split_once('?') else {
+        return Some((input, None, LayoutOptions::default()));
+    };
+    let flag = |value: &str| match value {
+        "true" | "1" => Some(true),
+        "false" | "0" => Some(false),
+        _ => None,
+    };
+    let mut variant = None;
+    let mut options = LayoutOptions::default();
+    for param in query.split('&').filter(|param| !param.is_empty()) {
+        let (key, value) = param.split_once('=')?;
+        match key {
+            "variant" => variant = Some(value),
+            "yo" => {
+                options.yo_policy = Some(match flag(value) {
+                    Some(true) => YoPolicy::Keep,
+                    Some(false) => YoPolicy::FoldToE,
+                    None => YoPolicy::from_str(value).ok()?,
+                })
+            }
+            "digit_symbols" => options.digit_symbols = Some(flag(value)?),
+            "keep_punctuation" => options.keep_punctuation = Some(flag(value)?),
+            "case" => options.case_policy = Some(CasePolicy::from_str(value).ok()?),
+            _ => return None,
+        }
+    }
+    Some((name, variant, options))
+}
+
+impl FromStr for LayoutId {
+    type Err = ();
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let (name, variant, options) = parse_params(input).ok_or(())?;
+        let layout = match variant {
+            Some(variant) => LayoutCode::from_name(&format!("{}-{}", name, variant))
+                .or_else(|()| LayoutCode::from_name(&format!("{}-{}", variant, name)))?,
+            None => LayoutCode::from_name(name)?,
+        };
+        Ok(LayoutId { layout, options })
+    }
+}
+
+impl LayoutCode {
+    pub const ALL: [LayoutCode; 53] = [
+        LayoutCode::Qwerty,
+        LayoutCode::Dvorak,
+        LayoutCode::Colemak,
+        LayoutCode::Russian,
+        LayoutCode::German,
+        LayoutCode::Azerty,
+        LayoutCode::Bepo,
+        LayoutCode::Spanish,
+        LayoutCode::Abnt2,
+        LayoutCode::Norwegian,
+        LayoutCode::Danish,
+        LayoutCode::Uk,
+        LayoutCode::ProgrammerDvorak,
+        LayoutCode::DvorakLeft,
+        LayoutCode::DvorakRight,
+        LayoutCode::ColemakDh,
+        LayoutCode::Norman,
+        LayoutCode::Halmak,
+        LayoutCode::Engram,
+        LayoutCode::Ukrainian,
+        LayoutCode::Belarusian,
+        LayoutCode::Kazakh,
+        LayoutCode::Bulgarian,
+        LayoutCode::BulgarianPhonetic,
+        LayoutCode::Serbian,
+        LayoutCode::Macedonian,
+        LayoutCode::Greek,
+        LayoutCode::Hebrew,
+        LayoutCode::Arabic,
+        LayoutCode::Persian,
+        LayoutCode::Turkish,
+        LayoutCode::Georgian,
+        LayoutCode::Armenian,
+        LayoutCode::ArmenianPhonetic,
+        LayoutCode::Thai,
+        LayoutCode::Telex,
+        LayoutCode::Korean,
+        LayoutCode::JisKana,
+        LayoutCode::JisKatakana,
+        LayoutCode::Hindi,
+        LayoutCode::Czech,
+        LayoutCode::Slovak,
+        LayoutCode::Polish,
+        LayoutCode::Hungarian,
+        LayoutCode::Romanian,
+        LayoutCode::Lithuanian,
+        LayoutCode::Latvian,
+        LayoutCode::LatvianErgonomic,
+        LayoutCode::Estonian,
+        LayoutCode::Icelandic,
+        LayoutCode::Mongolian,
+        LayoutCode::RussianTypewriter,
+        LayoutCode::RussianMac,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LayoutCode::Dvorak => "dvorak",
+            LayoutCode::Qwerty => "qwerty",
+            LayoutCode::Colemak => "colemak",
+            LayoutCode::Russian => "russian",
+            LayoutCode::German => "german",
+            LayoutCode::Azerty => "azerty",
+            LayoutCode::Bepo => "bepo",
+            LayoutCode::Spanish => "spanish",
+            LayoutCode::Abnt2 => "abnt2",
+            LayoutCode::Norwegian => "norwegian",
+            LayoutCode::Danish => "danish",
+            LayoutCode::Uk => "uk",
+            LayoutCode::ProgrammerDvorak => "programmer-dvorak",
+            LayoutCode::DvorakLeft => "dvorak-left",
+            LayoutCode::DvorakRight => "dvorak-right",
+            LayoutCode::ColemakDh => "colemak-dh",
+            LayoutCode::Norman => "norman",
+            LayoutCode::Halmak => "halmak",
+            LayoutCode::Engram => "engram",
+            LayoutCode::Ukrainian => "ukrainian",
+            LayoutCode::Belarusian => "belarusian",
+            LayoutCode::Kazakh => "kazakh",
+            LayoutCode::Bulgarian => "bulgarian",
+            LayoutCode::BulgarianPhonetic => "bulgarian-phonetic",
+            LayoutCode::Serbian => "serbian",
+            LayoutCode::Macedonian => "macedonian",
+            LayoutCode::Greek => "greek",
+            LayoutCode::Hebrew => "hebrew",
+            LayoutCode::Arabic => "arabic",
+            LayoutCode::Persian => "persian",
+            LayoutCode::Turkish => "turkish",
+            LayoutCode::Georgian => "georgian",
+            LayoutCode::Armenian => "armenian",
+            LayoutCode::ArmenianPhonetic => "armenian-phonetic",
+            LayoutCode::Thai => "thai",
+            LayoutCode::Telex => "telex",
+            LayoutCode::Korean => "korean",
+            LayoutCode::JisKana => "jis-kana",
+            LayoutCode::JisKatakana => "jis-katakana",
+            LayoutCode::Hindi => "hindi",
+            LayoutCode::Czech => "czech",
+            LayoutCode::Slovak => "slovak",
+            LayoutCode::Polish => "polish",
+            LayoutCode::Hungarian => "hungarian",
+            LayoutCode::Romanian => "romanian",
+            LayoutCode::Lithuanian => "lithuanian",
+            LayoutCode::Latvian => "latvian",
+            LayoutCode::LatvianErgonomic => "latvian-ergonomic",
+            LayoutCode::Estonian => "estonian",
+            LayoutCode::Icelandic => "icelandic",
+            LayoutCode::Mongolian => "mongolian",
+            LayoutCode::RussianTypewriter => "russian-typewriter",
+            LayoutCode::RussianMac => "russian-mac",
+        }
+    }
+}
+
+// Characters produced by each key of a US QWERTY keyboard, row by row from
+// the number row down, as (unshifted, shifted) strings of equal length
+pub const QWERTY_ROWS: [(&str, &str); 4] = [
+    ("`1234567890-=", "~!@#$%^&*()_+"),
+    ("qwertyuiop[]\\", "QWERTYUIOP{}|"),
+    ("asdfghjkl;'", "ASDFGHJKL:\""),
+    ("zxcvbnm,./", "ZXCVBNM<>?"),
+];
+
+// Pairs of (unshifted, shifted) characters that share a QWERTY key
+pub fn shift_pairs() -> impl Iterator<Item = (char, char)> {
+    QWERTY_ROWS
+        .iter()
+        .flat_map(|(unshifted, shifted)| unshifted.chars().zip(shifted.chars()))
+}
+
+// Whether `upper` is the capital of `lower` and `lower` the small letter of
+// `upper`. Turkish dotted and dotless i pair up as İ/i and I/ı, which
+// Unicode's default casing does not do.
+pub fn is_case_pair(lower: char, upper: char) -> bool {
+    match (lower, upper) {
+        ('i', 'İ') | ('ı', 'I') => true,
+        _ => lower.to_uppercase().eq([upper]) && upper.to_lowercase().eq([lower]),
+    }
+}
+
+// Character produced on `layout` by the key that produces `key` on QWERTY
+pub fn char_at(layout: LayoutCode, key: char) -> char {
+    keymap(LayoutCode::Qwerty, layout)
+        .and_then(|map| map.get(&key))
+        .copied()
+        .unwrap_or(key)
+}
+
 lazy_static! {
     static ref KEYMAPS: HashMap<(LayoutCode, LayoutCode), HashMap<char, char>> = create_keymaps();
 }
@@ -40,19 +396,221 @@ fn create_keymaps() -> HashMap<(LayoutCode, LayoutCode), HashMap<char, char>> {
         (LayoutCode::Qwerty, LayoutCode::Russian),
         qwerty_to_russian(),
     );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::German),
+        from_rows(&GERMAN_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Azerty),
+        from_rows(&AZERTY_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Bepo),
+        from_rows(&BEPO_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Spanish),
+        from_rows(&SPANISH_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Abnt2),
+        from_rows(&ABNT2_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Norwegian),
+        from_rows(&NORWEGIAN_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Danish),
+        from_rows(&DANISH_ROWS),
+    );
+    keymaps.insert((LayoutCode::Qwerty, LayoutCode::Uk), from_rows(&UK_ROWS));
+
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::ProgrammerDvorak),
+        from_rows(&PROGRAMMER_DVORAK_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::DvorakLeft),
+        from_rows(&DVORAK_LEFT_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::DvorakRight),
+        from_rows(&DVORAK_RIGHT_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::ColemakDh),
+        from_rows(&COLEMAK_DH_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Norman),
+        from_rows(&NORMAN_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Halmak),
+        from_rows(&HALMAK_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Engram),
+        from_rows(&ENGRAM_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Ukrainian),
+        from_rows(&UKRAINIAN_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Belarusian),
+        from_rows(&BELARUSIAN_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Kazakh),
+        from_rows(&KAZAKH_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Bulgarian),
+        from_rows(&BULGARIAN_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::BulgarianPhonetic),
+        from_rows(&BULGARIAN_PHONETIC_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Serbian),
+        from_rows(&SERBIAN_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Macedonian),
+        from_rows(&MACEDONIAN_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Greek),
+        from_rows(&GREEK_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Hebrew),
+        from_rows(&HEBREW_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Arabic),
+        from_rows(&ARABIC_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Persian),
+        from_rows(&PERSIAN_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Turkish),
+        from_rows(&TURKISH_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Georgian),
+        from_rows(&GEORGIAN_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Armenian),
+        from_rows(&ARMENIAN_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::ArmenianPhonetic),
+        from_rows(&ARMENIAN_PHONETIC_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Thai),
+        from_rows(&THAI_ROWS),
+    );
+    // Telex is typed on the QWERTY keys; its letters are composed from the
+    // keystrokes afterwards
+    keymaps.insert((LayoutCode::Qwerty, LayoutCode::Telex), HashMap::new());
+
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Korean),
+        from_rows(&KOREAN_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::JisKana),
+        from_rows(&JIS_KANA_ROWS),
+    );
+    // The same keys typing katakana instead of hiragana
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::JisKatakana),
+        from_rows(&JIS_KANA_ROWS)
+            .into_iter()
+            .map(|(key, c)| (key, kana::katakana(c)))
+            .collect(),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Hindi),
+        from_rows(&HINDI_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Czech),
+        from_rows(&CZECH_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Slovak),
+        from_rows(&SLOVAK_ROWS),
+    );
+    // Polish differs from QWERTY only on AltGr
+    keymaps.insert((LayoutCode::Qwerty, LayoutCode::Polish), HashMap::new());
+
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Hungarian),
+        from_rows(&HUNGARIAN_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Romanian),
+        from_rows(&ROMANIAN_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Lithuanian),
+        from_rows(&LITHUANIAN_ROWS),
+    );
+    // Latvian types its letters with a dead key on the QWERTY keys
+    keymaps.insert((LayoutCode::Qwerty, LayoutCode::Latvian), HashMap::new());
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::LatvianErgonomic),
+        from_rows(&LATVIAN_ERGONOMIC_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Estonian),
+        from_rows(&ESTONIAN_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Icelandic),
+        from_rows(&ICELANDIC_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::Mongolian),
+        from_rows(&MONGOLIAN_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::RussianTypewriter),
+        from_rows(&RUSSIAN_TYPEWRITER_ROWS),
+    );
+    keymaps.insert(
+        (LayoutCode::Qwerty, LayoutCode::RussianMac),
+        from_rows(&RUSSIAN_MAC_ROWS),
+    );
 
     generate_inverse_maps(&mut keymaps);
 
     generate_composite_maps(&mut keymaps);
 
+    generate_altgr_maps(&mut keymaps);
+
     keymaps
 }
 
+// Every layout except QWERTY, which the direct maps start from
+fn non_qwerty_layouts() -> impl Iterator<Item = LayoutCode> {
+    LayoutCode::ALL
+        .into_iter()
+        .filter(|&layout| layout != LayoutCode::Qwerty)
+}
+
 // Generates inverse maps for all direct maps to/from Qwerty
 fn generate_inverse_maps(keymaps: &mut HashMap<(LayoutCode, LayoutCode), HashMap<char, char>>) {
-    let layouts = vec![LayoutCode::Dvorak, LayoutCode::Colemak, LayoutCode::Russian];
-
-    for &layout in &layouts {
+    for layout in non_qwerty_layouts() {
         if let Some(map) = keymaps.get(&(LayoutCode::Qwerty, layout)) {
             let inverse_map = invert_map(map);
             keymaps.insert((layout, LayoutCode::Qwerty), inverse_map);
@@ -62,13 +620,12 @@ fn generate_inverse_maps(keymaps: &mut HashMap<(LayoutCode, LayoutCode), HashMap
 
 // Function to generate composite maps between all layouts via Qwerty
 fn generate_composite_maps(keymaps: &mut HashMap<(LayoutCode, LayoutCode), HashMap<char, char>>) {
-    let layouts = vec![LayoutCode::Dvorak, LayoutCode::Colemak, LayoutCode::Russian];
-    for &from in &layouts {
-        for &to in &layouts {
+    for from in non_qwerty_layouts() {
+        for to in non_qwerty_layouts() {
             if from != to {
-                if let Some(map_to_qwerty) = keymaps.get(&(from, LayoutCode::Qwerty)) {
-                    if let Some(map_from_qwerty) = keymaps.get(&(LayoutCode::Qwerty, to)) {
-                        let combined_map = combine_maps(map_to_qwerty, map_from_qwerty);
+                if let Some(from_map) = keymaps.get(&(LayoutCode::Qwerty, from)) {
+                    if let Some(to_map) = keymaps.get(&(LayoutCode::Qwerty, to)) {
+                        let combined_map = combine_maps(from_map, to_map);
                         keymaps.insert((from, to), combined_map);
                     }
                 }
@@ -77,53 +634,787 @@ fn generate_composite_maps(keymaps: &mut HashMap<(LayoutCode, LayoutCode), HashM
     }
 }
 
+// Returns the conversion map between two layouts, if one was generated
+pub fn keymap(from: LayoutCode, to: LayoutCode) -> Option<&'static HashMap<char, char>> {
+    KEYMAPS.get(&(from, to))
+}
+
 fn invert_map(map: &HashMap<char, char>) -> HashMap<char, char> {
-    map.iter().map(|(k, v)| (*v, *k)).collect()
+    // QWERTY's own map is empty
+    combine_maps(map, &HashMap::new())
 }
 
-fn combine_maps(first: &HashMap<char, char>, second: &HashMap<char, char>) -> HashMap<char, char> {
-    first
+// Maps each key's character on one layout to the same key's character on
+// another, given both layouts' maps from QWERTY. Keys a layout leaves as on
+// QWERTY are missing from its map, so this walks every key rather than the
+// entries of either map.
+//
+// When `from` types a character on several keys, the key that types it
+// unshifted wins, then the key whose unshifted character is its lowercase,
+// then the first key row by row.
+fn combine_maps(from: &HashMap<char, char>, to: &HashMap<char, char>) -> HashMap<char, char> {
+    let at = |map: &HashMap<char, char>, key: char| map.get(&key).copied().unwrap_or(key);
+    let mut keys: Vec<(u8, char)> = shift_pairs()
+        .flat_map(|(lower, upper)| {
+            let capital = is_case_pair(at(from, lower), at(from, upper));
+            [(0, lower), (if capital { 1 } else { 2 }, upper)]
+        })
+        .collect();
+    keys.sort_by_key(|&(precedence, _)| precedence);
+
+    let mut combined = HashMap::new();
+    for (_, key) in keys {
+        combined.entry(at(from, key)).or_insert(at(to, key));
+    }
+    // Unchanged characters still claim their key above, so that a second
+    // key typing the same character cannot take them over
+    combined.retain(|from_char, to_char| from_char != to_char);
+    combined
+}
+
+// Builds a map from QWERTY out of a layout's rows, given in the same shape
+// as `QWERTY_ROWS`
+fn from_rows(rows: &[(&str, &str); 4]) -> HashMap<char, char> {
+    let keys = QWERTY_ROWS
+        .iter()
+        .flat_map(|(unshifted, shifted)| unshifted.chars().chain(shifted.chars()));
+    let chars = rows
+        .iter()
+        .flat_map(|(unshifted, shifted)| unshifted.chars().chain(shifted.chars()));
+    keys.zip(chars).filter(|(key, c)| key != c).collect()
+}
+
+// A character typed with AltGr on the key that types `key` on QWERTY, and
+// the one typed with AltGr and shift
+pub(crate) struct AltGrKey {
+    pub(crate) key: char,
+    pub(crate) unshifted: char,
+    pub(crate) shifted: char,
+}
+
+pub(crate) fn altgr_keys(layout: LayoutCode) -> &'static [AltGrKey] {
+    match layout {
+        LayoutCode::Polish => &POLISH_ALTGR_KEYS,
+        LayoutCode::Hungarian => &HUNGARIAN_ALTGR_KEYS,
+        LayoutCode::Lithuanian => &LITHUANIAN_ALTGR_KEYS,
+        LayoutCode::LatvianErgonomic => &LATVIAN_ERGONOMIC_ALTGR_KEYS,
+        _ => &[],
+    }
+}
+
+// Characters `layout` types with AltGr
+pub fn altgr_chars(layout: LayoutCode) -> impl Iterator<Item = char> {
+    altgr_keys(layout)
+        .iter()
+        .flat_map(|altgr| [altgr.unshifted, altgr.shifted])
+}
+
+// Adds what layouts type with AltGr to the maps out of them. An AltGr
+// character converts to the AltGr character of the same key where the other
+// layout has one. Otherwise it stays as it is if the other layout can type
+// it at all, and else converts to what the key types without AltGr, as the
+// nearest thing to it. Characters the layout also types without AltGr keep
+// the mapping of that key.
+fn generate_altgr_maps(keymaps: &mut HashMap<(LayoutCode, LayoutCode), HashMap<char, char>>) {
+    for from in LayoutCode::ALL {
+        for to in LayoutCode::ALL {
+            if from == to || altgr_keys(from).is_empty() {
+                continue;
+            }
+            let to_map = keymaps.get(&(LayoutCode::Qwerty, to));
+            let at = |key: char| to_map.and_then(|map| map.get(&key)).copied().unwrap_or(key);
+            let typeable: HashSet<char> = shift_pairs()
+                .flat_map(|(lower, upper)| [at(lower), at(upper)])
+                .chain(altgr_chars(to))
+                .collect();
+            let targets: Vec<(char, char)> = altgr_keys(from)
+                .iter()
+                .flat_map(|altgr| {
+                    let shifted_key = shift_pairs()
+                        .find(|&(lower, _)| lower == altgr.key)
+                        .map_or(altgr.key, |(_, upper)| upper);
+                    let on_to = altgr_keys(to).iter().find(|other| other.key == altgr.key);
+                    let nearest = |c: char, key: char| {
+                        if typeable.contains(&c) {
+                            c
+                        } else {
+                            at(key)
+                        }
+                    };
+                    [
+                        (
+                            altgr.unshifted,
+                            on_to.map_or(nearest(altgr.unshifted, altgr.key), |other| {
+                                other.unshifted
+                            }),
+                        ),
+                        (
+                            altgr.shifted,
+                            on_to
+                                .map_or(nearest(altgr.shifted, shifted_key), |other| other.shifted),
+                        ),
+                    ]
+                })
+                .collect();
+            let map = keymaps.entry((from, to)).or_default();
+            for (from_char, to_char) in targets {
+                if to_char != from_char {
+                    map.entry(from_char).or_insert(to_char);
+                }
+            }
+        }
+    }
+}
+
+// A dead key: typing `accent` and then one of `bases` produces the character
+// at the same position in `composed`
+struct DeadKey {
+    accent: char,
+    bases: &'static str,
+    composed: &'static str,
+}
+
+const ACUTE: DeadKey = DeadKey {
+    accent: '´',
+    bases: "aeiouyAEIOUY",
+    composed: "áéíóúýÁÉÍÓÚÝ",
+};
+const GRAVE: DeadKey = DeadKey {
+    accent: '`',
+    bases: "aeiouAEIOU",
+    composed: "àèìòùÀÈÌÒÙ",
+};
+const CIRCUMFLEX: DeadKey = DeadKey {
+    accent: '^',
+    bases: "aeiouAEIOU",
+    composed: "âêîôûÂÊÎÔÛ",
+};
+const DIAERESIS: DeadKey = DeadKey {
+    accent: '¨',
+    bases: "aeiouAEIOU",
+    composed: "äëïöüÄËÏÖÜ",
+};
+const GREEK_TONOS: DeadKey = DeadKey {
+    accent: '´',
+    bases: "αεηιουωΑΕΗΙΟΥΩ",
+    composed: "άέήίόύώΆΈΉΊΌΎΏ",
+};
+const GREEK_DIALYTIKA: DeadKey = DeadKey {
+    accent: '¨',
+    bases: "ιυΙΥ",
+    composed: "ϊϋΪΫ",
+};
+const CARON: DeadKey = DeadKey {
+    accent: 'ˇ',
+    bases: "cdelnrstzCDELNRSTZ",
+    composed: "čďěľňřšťžČĎĚĽŇŘŠŤŽ",
+};
+const RING: DeadKey = DeadKey {
+    accent: '°',
+    bases: "auAU",
+    composed: "åůÅŮ",
+};
+const TILDE: DeadKey = DeadKey {
+    accent: '~',
+    bases: "aonAON",
+    composed: "ãõñÃÕÑ",
+};
+
+fn dead_keys(layout: LayoutCode) -> &'static [DeadKey] {
+    match layout {
+        LayoutCode::Spanish => &SPANISH_DEAD_KEYS,
+        LayoutCode::Abnt2 => &ABNT2_DEAD_KEYS,
+        LayoutCode::Norwegian => &NORWEGIAN_DEAD_KEYS,
+        LayoutCode::Danish => &DANISH_DEAD_KEYS,
+        LayoutCode::Greek => &GREEK_DEAD_KEYS,
+        LayoutCode::Czech => &CZECH_DEAD_KEYS,
+        LayoutCode::Slovak => &SLOVAK_DEAD_KEYS,
+        LayoutCode::Latvian => &LATVIAN_DEAD_KEYS,
+        LayoutCode::Estonian => &ESTONIAN_DEAD_KEYS,
+        LayoutCode::Icelandic => &ICELANDIC_DEAD_KEYS,
+        _ => &[],
+    }
+}
+
+// Characters `layout` types with a dead key followed by another key, or
+// composes from several keystrokes as Telex, Hangul and kana do
+pub fn composed_chars(layout: LayoutCode) -> impl Iterator<Item = char> {
+    let composed: Box<dyn Iterator<Item = char>> = match layout {
+        LayoutCode::Telex => Box::new(telex::letters()),
+        LayoutCode::Korean => Box::new(hangul::letters()),
+        LayoutCode::JisKana => Box::new(kana::voiced(false)),
+        LayoutCode::JisKatakana => Box::new(kana::voiced(true)),
+        _ => Box::new(std::iter::empty()),
+    };
+    dead_keys(layout)
         .iter()
-        .map(|(&k, &v)| (k, second.get(&v).copied().unwrap_or(v)))
+        .flat_map(|dead_key| dead_key.composed.chars())
+        .chain(composed)
+}
+
+// A key that types several characters at once, such as the Arabic lam-alef.
+// The layout's rows give it the single character `key`, usually the Unicode
+// ligature, which is spelled out as `text` on output.
+struct Sequence {
+    key: char,
+    text: &'static str,
+}
+
+fn sequences(layout: LayoutCode) -> &'static [Sequence] {
+    match layout {
+        LayoutCode::Arabic => &ARABIC_SEQUENCES,
+        _ => &[],
+    }
+}
+
+// Characters read as another one the layout types, such as the Romanian
+// letters with a cedilla for those with a comma below: (alias, character)
+fn aliases(layout: LayoutCode) -> &'static [(char, char)] {
+    match layout {
+        LayoutCode::Romanian => &ROMANIAN_ALIASES,
+        _ => &[],
+    }
+}
+
+// Characters `layout` reads in place of ones it types
+pub fn alias_chars(layout: LayoutCode) -> impl Iterator<Item = char> {
+    aliases(layout).iter().map(|&(alias, _)| alias)
+}
+
+// Characters `layout` types to change the character typed after them: dead
+// key accents and voicing marks
+fn marks(layout: LayoutCode) -> impl Iterator<Item = char> {
+    let voicing: &[char] = match layout {
+        LayoutCode::JisKana | LayoutCode::JisKatakana => &kana::MARKS,
+        _ => &[],
+    };
+    dead_keys(layout)
+        .iter()
+        .map(|dead_key| dead_key.accent)
+        .chain(voicing.iter().copied())
+}
+
+// `map` without the entries that turn one punctuation mark, symbol or digit
+// into another, for conversions that should only change letters. Symbols on
+// keys that type letters on the other layout are still converted, being how
+// those letters are typed: QWERTY "[t]" is Russian "хеъ", and `"vbh"` is
+// `ЭмирЭ`. Marks that combine into letters are still converted too.
+pub fn letters_only(
+    map: &HashMap<char, char>,
+    from: LayoutCode,
+    to: LayoutCode,
+) -> HashMap<char, char> {
+    let marks: HashSet<char> = marks(from).chain(marks(to)).collect();
+    let is_symbol = |c: char| !c.is_alphabetic() && !marks.contains(&c);
+    map.iter()
+        .filter(|&(&c, &mapped)| !(is_symbol(c) && is_symbol(mapped)))
+        .map(|(&c, &mapped)| (c, mapped))
         .collect()
 }
 
-pub fn convert_text(text: String, from: LayoutCode, to: LayoutCode) -> String {
-    if let Some(map) = KEYMAPS.get(&(from, to)) {
-        text.chars()
-            .map(|c| map.get(&c).copied().unwrap_or(c)) // Safe because `unwrap_or` provides a default
-            .collect()
-    } else {
-        // Log the error or handle the case when map is not found
-        eprintln!("Error: No conversion map found for {:?} to {:?}", from, to);
-        text // Optionally, return the original text or a specific error message
+// Symbols on the shifted digit keys that `layout`'s map leaves out, as (the
+// QWERTY symbol, the layout's). Converting every # to № or @ to " would
+// break hashtags and email addresses, so these are only converted on request.
+const RUSSIAN_DIGIT_SYMBOLS: [(char, char); 5] =
+    [('@', '"'), ('#', '№'), ('$', ';'), ('^', ':'), ('&', '?')];
+
+fn digit_symbols(layout: LayoutCode) -> &'static [(char, char)] {
+    match layout {
+        LayoutCode::Russian => &RUSSIAN_DIGIT_SYMBOLS,
+        _ => &[],
+    }
+}
+
+// Character on the shifted digit key that types `key` on QWERTY, counting
+// the symbols left out of the maps
+fn digit_symbol(layout: LayoutCode, key: char) -> char {
+    digit_symbols(layout)
+        .iter()
+        .find(|&&(qwerty, _)| qwerty == key)
+        .map_or_else(|| char_at(layout, key), |&(_, symbol)| symbol)
+}
+
+// Symbols of the shifted digit keys left out of `layout`'s map
+pub fn digit_symbol_chars(layout: LayoutCode) -> impl Iterator<Item = char> {
+    digit_symbols(layout).iter().map(|&(_, symbol)| symbol)
+}
+
+// `keymap` with the symbols of the shifted digit keys converted as well, on
+// layouts whose maps leave them out
+pub fn keymap_with_digit_symbols(from: LayoutCode, to: LayoutCode) -> Option<HashMap<char, char>> {
+    let mut map = match keymap(from, to) {
+        Some(map) => map.clone(),
+        None if from == to => return Some(HashMap::new()),
+        None => return None,
+    };
+    for key in "!@#$%^&*()".chars() {
+        let typed = digit_symbol(from, key);
+        let meant = digit_symbol(to, key);
+        if typed == meant {
+            map.remove(&typed);
+        } else {
+            map.insert(typed, meant);
+        }
     }
+    Some(map)
+}
+
+// Everything `layout` can produce, unshifted, shifted, with AltGr or with a
+// dead key
+pub fn typeable_chars(layout: LayoutCode) -> impl Iterator<Item = char> {
+    shift_pairs()
+        .flat_map(|(lower, upper)| [lower, upper])
+        .map(move |key| char_at(layout, key))
+        .chain(altgr_chars(layout))
+        .chain(alias_chars(layout))
+        .chain(composed_chars(layout))
 }
 
-pub fn parallel_convert_text(text: String, from: LayoutCode, to: LayoutCode) -> String {
-    const THRESHOLD: usize = 1000;
-    const MAX_THREADS: usize = 4;
-    if text.len() > THRESHOLD {
-        let chunk_size = text.len() / MAX_THREADS;
-        let chunks: Vec<String> = text
+// Spells `text` as typed on `layout` one key at a time, so it can be mapped
+// key by key: aliases become the character they stand for, sequences the
+// character of the key typing them and characters composed with dead keys
+// the two keystrokes typing them, unless the layout also has a key of their
+// own for them
+pub(crate) fn decompose(text: &str, layout: LayoutCode) -> Cow<'_, str> {
+    let mut text = Cow::Borrowed(text);
+    let aliases = aliases(layout);
+    if text
+        .chars()
+        .any(|c| aliases.iter().any(|&(alias, _)| alias == c))
+    {
+        text = text
             .chars()
-            .collect::<Vec<char>>()
-            .chunks(chunk_size)
-            .map(|chunk| chunk.iter().collect())
+            .map(|c| {
+                aliases
+                    .iter()
+                    .find(|&&(alias, _)| alias == c)
+                    .map_or(c, |&(_, typed)| typed)
+            })
             .collect();
-        let mut converted_chunks = Vec::new();
-        for chunk in chunks {
-            let from = from;
-            let to = to;
-            let handle = std::thread::spawn(move || convert_text(chunk, from, to));
-            converted_chunks.push(handle);
+    }
+    for sequence in sequences(layout) {
+        if text.contains(sequence.text) {
+            text = Cow::Owned(text.replace(sequence.text, sequence.key.encode_utf8(&mut [0; 4])));
         }
-        converted_chunks
-            .into_iter()
-            .map(|handle| handle.join().unwrap())
-            .collect()
+    }
+
+    match layout {
+        LayoutCode::Telex => text = Cow::Owned(telex::decompose(&text)),
+        LayoutCode::Korean => text = Cow::Owned(hangul::decompose(&text)),
+        LayoutCode::JisKana | LayoutCode::JisKatakana => text = Cow::Owned(kana::decompose(&text)),
+        _ => {}
+    }
+
+    let dead_keys = dead_keys(layout);
+    if dead_keys.is_empty() {
+        return text;
+    }
+    let to_qwerty = keymap(layout, LayoutCode::Qwerty);
+    let mut decomposed = String::with_capacity(text.len());
+    for c in text.chars() {
+        if to_qwerty.is_some_and(|map| map.contains_key(&c)) {
+            decomposed.push(c);
+            continue;
+        }
+        let keystrokes = dead_keys.iter().find_map(|dead_key| {
+            let index = dead_key
+                .composed
+                .chars()
+                .position(|composed| composed == c)?;
+            Some((dead_key.accent, dead_key.bases.chars().nth(index)?))
+        });
+        match keystrokes {
+            Some((accent, base)) => {
+                decomposed.push(accent);
+                decomposed.push(base);
+            }
+            None => decomposed.push(c),
+        }
+    }
+    Cow::Owned(decomposed)
+}
+
+// Whether `compose` changes anything for `layout`
+fn composes(layout: LayoutCode) -> bool {
+    !dead_keys(layout).is_empty()
+        || !sequences(layout).is_empty()
+        || matches!(
+            layout,
+            LayoutCode::Telex | LayoutCode::Korean | LayoutCode::JisKana | LayoutCode::JisKatakana
+        )
+}
+
+// Turns keystrokes mapped onto `layout` into the text they type there: each
+// accent typed with a dead key joins the character after it where the layout
+// has a composed form, and sequence keys are spelled out
+pub(crate) fn compose(text: String, layout: LayoutCode) -> String {
+    let mut text = compose_dead_keys(text, dead_keys(layout));
+    for sequence in sequences(layout) {
+        if text.contains(sequence.key) {
+            text = text.replace(sequence.key, sequence.text);
+        }
+    }
+    match layout {
+        LayoutCode::Telex => text = telex::compose(&text),
+        LayoutCode::Korean => text = hangul::compose(&text),
+        LayoutCode::JisKana | LayoutCode::JisKatakana => text = kana::compose(&text),
+        _ => {}
+    }
+    text
+}
+
+fn compose_dead_keys(text: String, dead_keys: &[DeadKey]) -> String {
+    if dead_keys.is_empty() {
+        return text;
+    }
+    let mut composed = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let combined = chars.peek().and_then(|&next| {
+            let dead_key = dead_keys.iter().find(|dead_key| dead_key.accent == c)?;
+            let index = dead_key.bases.chars().position(|base| base == next)?;
+            dead_key.composed.chars().nth(index)
+        });
+        match combined {
+            Some(combined) => {
+                composed.push(combined);
+                chars.next();
+            }
+            None => composed.push(c),
+        }
+    }
+    composed
+}
+
+fn map_chars(text: &str, map: &HashMap<char, char>) -> String {
+    text.chars()
+        .map(|c| map.get(&c).copied().unwrap_or(c)) // Safe because `unwrap_or` provides a default
+        .collect()
+}
+
+pub fn convert_text(text: &str, from: LayoutCode, to: LayoutCode) -> String {
+    let mut converted = String::with_capacity(text.len());
+    convert_to_buf(text, from, to, &mut converted);
+    converted
+}
+
+// Appends `text` converted from one layout to another to `out`, so that
+// callers converting many texts can reuse one buffer
+pub fn convert_to_buf(text: &str, from: LayoutCode, to: LayoutCode, out: &mut String) {
+    let Some(map) = keymap(from, to) else {
+        // Log the error or handle the case when map is not found
+        eprintln!("Error: No conversion map found for {:?} to {:?}", from, to);
+        out.push_str(text);
+        return;
+    };
+    convert_with_map(text, from, to, map, out);
+}
+
+// `convert_to_buf` with the map between the layouts already looked up
+pub(crate) fn convert_with_map(
+    text: &str,
+    from: LayoutCode,
+    to: LayoutCode,
+    map: &HashMap<char, char>,
+    out: &mut String,
+) {
+    let text = decompose(text, from);
+    if composes(to) {
+        out.push_str(&compose(map_chars(&text, map), to));
     } else {
-        convert_text(text, from, to)
+        out.extend(text.chars().map(|c| map.get(&c).copied().unwrap_or(c)));
+    }
+}
+
+// When and how `parallel_convert_text` splits text between threads
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parallelism {
+    // Texts up to this many bytes are converted on the calling thread
+    pub threshold: usize,
+    // Characters converted by one thread at a time, `None` to split the text
+    // evenly between the threads
+    pub chunk_size: Option<usize>,
+    pub max_threads: usize,
+}
+
+impl Default for Parallelism {
+    // One thread per CPU core, or a single one where the number is unknown
+    fn default() -> Self {
+        Parallelism {
+            threshold: 1000,
+            chunk_size: None,
+            max_threads: std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+        }
+    }
+}
+
+lazy_static! {
+    static ref PARALLELISM: RwLock<Parallelism> = RwLock::new(Parallelism::default());
+}
+
+// The policy `parallel_convert_text` and new `Converter`s use
+pub fn parallelism() -> Parallelism {
+    *PARALLELISM.read().unwrap()
+}
+
+pub fn set_parallelism(parallelism: Parallelism) {
+    *PARALLELISM.write().unwrap() = parallelism;
+}
+
+pub fn parallel_convert_text(text: &str, from: LayoutCode, to: LayoutCode) -> String {
+    match keymap(from, to) {
+        Some(map) => parallel_convert_with_map(text, from, to, map, &parallelism()),
+        None => convert_text(text, from, to),
+    }
+}
+
+pub(crate) fn parallel_convert_with_map(
+    text: &str,
+    from: LayoutCode,
+    to: LayoutCode,
+    map: &HashMap<char, char>,
+    parallelism: &Parallelism,
+) -> String {
+    // wasm32 has no threads to spawn
+    if text.len() <= parallelism.threshold
+        || parallelism.max_threads <= 1
+        || cfg!(target_arch = "wasm32")
+    {
+        let mut converted = String::with_capacity(text.len());
+        convert_with_map(text, from, to, map, &mut converted);
+        return converted;
+    }
+
+    // Dead keys are resolved on the whole text so that an accent and the
+    // character it combines with never end up in different chunks
+    let text = decompose(text, from);
+    let chars: Vec<char> = text.chars().collect();
+    let chunk_size = parallelism
+        .chunk_size
+        .unwrap_or(chars.len().div_ceil(parallelism.max_threads))
+        .max(1);
+    let chunks: Vec<&[char]> = chars.chunks(chunk_size).collect();
+    let mut converted = String::with_capacity(text.len());
+    for batch in chunks.chunks(parallelism.max_threads) {
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = batch
+                .iter()
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|c| map.get(c).copied().unwrap_or(*c))
+                            .collect::<String>()
+                    })
+                })
+                .collect();
+            for handle in handles {
+                converted.push_str(&handle.join().unwrap());
+            }
+        });
+    }
+    compose(converted, to)
+}
+
+// Whether text converts between the layouts one character at a time, each
+// into a character of the same UTF-8 length, so that it can be rewritten in
+// place. Dead keys, composed letters and aliases change the length.
+pub fn same_width(from: LayoutCode, to: LayoutCode) -> bool {
+    let Some(map) = keymap(from, to) else {
+        return false;
+    };
+    !composes(from)
+        && !composes(to)
+        && aliases(from).is_empty()
+        && map
+            .iter()
+            .all(|(c, mapped)| c.len_utf8() == mapped.len_utf8())
+}
+
+// Converts UTF-8 text in place, without allocating. Fails, leaving the text
+// as it is, if it is not valid UTF-8 or the layouts are not `same_width`.
+pub fn convert_bytes_in_place(
+    bytes: &mut [u8],
+    from: LayoutCode,
+    to: LayoutCode,
+) -> Result<(), String> {
+    if !same_width(from, to) {
+        return Err(format!(
+            "{:?} to {:?} cannot be converted in place",
+            from, to
+        ));
+    }
+    std::str::from_utf8(bytes).map_err(|err| err.to_string())?;
+    let map = keymap(from, to).ok_or("no conversion map found")?;
+    let mut i = 0;
+    while i < bytes.len() {
+        let width = match bytes[i] {
+            0x00..=0x7F => 1,
+            0xC0..=0xDF => 2,
+            0xE0..=0xEF => 3,
+            _ => 4,
+        };
+        let c = std::str::from_utf8(&bytes[i..i + width])
+            .ok()
+            .and_then(|c| c.chars().next());
+        if let Some(&mapped) = c.and_then(|c| map.get(&c)) {
+            mapped.encode_utf8(&mut bytes[i..i + width]);
+        }
+        i += width;
+    }
+    Ok(())
+}
+
+// `convert_bytes_in_place` for a `String`
+pub fn convert_in_place(text: &mut String, from: LayoutCode, to: LayoutCode) -> Result<(), String> {
+    let mut bytes = std::mem::take(text).into_bytes();
+    let converted = convert_bytes_in_place(&mut bytes, from, to);
+    *text = String::from_utf8(bytes).expect("same-width conversion keeps text valid UTF-8");
+    converted
+}
+
+// Replaces ё with е, as most Russian text is written: converting to a
+// Russian layout types ё wherever its key was pressed
+pub fn fold_yo(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            'ё' => 'е',
+            'Ё' => 'Е',
+            _ => c,
+        })
+        .collect()
+}
+
+// What happens to ё in converted text. Converting to a Russian layout types
+// ё wherever its key was pressed, but most Russian text is written with е
+// throughout and some systems expect ё to be spelled out.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum YoPolicy {
+    #[default]
+    Keep,
+    FoldToE,
+    // Also writes ё where е was typed in words always spelled with ё
+    Restore,
+}
+
+impl FromStr for YoPolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keep" => Ok(YoPolicy::Keep),
+            "fold_to_e" | "fold-to-e" => Ok(YoPolicy::FoldToE),
+            "restore" => Ok(YoPolicy::Restore),
+            _ => Err(()),
+        }
+    }
+}
+
+impl YoPolicy {
+    pub fn apply(self, text: &str) -> Cow<'_, str> {
+        match self {
+            YoPolicy::Keep => Cow::Borrowed(text),
+            YoPolicy::FoldToE => Cow::Owned(fold_yo(text)),
+            YoPolicy::Restore => Cow::Owned(detect::restore_yo(text)),
+        }
+    }
+}
+
+// How converting between cased and caseless layouts treats capitals. Hebrew,
+// Georgian, Korean and kana type the same letter for a capital as for the
+// lower case one, so converting to them and back loses the capitals.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CasePolicy {
+    // Each key converts as typed with or without Shift
+    #[default]
+    Keys,
+    // Capitals the target cannot tell from lower case are left as they are,
+    // so that converting back restores them
+    KeepCapitals,
+    // Converting from a caseless layout to a cased one capitalizes the first
+    // letter of each sentence
+    Sentence,
+}
+
+impl FromStr for CasePolicy {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "keys" => Ok(CasePolicy::Keys),
+            "keep_capitals" | "keep-capitals" => Ok(CasePolicy::KeepCapitals),
+            "sentence" => Ok(CasePolicy::Sentence),
+            _ => Err(()),
+        }
+    }
+}
+
+// Whether `layout` types any capital letters
+pub fn is_cased(layout: LayoutCode) -> bool {
+    typeable_chars(layout).any(char::is_uppercase)
+}
+
+// `map` without the capitals that convert to the same character as their
+// lower case letter, which are then left as they are
+pub fn keep_capitals(map: &HashMap<char, char>) -> HashMap<char, char> {
+    map.iter()
+        .filter(|&(&c, mapped)| {
+            let mut lower = c.to_lowercase();
+            let lower = match (lower.next(), lower.next()) {
+                (Some(lower), None) if lower != c => lower,
+                _ => return true,
+            };
+            map.get(&lower) != Some(mapped)
+        })
+        .map(|(&c, &mapped)| (c, mapped))
+        .collect()
+}
+
+// Capitalizes the first letter of each sentence of a text given in pieces,
+// for converting only some of them. A sentence starts the text, follows a
+// line break, or follows `.`, `!` or `?` and whitespace, so that the dots of
+// `3.14` or `example.com` end none.
+pub struct SentenceCase {
+    sentence_start: bool,
+    after_stop: bool,
+}
+
+impl Default for SentenceCase {
+    fn default() -> Self {
+        SentenceCase {
+            sentence_start: true,
+            after_stop: false,
+        }
+    }
+}
+
+impl SentenceCase {
+    // Appends `text` to `out` with the first letters of sentences capitalized
+    pub fn push(&mut self, text: &str, out: &mut String) {
+        for c in text.chars() {
+            if self.sentence_start && c.is_alphabetic() {
+                out.extend(c.to_uppercase());
+            } else {
+                out.push(c);
+            }
+            self.advance(c);
+        }
+    }
+
+    // Reads `text` without changing it, for the pieces left as they are
+    pub fn skip(&mut self, text: &str) {
+        text.chars().for_each(|c| self.advance(c));
+    }
+
+    fn advance(&mut self, c: char) {
+        if c == '\n' || (self.after_stop && c.is_whitespace()) {
+            self.sentence_start = true;
+        } else if c.is_alphabetic() {
+            self.sentence_start = false;
+        }
+        self.after_stop = matches!(c, '.' | '!' | '?') || (self.after_stop && c.is_whitespace());
     }
 }
 
@@ -213,13 +1504,11 @@ fn qwerty_to_colemak() -> HashMap<char, char> {
     map.insert('d', 's');
     map.insert('f', 't');
     map.insert('g', 'd');
-    map.insert('h', 'h');
     map.insert('j', 'n');
     map.insert('k', 'e');
     map.insert('l', 'i');
-    map.insert(';', 'p');
-    map.insert('\'', '-');
-    map.insert('-', '\'');
+    map.insert(';', 'o');
+    map.insert('n', 'k');
     // capital letters
     map.insert('E', 'F');
     map.insert('R', 'P');
@@ -233,13 +1522,11 @@ fn qwerty_to_colemak() -> HashMap<char, char> {
     map.insert('D', 'S');
     map.insert('F', 'T');
     map.insert('G', 'D');
-    map.insert('H', 'H');
     map.insert('J', 'N');
     map.insert('K', 'E');
     map.insert('L', 'I');
-    map.insert(':', 'P');
-    map.insert('"', '_');
-    map.insert('_', '"');
+    map.insert(':', 'O');
+    map.insert('N', 'K');
     map
 }
 
@@ -277,6 +1564,7 @@ fn qwerty_to_russian() -> HashMap<char, char> {
     map.insert('m', 'ь');
     map.insert(',', 'б');
     map.insert('.', 'ю');
+    map.insert('`', 'ё');
     map.insert('/', '.');
     // capital letters
     map.insert('Q', 'Й');
@@ -311,6 +1599,670 @@ fn qwerty_to_russian() -> HashMap<char, char> {
     map.insert('M', 'Ь');
     map.insert('<', 'Б');
     map.insert('>', 'Ю');
+    map.insert('~', 'Ё');
     map.insert('?', ',');
     map
 }
+
+// German T1 (DIN 2137). The dead keys (^, ´ and `) are taken as the accents
+// themselves; the <>| key left of Z has no QWERTY counterpart and the #' key
+// sits where ANSI boards have backslash.
+const GERMAN_ROWS: [(&str, &str); 4] = [
+    ("^1234567890ß´", "°!\"§$%&/()=?`"),
+    ("qwertzuiopü+#", "QWERTZUIOPÜ*'"),
+    ("asdfghjklöä", "ASDFGHJKLÖÄ"),
+    ("yxcvbnm,.-", "YXCVBNM;:_"),
+];
+
+// French AZERTY (fr-FR). Digits need shift. The ² key has nothing on shift
+// and keeps the QWERTY tilde, and the *µ key sits where ANSI boards have
+// backslash.
+const AZERTY_ROWS: [(&str, &str); 4] = [
+    ("²&é\"'(-è_çà)=", "~1234567890°+"),
+    ("azertyuiop^$*", "AZERTYUIOP¨£µ"),
+    ("qsdfghjklmù", "QSDFGHJKLM%"),
+    ("wxcvbn,;:!", "WXCVBN?./§"),
+];
+
+// BÉPO (fr-bepo), base and shift levels only until the AltGr layer is
+// modelled. Ç is on the key where ANSI boards have backslash; Ê is on the key
+// left of Z, which has no QWERTY counterpart.
+const BEPO_ROWS: [(&str, &str); 4] = [
+    ("$\"«»()@+-/*=%", "#1234567890°`"),
+    ("bépoè^vdljzwç", "BÉPOÈ!VDLJZWÇ"),
+    ("auie,ctsrnm", "AUIE;CTSRNM"),
+    ("àyx.k'qghf", "ÀYX:K?QGHF"),
+];
+
+// Spanish (es-ES). Ç is on the key where ANSI boards have backslash; the <>
+// key left of Z has no QWERTY counterpart.
+const SPANISH_ROWS: [(&str, &str); 4] = [
+    ("º1234567890'¡", "ª!\"·$%&/()=?¿"),
+    ("qwertyuiop`+ç", "QWERTYUIOP^*Ç"),
+    ("asdfghjklñ´", "ASDFGHJKLÑ¨"),
+    ("zxcvbnm,.-", "ZXCVBNM;:_"),
+];
+
+const SPANISH_DEAD_KEYS: [DeadKey; 4] = [ACUTE, GRAVE, CIRCUMFLEX, DIAERESIS];
+
+// Portuguese (Brazil) ABNT2. The ]} key sits where ANSI boards have
+// backslash. ABNT2's two extra keys have no QWERTY counterpart: the ISO key
+// left of Z types \| just as on a US ISO board, so those characters convert
+// unchanged, and /? right of the period only exist on ABNT2 hardware.
+const ABNT2_ROWS: [(&str, &str); 4] = [
+    ("'1234567890-=", "\"!@#$%¨&*()_+"),
+    ("qwertyuiop´[]", "QWERTYUIOP`{}"),
+    ("asdfghjklç~", "ASDFGHJKLÇ^"),
+    ("zxcvbnm,.;", "ZXCVBNM<>:"),
+];
+
+const ABNT2_DEAD_KEYS: [DeadKey; 5] = [ACUTE, GRAVE, TILDE, CIRCUMFLEX, DIAERESIS];
+
+// Norwegian. The '* key sits where ANSI boards have backslash; the <> key
+// left of Z has no QWERTY counterpart.
+const NORWEGIAN_ROWS: [(&str, &str); 4] = [
+    ("|1234567890+\\", "§!\"#¤%&/()=?`"),
+    ("qwertyuiopå¨'", "QWERTYUIOPÅ^*"),
+    ("asdfghjkløæ", "ASDFGHJKLØÆ"),
+    ("zxcvbnm,.-", "ZXCVBNM;:_"),
+];
+
+const NORWEGIAN_DEAD_KEYS: [DeadKey; 3] = [GRAVE, CIRCUMFLEX, DIAERESIS];
+
+// Danish. Æ and Ø are the other way round from Norwegian and the number row
+// ends in +? and the acute/grave dead key. The '* key sits where ANSI boards
+// have backslash; the <> key left of Z has no QWERTY counterpart.
+const DANISH_ROWS: [(&str, &str); 4] = [
+    ("½1234567890+´", "§!\"#¤%&/()=?`"),
+    ("qwertyuiopå¨'", "QWERTYUIOPÅ^*"),
+    ("asdfghjklæø", "ASDFGHJKLÆØ"),
+    ("zxcvbnm,.-", "ZXCVBNM;:_"),
+];
+
+const DANISH_DEAD_KEYS: [DeadKey; 4] = [ACUTE, GRAVE, CIRCUMFLEX, DIAERESIS];
+
+// United Kingdom. The #~ key sits where ANSI boards have backslash; the \|
+// key left of Z types the same on a US ISO board, so backslash and pipe
+// convert unchanged.
+const UK_ROWS: [(&str, &str); 4] = [
+    ("`1234567890-=", "¬!\"£$%^&*()_+"),
+    ("qwertyuiop[]#", "QWERTYUIOP{}~"),
+    ("asdfghjkl;'", "ASDFGHJKL:@"),
+    ("zxcvbnm,./", "ZXCVBNM<>?"),
+];
+
+// Programmer Dvorak. The number row types symbols and needs shift for the
+// digits, which are in the order 7531902468.
+const PROGRAMMER_DVORAK_ROWS: [(&str, &str); 4] = [
+    ("$&[{}(=*)+]!#", "~%7531902468`"),
+    (";,.pyfgcrl/@\\", ":<>PYFGCRL?^|"),
+    ("aoeuidhtns-", "AOEUIDHTNS_"),
+    ("'qjkxbmwvz", "\"QJKXBMWVZ"),
+];
+
+// One-handed Dvorak for the left hand. The digits are spread over the right
+// edge of the board, 1 to 4 on the number row and 5 to 0 down the columns
+// below it.
+const DVORAK_LEFT_ROWS: [(&str, &str); 4] = [
+    ("`[]/pfmlj4321", "~{}?PFMLJ$#@!"),
+    (";qbyurso.65=\\", ":QBYURSO>^%+|"),
+    ("-kcdtheaz87", "_KCDTHEAZ*&"),
+    ("'xgvwni,09", "\"XGVWNI<)("),
+];
+
+// One-handed Dvorak for the right hand, the mirror image of the left-handed
+// variant with the digits down the left edge of the board.
+const DVORAK_RIGHT_ROWS: [(&str, &str); 4] = [
+    ("`1234jlmfp/[]", "~!@#$JLMFP?{}"),
+    ("56q.orsuyb;=\\", "%^Q>ORSUYB:+|"),
+    ("78zaehtdck-", "&*ZAEHTDCK_"),
+    ("90x,inwvg'", "()X<INWVG\""),
+];
+
+// Colemak-DH (ANSI), which moves D and H from the middle columns down to
+// the bottom row
+const COLEMAK_DH_ROWS: [(&str, &str); 4] = [
+    ("`1234567890-=", "~!@#$%^&*()_+"),
+    ("qwfpbjluy;[]\\", "QWFPBJLUY:{}|"),
+    ("arstgmneio'", "ARSTGMNEIO\""),
+    ("zxcdvkh,./", "ZXCDVKH<>?"),
+];
+
+// Norman, which keeps most keys where QWERTY has them and moves the common
+// letters onto the home row
+const NORMAN_ROWS: [(&str, &str); 4] = [
+    ("`1234567890-=", "~!@#$%^&*()_+"),
+    ("qwdfkjurl;[]\\", "QWDFKJURL:{}|"),
+    ("asetgynioh'", "ASETGYNIOH\""),
+    ("zxcvbpm,./", "ZXCVBPM<>?"),
+];
+
+// Halmak, generated by optimising for finger travel. Comma and period sit
+// in the middle of the home row and slash on the bottom row.
+const HALMAK_ROWS: [(&str, &str); 4] = [
+    ("`1234567890-=", "~!@#$%^&*()_+"),
+    ("wlrbz;qudj[]\\", "WLRBZ:QUDJ{}|"),
+    ("shnt,.aeoi'", "SHNT<>AEOI\""),
+    ("fmvc/gpxky", "FMVC?GPXKY"),
+];
+
+// Engram. Quotes and parentheses share the two keys in the middle of the top
+// row, with comma and period below them, and the brackets move to the ends
+// of the number row.
+const ENGRAM_ROWS: [(&str, &str); 4] = [
+    ("[1234567890]/", "{|=~+<>^&%*}\\"),
+    ("byou'\"ldwvz#@", "BYOU()LDWVZ$`"),
+    ("ciea,.htsnq", "CIEA;:HTSNQ"),
+    ("gxjk-?rmfp", "GXJK_!RMFP"),
+];
+
+// Ukrainian. Ґ is on the key where ANSI boards have backslash, and the
+// apostrophe on the key left of 1.
+const UKRAINIAN_ROWS: [(&str, &str); 4] = [
+    ("'1234567890-=", "ʼ!\"№;%:?*()_+"),
+    ("йцукенгшщзхїґ", "ЙЦУКЕНГШЩЗХЇҐ"),
+    ("фівапролджє", "ФІВАПРОЛДЖЄ"),
+    ("ячсмитьбю.", "ЯЧСМИТЬБЮ,"),
+];
+
+// Belarusian. Ў replaces Щ, І replaces И, and the apostrophe takes the
+// place of Ъ on both levels, so it converts back to the unshifted key.
+const BELARUSIAN_ROWS: [(&str, &str); 4] = [
+    ("ё1234567890-=", "Ё!\"№;%:?*()_+"),
+    ("йцукенгшўзх'\\", "ЙЦУКЕНГШЎЗХ'/"),
+    ("фывапролджэ", "ФЫВАПРОЛДЖЭ"),
+    ("ячсмітьбю.", "ЯЧСМІТЬБЮ,"),
+];
+
+// Kazakh. The number row carries the Kazakh letters and has no digits at
+// all, which Kazakh typists reach on the keypad or another layout; digits
+// therefore convert unchanged out of this layout. Ё is only on the third
+// level and is left out.
+const KAZAKH_ROWS: [(&str, &str); 4] = [
+    ("(\"әіңғ,.үұқөһ", ")!ӘІҢҒ;:ҮҰҚӨҺ"),
+    ("йцукенгшщзхъ\\", "ЙЦУКЕНГШЩЗХЪ/"),
+    ("фывапролджэ", "ФЫВАПРОЛДЖЭ"),
+    ("ячсмитьбю№", "ЯЧСМИТЬБЮ?"),
+];
+
+// Bulgarian BDS 5237:2006. A layout of its own rather than a variant of
+// ЙЦУКЕН: comma takes the first letter key, Ы is only on shift there and Ь
+// types ѝ with shift.
+const BULGARIAN_ROWS: [(&str, &str); 4] = [
+    ("(1234567890-.", ")!?+\"%=:/–№$€"),
+    (",уеишщксдзц;„", "ыУЕИШЩКСДЗЦ§“"),
+    ("ьяаожгтнвмч", "ѝЯАОЖГТНВМЧ"),
+    ("юйъэфхпрлб", "ЮЙЪЭФХПРЛБ"),
+];
+
+// Bulgarian phonetic (the traditional variant), which puts each letter on
+// the QWERTY key of the Latin letter it sounds like
+const BULGARIAN_PHONETIC_ROWS: [(&str, &str); 4] = [
+    ("ч1234567890-=", "Ч!@№$%€§*()–+"),
+    ("явертъуиопшщю", "ЯВЕРТЪУИОПШЩЮ"),
+    ("асдфгхйкл;'", "АСДФГХЙКЛ:\""),
+    ("зьцжбнм,./", "ЗѝЦЖБНМ„“?"),
+];
+
+// Serbian Cyrillic. Ж is on the key where ANSI boards have backslash, its
+// place on Serbian boards, and again on Z; it converts back to the former.
+const SERBIAN_ROWS: [(&str, &str); 4] = [
+    ("`1234567890'+", "~!\"#$%&/()=?*"),
+    ("љњертзуиопшђж", "ЉЊЕРТЗУИОПШЂЖ"),
+    ("асдфгхјклчћ", "АСДФГХЈКЛЧЋ"),
+    ("жџцвбнм,.-", "ЖЏЦВБНМ;:_"),
+];
+
+// Macedonian. Ж is on the key where ANSI boards have backslash, and the
+// shifted 2 and 3 type the low and high double quotes.
+const MACEDONIAN_ROWS: [(&str, &str); 4] = [
+    ("`1234567890-=", "~!„“$%^&*()_+"),
+    ("љњертѕуиопшѓж", "ЉЊЕРТЅУИОПШЃЖ"),
+    ("асдфгхјклчќ", "АСДФГХЈКЛЧЌ"),
+    ("зџцвбнм,./", "ЗЏЦВБНМ;:?"),
+];
+
+// Greek. W types the final sigma ς, so both W and S type Σ with shift; Σ
+// converts back to S. The tonos and dialytika dead keys are on the
+// semicolon key.
+const GREEK_ROWS: [(&str, &str); 4] = [
+    ("`1234567890-=", "~!@#$%^&*()_+"),
+    (";ςερτυθιοπ[]\\", ":ΣΕΡΤΥΘΙΟΠ{}|"),
+    ("ασδφγηξκλ´'", "ΑΣΔΦΓΗΞΚΛ¨\""),
+    ("ζχψωβνμ,./", "ΖΧΨΩΒΝΜ<>?"),
+];
+
+const GREEK_DEAD_KEYS: [DeadKey; 2] = [GREEK_TONOS, GREEK_DIALYTIKA];
+
+// Israeli Hebrew (SI 1452). Hebrew has no case and the layout types Latin
+// capitals with shift, which a mistyped text does not mean: shift on a letter
+// key is taken to type the same letter, so Latin capitals convert like their
+// lowercase and Hebrew converts back to lowercase. Parentheses and the
+// angle brackets on the comma and period keys are mirrored for
+// right-to-left text.
+const HEBREW_ROWS: [(&str, &str); 4] = [
+    (";1234567890-=", "~!@#$%^&*)(_+"),
+    ("/'קראטוןםפ][\\", "/'קראטוןםפ}{|"),
+    ("שדגכעיחלךף,", "שדגכעיחלך:\""),
+    ("זסבהנמצתץ.", "זסבהנמצ><?"),
+];
+
+// Arabic (101). The harakat on the shifted top row are combining marks. The
+// lam-alef keys type two characters, written here as their ligatures; lam
+// followed by alef converts back to the lam-alef key however it was typed.
+const ARABIC_ROWS: [(&str, &str); 4] = [
+    ("ذ1234567890-=", "\u{651}!@#$%^&*)(_+"),
+    (
+        "ضصثقفغعهخحجد\\",
+        "\u{64E}\u{64B}\u{64F}\u{64C}\u{FEF9}إ`÷×؛<>|",
+    ),
+    ("شسيبلاتنمكط", "\u{650}\u{64D}][\u{FEF7}أـ،/:\""),
+    ("ئءؤر\u{FEFB}ىةوزظ", "~\u{652}}{\u{FEF5}آ',.؟"),
+];
+
+const ARABIC_SEQUENCES: [Sequence; 4] = [
+    Sequence {
+        key: '\u{FEFB}',
+        text: "لا",
+    },
+    Sequence {
+        key: '\u{FEF7}',
+        text: "لأ",
+    },
+    Sequence {
+        key: '\u{FEF9}',
+        text: "لإ",
+    },
+    Sequence {
+        key: '\u{FEF5}',
+        text: "لآ",
+    },
+];
+
+// Persian (ISIRI 9147). Unlike Arabic 101 the number row types Persian
+// digits, پ چ ژ گ have keys of their own and ی and ک are the Persian forms.
+// The key left of 1 types a zero-width joiner and shifted B a zero-width
+// non-joiner; the shifted top row holds the harakat.
+const PERSIAN_ROWS: [(&str, &str); 4] = [
+    ("\u{200D}۱۲۳۴۵۶۷۸۹۰-=", "÷!٬٫\u{FDFC}٪×،*)(ـ+"),
+    (
+        "ضصثقفغعهخحجچ\\",
+        "\u{652}\u{64C}\u{64D}\u{64B}\u{64F}\u{650}\u{64E}\u{651}][}{|",
+    ),
+    ("شسیبلاتنمکگ", "ؤئيإأآة»«:؛"),
+    ("ظطزرذدپو./", "ك\u{653}ژ\u{670}\u{200C}\u{654}ء><؟"),
+];
+
+// Turkish Q. The dotless ı is on I and the dotted i right of Ş, so their
+// capitals are I and İ respectively.
+const TURKISH_ROWS: [(&str, &str); 4] = [
+    ("\"1234567890*-", "é!'^+%&/()=?_"),
+    ("qwertyuıopğü,", "QWERTYUIOPĞÜ;"),
+    ("asdfghjklşi", "ASDFGHJKLŞİ"),
+    ("zxcvbnmöç.", "ZXCVBNMÖÇ:"),
+];
+
+// Georgian (QWERTY-based mkhedruli). Seven letters such as ჭ and ღ are typed
+// with shift on the key of a related letter. Mkhedruli has no case, so shift
+// on the other letter keys is taken to type the same letter, as for Hebrew.
+const GEORGIAN_ROWS: [(&str, &str); 4] = [
+    ("„1234567890-=", "“!@#$%^&*()_+"),
+    ("ქწერტყუიოპ[]\\", "ქჭეღთყუიოპ{}|"),
+    ("ასდფგჰჯკლ;'", "აშდფგჰჟკლ:\""),
+    ("ზხცვბნმ,./", "ძხჩვბნმ<>?"),
+];
+
+// Armenian, the standard layout descended from the typewriter. Հ and the
+// other capitals are the shifted letters; the number row keeps punctuation
+// such as ։ and ՞ unshifted.
+const ARMENIAN_ROWS: [(&str, &str); 4] = [
+    ("՝ֆձ֊,։՞․՛)օէղ", "՜ՖՁ—$…%և՚(ՕԷՂ"),
+    ("ճփբսմուկըթծց»", "ՃՓԲՍՄՈՒԿԸԹԾՑ«"),
+    ("ջվգեանիտհպր", "ՋՎԳԵԱՆԻՏՀՊՐ"),
+    ("ժդչյզլքխշռ", "ԺԴՉՅԶԼՔԽՇՌ"),
+];
+
+// Armenian phonetic: letters sit on the Latin keys that sound alike (ք on
+// Q, ս on S), and the rest fill the number row and punctuation keys. ւ is
+// on both 7 and U; it converts back to 7.
+const ARMENIAN_PHONETIC_ROWS: [(&str, &str); 4] = [
+    ("՝էթփձջւևռչճ֊ժ", "՜ԷԹՓՁՋՒ֏ՌՉՃ―Ժ"),
+    ("քոերտըւիօպխծշ", "ՔՈԵՐՏԸՒԻՕՊԽԾՇ"),
+    ("ասդֆգհյկլ;՛", "ԱՍԴՖԳՀՅԿԼ։\""),
+    ("զղցվբնմ,․/", "ԶՂՑՎԲՆՄ«»՞"),
+];
+
+// Thai Kedmanee. Thai has no case; shift types different letters, the Thai
+// digits and most tone marks. Vowel signs and tone marks are combining
+// characters and convert like any other character.
+const THAI_ROWS: [(&str, &str); 4] = [
+    ("_ๅ/-ภถ\u{E38}\u{E36}คตจขช", "%+๑๒๓๔\u{E39}฿๕๖๗๘๙"),
+    ("ๆไำพะ\u{E31}\u{E35}รนยบลฃ", "๐\"ฎฑธ\u{E4D}\u{E4A}ณฯญฐ,ฅ"),
+    ("ฟหกดเ\u{E49}\u{E48}าสวง", "ฤฆฏโฌ\u{E47}\u{E4B}ษศซ."),
+    ("ผปแอ\u{E34}\u{E37}ทมใฝ", "()ฉฮ\u{E3A}\u{E4C}?ฒฬฦ"),
+];
+
+// Korean 2-set (dubeolsik). Consonants are on the left hand and vowels on
+// the right; shift types the doubled consonants and ㅒ ㅖ, and on the other
+// letter keys is taken to type the same jamo. Keys type the compatibility
+// jamo, which `hangul` composes into syllables.
+const KOREAN_ROWS: [(&str, &str); 4] = [
+    ("`1234567890-=", "~!@#$%^&*()_+"),
+    ("ㅂㅈㄷㄱㅅㅛㅕㅑㅐㅔ[]\\", "ㅃㅉㄸㄲㅆㅛㅕㅑㅒㅖ{}|"),
+    ("ㅁㄴㅇㄹㅎㅗㅓㅏㅣ;'", "ㅁㄴㅇㄹㅎㅗㅓㅏㅣ:\""),
+    ("ㅋㅌㅊㅍㅠㅜㅡ,./", "ㅋㅌㅊㅍㅠㅜㅡ<>?"),
+];
+
+// Japanese JIS kana, typing hiragana. The dakuten and handakuten keys are
+// typed after the kana they voice (か ゛ → が). The ro and yen keys, with ろ
+// and ー, have no QWERTY equivalent, and shift on keys without a small kana
+// or punctuation is taken to type the same kana.
+const JIS_KANA_ROWS: [(&str, &str); 4] = [
+    ("`ぬふあうえおやゆよわほへ", "~ぬふぁぅぇぉゃゅょをほへ"),
+    ("たていすかんなにらせ゛゜む", "たてぃすかんなにらせ゛「」"),
+    ("ちとしはきくまのりれけ", "ちとしはきくまのりれけ"),
+    ("つさそひこみもねるめ", "っさそひこみも、。・"),
+];
+
+// Hindi (Devanagari InScript). Vowels are on the left hand and consonants on
+// the right; the vowel keys type matras (vowel signs) unshifted and the
+// independent vowels shifted. Matras, the halant on D and the nukta right of
+// ड are combining characters and convert like any other character. The
+// number row types Devanagari digits, as in the Linux layout.
+const HINDI_ROWS: [(&str, &str); 4] = [
+    ("ॊ१२३४५६७८९०-\u{943}", "ऒऍ\u{945}#$%^&*()ःऋ"),
+    ("ौ\u{948}ाी\u{942}बहगदजड\u{93C}ॉ", "औऐआईऊभङघधझढञऑ"),
+    ("ो\u{947}\u{94D}ि\u{941}परकतचट", "ओएअइउफऱखथछठ"),
+    ("\u{946}\u{902}मनवलस,.य", "ऎ\u{901}णऩऴळशष।\u{95F}"),
+];
+
+// Czech QWERTZ. The number row types ě š č ř ž ý á í é and needs shift for
+// the digits. The key left of Backspace has the acute dead key, with the
+// caron on shift, and the key right of ú has the diaeresis.
+const CZECH_ROWS: [(&str, &str); 4] = [
+    (";+ěščřžýáíé=´", "°1234567890%ˇ"),
+    ("qwertzuiopú)¨", "QWERTZUIOP/('"),
+    ("asdfghjklů§", "ASDFGHJKL\"!"),
+    ("yxcvbnm,.-", "YXCVBNM?:_"),
+];
+
+const CZECH_DEAD_KEYS: [DeadKey; 3] = [ACUTE, CARON, DIAERESIS];
+
+// Slovak QWERTZ. Like Czech the number row types accented letters and needs
+// shift for the digits, but with ľ and ť in place of ě and ř; ä, ň and ô
+// have keys of their own. The key left of Backspace has the acute dead key,
+// with the caron on shift.
+const SLOVAK_ROWS: [(&str, &str); 4] = [
+    (";+ľščťžýáíé=´", "°1234567890%ˇ"),
+    ("qwertzuiopúäň", "QWERTZUIOP/()"),
+    ("asdfghjklô§", "ASDFGHJKL\"!"),
+    ("yxcvbnm,.-", "YXCVBNM?:_"),
+];
+
+const SLOVAK_DEAD_KEYS: [DeadKey; 2] = [ACUTE, CARON];
+
+// Polish (programmers) is QWERTY with the Polish letters on AltGr, on the
+// key of the Latin letter they are based on; ź is on X since ż takes Z.
+const POLISH_ALTGR_KEYS: [AltGrKey; 9] = [
+    AltGrKey {
+        key: 'a',
+        unshifted: 'ą',
+        shifted: 'Ą',
+    },
+    AltGrKey {
+        key: 'c',
+        unshifted: 'ć',
+        shifted: 'Ć',
+    },
+    AltGrKey {
+        key: 'e',
+        unshifted: 'ę',
+        shifted: 'Ę',
+    },
+    AltGrKey {
+        key: 'l',
+        unshifted: 'ł',
+        shifted: 'Ł',
+    },
+    AltGrKey {
+        key: 'n',
+        unshifted: 'ń',
+        shifted: 'Ń',
+    },
+    AltGrKey {
+        key: 'o',
+        unshifted: 'ó',
+        shifted: 'Ó',
+    },
+    AltGrKey {
+        key: 's',
+        unshifted: 'ś',
+        shifted: 'Ś',
+    },
+    AltGrKey {
+        key: 'x',
+        unshifted: 'ź',
+        shifted: 'Ź',
+    },
+    AltGrKey {
+        key: 'z',
+        unshifted: 'ż',
+        shifted: 'Ż',
+    },
+];
+
+// Hungarian QWERTZ, in its variant for 101-key boards: í on the key left of
+// 1, which types 0 with AltGr, since ANSI boards lack the ISO key that has í
+// on 102-key boards. The key right of 9 has ö, and ő ú ű é á are right of
+// the letters; the long ő and ű are keys of their own.
+const HUNGARIAN_ROWS: [(&str, &str); 4] = [
+    ("í123456789öüó", "Í'\"+!%/=()ÖÜÓ"),
+    ("qwertzuiopőúű", "QWERTZUIOPŐÚŰ"),
+    ("asdfghjkléá", "ASDFGHJKLÉÁ"),
+    ("yxcvbnm,.-", "YXCVBNM?:_"),
+];
+
+const HUNGARIAN_ALTGR_KEYS: [AltGrKey; 1] = [AltGrKey {
+    key: '`',
+    unshifted: '0',
+    shifted: '§',
+}];
+
+// Romanian (standard). ă î â are right of P and ș ț right of L, with the
+// comma below. Older fonts and keyboards used ş and ţ with a cedilla, which
+// are taken as the same letters.
+const ROMANIAN_ROWS: [(&str, &str); 4] = [
+    ("„1234567890-=", "”!@#$%^&*()_+"),
+    ("qwertyuiopăîâ", "QWERTYUIOPĂÎÂ"),
+    ("asdfghjklșț", "ASDFGHJKLȘȚ"),
+    ("zxcvbnm,./", "ZXCVBNM;:?"),
+];
+
+const ROMANIAN_ALIASES: [(char, char); 4] = [('ş', 'ș'), ('Ş', 'Ș'), ('ţ', 'ț'), ('Ţ', 'Ț')];
+
+// Lithuanian. The number row types ą č ę ė į š ų ū, „ “ and ž, with the
+// capitals on shift; the digits and their usual symbols are on AltGr.
+const LITHUANIAN_ROWS: [(&str, &str); 4] = [
+    ("`ąčęėįšųū„“-ž", "~ĄČĘĖĮŠŲŪ()_Ž"),
+    ("qwertyuiop[]\\", "QWERTYUIOP{}|"),
+    ("asdfghjkl;'", "ASDFGHJKL:\""),
+    ("zxcvbnm,./", "ZXCVBNM<>?"),
+];
+
+const LITHUANIAN_ALTGR_KEYS: [AltGrKey; 11] = [
+    AltGrKey {
+        key: '1',
+        unshifted: '1',
+        shifted: '!',
+    },
+    AltGrKey {
+        key: '2',
+        unshifted: '2',
+        shifted: '@',
+    },
+    AltGrKey {
+        key: '3',
+        unshifted: '3',
+        shifted: '#',
+    },
+    AltGrKey {
+        key: '4',
+        unshifted: '4',
+        shifted: '$',
+    },
+    AltGrKey {
+        key: '5',
+        unshifted: '5',
+        shifted: '%',
+    },
+    AltGrKey {
+        key: '6',
+        unshifted: '6',
+        shifted: '^',
+    },
+    AltGrKey {
+        key: '7',
+        unshifted: '7',
+        shifted: '&',
+    },
+    AltGrKey {
+        key: '8',
+        unshifted: '8',
+        shifted: '*',
+    },
+    AltGrKey {
+        key: '9',
+        unshifted: '9',
+        shifted: '(',
+    },
+    AltGrKey {
+        key: '0',
+        unshifted: '0',
+        shifted: ')',
+    },
+    AltGrKey {
+        key: '=',
+        unshifted: '=',
+        shifted: '+',
+    },
+];
+
+// Latvian (apostrophe). QWERTY, with the apostrophe key as a dead key for
+// ā č ē ģ ī ķ ļ ņ š ū ž; the apostrophe itself is typed with it and space.
+const LATVIAN_APOSTROPHE: DeadKey = DeadKey {
+    accent: '\'',
+    bases: "acegiklnsuzACEGIKLNSUZ",
+    composed: "āčēģīķļņšūžĀČĒĢĪĶĻŅŠŪŽ",
+};
+
+const LATVIAN_DEAD_KEYS: [DeadKey; 1] = [LATVIAN_APOSTROPHE];
+
+// Latvian (ergonomic, ŪGJRMV), which places the Latvian letters by
+// frequency. The ķ key sits where ANSI boards have backslash; ģ is on the
+// ISO key left of Z, which ANSI boards lack, and also on AltGr with 2. Q W
+// Y X, which Latvian does not use, are on AltGr.
+const LATVIAN_ERGONOMIC_ROWS: [(&str, &str); 4] = [
+    ("`1234567890-f", "~!@#$%^&*()_F"),
+    ("ūgjrmvnzēčžhķ", "ŪGJRMVNZĒČŽHĶ"),
+    ("šusildatec'", "ŠUSILDATEC\""),
+    ("ņbīkpoā,.ļ", "ŅBĪKPOĀ;:Ļ"),
+];
+
+const LATVIAN_ERGONOMIC_ALTGR_KEYS: [AltGrKey; 8] = [
+    AltGrKey {
+        key: 'q',
+        unshifted: 'q',
+        shifted: 'Q',
+    },
+    AltGrKey {
+        key: 'y',
+        unshifted: 'w',
+        shifted: 'W',
+    },
+    AltGrKey {
+        key: 'u',
+        unshifted: 'y',
+        shifted: 'Y',
+    },
+    AltGrKey {
+        key: 'x',
+        unshifted: 'x',
+        shifted: 'X',
+    },
+    AltGrKey {
+        key: '2',
+        unshifted: 'ģ',
+        shifted: 'Ģ',
+    },
+    AltGrKey {
+        key: 'r',
+        unshifted: 'ŗ',
+        shifted: 'Ŗ',
+    },
+    AltGrKey {
+        key: 'n',
+        unshifted: 'ō',
+        shifted: 'Ō',
+    },
+    AltGrKey {
+        key: '=',
+        unshifted: '=',
+        shifted: '+',
+    },
+];
+
+// Estonian. ü õ are right of P and ö ä right of L. The key left of 1 has
+// the caron dead key for š and ž, with the tilde on shift, and the key left
+// of Backspace the acute, with the grave on shift.
+const ESTONIAN_ROWS: [(&str, &str); 4] = [
+    ("ˇ1234567890+´", "~!\"#¤%&/()=?`"),
+    ("qwertyuiopüõ'", "QWERTYUIOPÜÕ*"),
+    ("asdfghjklöä", "ASDFGHJKLÖÄ"),
+    ("zxcvbnm,.-", "ZXCVBNM;:_"),
+];
+
+const ESTONIAN_DEAD_KEYS: [DeadKey; 4] = [CARON, TILDE, ACUTE, GRAVE];
+
+// Icelandic. ð is right of P and æ right of L, ö right of 0 and þ in place
+// of the slash. The key right of æ has the acute dead key on both levels,
+// for á é í ó ú ý, and the key left of 1 the ring, with the diaeresis on
+// shift.
+const ICELANDIC_ROWS: [(&str, &str); 4] = [
+    ("°1234567890ö-", "¨!\"#$%&/()=Ö_"),
+    ("qwertyuiopð'+", "QWERTYUIOPÐ?*"),
+    ("asdfghjklæ´", "ASDFGHJKLÆ´"),
+    ("zxcvbnm,.þ", "ZXCVBNM;:Þ"),
+];
+
+const ICELANDIC_DEAD_KEYS: [DeadKey; 3] = [ACUTE, RING, DIAERESIS];
+
+// Mongolian Cyrillic. ө and ү have keys of their own, е and щ are right of
+// 0, and shift on the digits types punctuation in an order of its own, with
+// № on 1 and the tögrög sign on 4. The parentheses are on the ISO key left
+// of Z, which ANSI boards lack.
+const MONGOLIAN_ROWS: [(&str, &str); 4] = [
+    ("=1234567890ещ", "+№-\"₮:._,%?ЕЩ"),
+    ("фцужэнгшүзкъ!", "ФЦУЖЭНГШҮЗКЪ|"),
+    ("йыбөахролдп", "ЙЫБӨАХРОЛДП"),
+    ("ячёсмитьвю", "ЯЧЁСМИТЬВЮ"),
+];
+
+// Russian (typewriter). The letters are as on the PC layout apart from ё,
+// which takes the slash key, but the number row types punctuation unshifted
+// and the digits with shift.
+const RUSSIAN_TYPEWRITER_ROWS: [(&str, &str); 4] = [
+    ("|№-/\":,._?%!;", "+1234567890=\\"),
+    ("йцукенгшщзхъ)", "ЙЦУКЕНГШЩЗХЪ("),
+    ("фывапролджэ", "ФЫВАПРОЛДЖЭ"),
+    ("ячсмитьбюё", "ЯЧСМИТЬБЮЁ"),
+];
+
+// Russian (Apple). The letters are as on the PC layout apart from ё, which
+// takes the backslash key; the key left of 1 has ] and [, and shift on the
+// digits types ! " № % : , . ; with the slash right of ю.
+const RUSSIAN_MAC_ROWS: [(&str, &str); 4] = [
+    ("]1234567890-=", "[!\"№%:,.;()_+"),
+    ("йцукенгшщзхъё", "ЙЦУКЕНГШЩЗХЪЁ"),
+    ("фывапролджэ", "ФЫВАПРОЛДЖЭ"),
+    ("ячсмитьбю/", "ЯЧСМИТЬБЮ?"),
+];
@@ -0,0 +1,54 @@
+pub mod analyze;
+pub mod cache;
+pub mod canonical;
+pub mod config;
+pub mod converter;
+#[cfg(all(feature = "evdev", target_os = "linux"))]
+pub mod daemon;
+#[cfg(feature = "dbus")]
+pub mod dbus;
+pub mod detect;
+pub mod diff;
+pub mod document;
+pub mod drills;
+pub mod edits;
+pub mod emacs;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod files;
+pub mod firmware;
+pub mod geometry;
+pub mod hangul;
+#[cfg(feature = "server")]
+pub mod http;
+#[cfg(all(feature = "ibus", target_os = "linux"))]
+pub mod ibus;
+pub mod kana;
+pub mod keys;
+pub mod klc;
+pub mod layouts;
+#[cfg(all(feature = "mactap", target_os = "macos"))]
+pub mod mactap;
+pub mod models;
+pub mod profiles;
+pub mod qmk;
+pub mod registry;
+pub mod remap;
+pub mod remapper;
+pub mod schema;
+#[cfg(target_os = "linux")]
+pub mod selection;
+pub mod shortcuts;
+#[cfg(unix)]
+pub mod systemd;
+pub mod telex;
+pub mod trigram;
+pub mod undo;
+pub mod validate;
+pub mod vim;
+pub mod visualize;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(all(feature = "winhook", windows))]
+pub mod winhook;
+pub mod xkb;
+pub mod zmk;
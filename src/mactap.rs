@@ -0,0 +1,168 @@
+// macOS mode that installs a CGEvent tap and, on a hotkey, retypes the
+// current selection with its wrong-layout words fixed. The selection is read
+// by sending Cmd+C, and the replacement is typed as Unicode key events, which
+// replaces the selection in place. The clipboard is restored afterwards. The
+// tap needs the Accessibility permission.
+use crate::detect;
+use crate::layouts::LayoutCode;
+use core_foundation::runloop::{kCFRunLoopCommonModes, CFRunLoop};
+use core_graphics::event::{
+    CGEvent, CGEventFlags, CGEventTap, CGEventTapLocation, CGEventTapOptions, CGEventTapPlacement,
+    CGEventType, CGKeyCode, EventField,
+};
+use core_graphics::event_source::{CGEventSource, CGEventSourceStateID};
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::Duration;
+
+const KEY_C: CGKeyCode = 8;
+
+// Longest string a single keyboard event carries, in UTF-16 units
+const CHUNK: usize = 20;
+
+// How long to wait for the frontmost application to copy the selection
+const COPY_DELAY: Duration = Duration::from_millis(150);
+
+// Virtual keycodes for the supported hotkey names, which follow the evdev
+// names used by the Linux daemon. PC keyboards send F15 for Pause and F14
+// for Scroll Lock on macOS.
+const F_KEYS: [CGKeyCode; 20] = [
+    122, 120, 99, 118, 96, 97, 98, 100, 101, 109, 103, 111, 105, 107, 113, 106, 64, 79, 80, 90,
+];
+
+pub fn parse_hotkey(name: &str) -> Option<CGKeyCode> {
+    match name {
+        "KEY_PAUSE" => Some(F_KEYS[14]),
+        "KEY_SCROLLLOCK" => Some(F_KEYS[13]),
+        _ => {
+            let number: usize = name.strip_prefix("KEY_F")?.parse().ok()?;
+            F_KEYS.get(number.checked_sub(1)?).copied()
+        }
+    }
+}
+
+fn source() -> Result<CGEventSource, String> {
+    CGEventSource::new(CGEventSourceStateID::HIDSystemState)
+        .map_err(|_| "cannot create an event source".to_string())
+}
+
+fn clipboard() -> Result<String, String> {
+    let output = Command::new("pbpaste")
+        .output()
+        .map_err(|err| format!("cannot run pbpaste: {}", err))?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn set_clipboard(text: &str) -> Result<(), String> {
+    let mut child = Command::new("pbcopy")
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("cannot run pbcopy: {}", err))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(text.as_bytes())
+            .map_err(|err| format!("cannot write to pbcopy: {}", err))?;
+    }
+    child
+        .wait()
+        .map_err(|err| format!("cannot run pbcopy: {}", err))?;
+    Ok(())
+}
+
+fn copy_selection() -> Result<(), String> {
+    for down in [true, false] {
+        let event = CGEvent::new_keyboard_event(source()?, KEY_C, down)
+            .map_err(|_| "cannot create a key event".to_string())?;
+        event.set_flags(CGEventFlags::CGEventFlagCommand);
+        event.post(CGEventTapLocation::HID);
+    }
+    Ok(())
+}
+
+fn type_text(text: &str) -> Result<(), String> {
+    let units: Vec<u16> = text.encode_utf16().collect();
+    for chunk in units.chunks(CHUNK) {
+        let chunk = String::from_utf16_lossy(chunk);
+        for down in [true, false] {
+            let event = CGEvent::new_keyboard_event(source()?, 0, down)
+                .map_err(|_| "cannot create a key event".to_string())?;
+            event.set_string(&chunk);
+            event.post(CGEventTapLocation::HID);
+        }
+    }
+    Ok(())
+}
+
+fn fix_selection(layouts: &[LayoutCode]) -> Result<(), String> {
+    let saved = clipboard()?;
+    set_clipboard("")?;
+    copy_selection()?;
+    std::thread::sleep(COPY_DELAY);
+    let selection = clipboard()?;
+
+    let result = if selection.is_empty() {
+        log::info!("nothing selected");
+        Ok(())
+    } else {
+        let fixed = detect::fix_text(&selection, layouts);
+        if fixed == selection {
+            log::info!("the selection looks correctly typed");
+            Ok(())
+        } else {
+            log::info!("retyping '{}' as '{}'", selection, fixed);
+            type_text(&fixed)
+        }
+    };
+    set_clipboard(&saved)?;
+    result
+}
+
+// Installs the event tap and runs until the process is stopped. `hotkey` is a
+// name accepted by `parse_hotkey`.
+pub fn run(layouts: Vec<LayoutCode>, hotkey: &str) -> Result<(), String> {
+    let hotkey = parse_hotkey(hotkey).ok_or_else(|| format!("unknown key '{}'", hotkey))?;
+
+    // The tap callback only signals; the fixing happens on its own thread
+    // since it waits for other applications
+    let (requests, pending) = mpsc::channel::<()>();
+    std::thread::spawn(move || {
+        for () in pending {
+            if let Err(err) = fix_selection(&layouts) {
+                log::error!("{}", err);
+            }
+        }
+    });
+
+    let tap = CGEventTap::new(
+        CGEventTapLocation::Session,
+        CGEventTapPlacement::HeadInsertEventTap,
+        CGEventTapOptions::Default,
+        vec![CGEventType::KeyDown],
+        move |_, _, event| {
+            let keycode = event.get_integer_value_field(EventField::KEYBOARD_EVENT_KEYCODE);
+            if keycode == hotkey as i64 {
+                requests.send(()).ok();
+                // Swallow the hotkey
+                event.set_type(CGEventType::Null);
+            }
+            None
+        },
+    )
+    .map_err(|_| {
+        "cannot create the event tap, allow keymorph under System Settings > \
+         Privacy & Security > Accessibility"
+            .to_string()
+    })?;
+
+    unsafe {
+        let source = tap
+            .mach_port
+            .create_runloop_source(0)
+            .map_err(|_| "cannot attach the event tap to the run loop".to_string())?;
+        CFRunLoop::get_current().add_source(&source, kCFRunLoopCommonModes);
+    }
+    tap.enable();
+    CFRunLoop::run_current();
+    Ok(())
+}
@@ -1,48 +1,169 @@
-mod layouts;
-mod models;
+mod cli;
 
-use actix_web::middleware::Logger;
-use actix_web::{get, post, web, App, HttpResponse, HttpServer, Responder};
-use std::str::FromStr;
+use clap::Parser;
+use keymorph::config::Config;
+use keymorph::detect;
+use keymorph::layouts;
+use keymorph::registry;
+use std::process::ExitCode;
 
-#[get("/api/healthchecker")]
-async fn health_checker_handler() -> impl Responder {
-    const MESSAGE: &str = "JWT Authentication in Rust using Actix-web, Postgres, and SQLX";
+fn main() -> ExitCode {
+    let args = cli::Cli::parse();
 
-    HttpResponse::Ok().json(serde_json::json!({"status": "success", "message": MESSAGE}))
+    let mut config = match Config::load(args.config.as_deref()) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            return ExitCode::from(2);
+        }
+    };
+    layouts::set_parallelism(config.conversion.parallelism());
+    detect::set_backend(config.detection.backend);
+
+    match args
+        .command
+        .unwrap_or(cli::Command::Serve(cli::ServeArgs::default()))
+    {
+        cli::Command::Serve(serve_args) => {
+            serve_args.apply(&mut config);
+            serve(&config)
+        }
+        cli::Command::Layouts { command } => cli::layouts(command, &config),
+        cli::Command::Convert {
+            from,
+            to,
+            options,
+            output,
+        } => cli::convert(&from, &to, &options, &output, &config),
+        cli::Command::Fix {
+            layouts,
+            backend,
+            by_segment,
+            min_confidence,
+            output,
+        } => cli::fix(
+            &layouts,
+            backend,
+            by_segment.then_some(min_confidence),
+            &output,
+            &config,
+        ),
+        cli::Command::Analyze {
+            file,
+            layouts,
+            json,
+            tables,
+        } => cli::analyze(&file, layouts.as_deref(), json, &tables, &config),
+        cli::Command::Shortcut {
+            shortcuts,
+            from,
+            to,
+            by,
+        } => cli::shortcut(&shortcuts, &from, &to, by),
+        cli::Command::RemapVim { from, to, output } => cli::remap_vim(&from, &to, &output),
+        cli::Command::RemapEmacs { from, to, output } => cli::remap_emacs(&from, &to, &output),
+        cli::Command::RemapProfile {
+            from,
+            to,
+            format,
+            fields,
+            output,
+        } => cli::remap_profile(&from, &to, format, &fields, &output),
+        cli::Command::Keys { command } => cli::keys(command),
+        #[cfg(all(feature = "evdev", target_os = "linux"))]
+        cli::Command::Daemon(daemon_args) => {
+            daemon_args.apply(&mut config);
+            init_logging(&config);
+            cli::daemon(daemon_args.list_devices, &config)
+        }
+        #[cfg(all(feature = "winhook", windows))]
+        cli::Command::Hook(hook_args) => {
+            hook_args.apply(&mut config);
+            init_logging(&config);
+            cli::hook(&config)
+        }
+        #[cfg(target_os = "linux")]
+        cli::Command::Selection {
+            layouts,
+            clipboard,
+            #[cfg(feature = "evdev")]
+            watch,
+        } => {
+            #[cfg(not(feature = "evdev"))]
+            let watch = false;
+            init_logging(&config);
+            cli::selection(layouts.as_deref(), clipboard, watch, &config)
+        }
+        #[cfg(feature = "dbus")]
+        cli::Command::Dbus { no_watch } => {
+            if no_watch {
+                config.layouts.watch = false;
+            }
+            init_logging(&config);
+            if let Err(code) = load_layouts(&config) {
+                return code;
+            }
+            let converter = keymorph::dbus::Converter::new(&config, args.config.clone());
+            match keymorph::dbus::run(converter) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(err) => {
+                    eprintln!("Error: {}", err);
+                    ExitCode::FAILURE
+                }
+            }
+        }
+        #[cfg(all(feature = "ibus", target_os = "linux"))]
+        cli::Command::Ime {
+            layouts,
+            xml,
+            ibus: _,
+        } => {
+            init_logging(&config);
+            cli::ime(layouts.as_deref(), xml, &config)
+        }
+        #[cfg(all(feature = "mactap", target_os = "macos"))]
+        cli::Command::Tap(tap_args) => {
+            tap_args.apply(&mut config);
+            init_logging(&config);
+            cli::hook(&config)
+        }
+    }
+}
+
+fn init_logging(config: &Config) {
+    env_logger::Builder::new()
+        .parse_filters(&config.logging.filter)
+        .init();
 }
 
-#[post("/api/convert")]
-async fn convert_text_handler(text_schema: web::Json<models::TextSchema>) -> impl Responder {
-    let from_result = layouts::LayoutCode::from_str(&text_schema.from);
-    let to_result = layouts::LayoutCode::from_str(&text_schema.to);
-
-    if let (Ok(from), Ok(to)) = (from_result, to_result) {
-        let converted_text = layouts::parallel_convert_text(text_schema.text.clone(), from, to);
-        HttpResponse::Ok().json(serde_json::json!({"status": "success", "data": converted_text}))
-    } else {
-        HttpResponse::BadRequest().json(
-            serde_json::json!({"status": "error", "message": "Invalid layout codes provided."}),
-        )
+// Registers the configured layout directories, watching them if enabled
+fn load_layouts(config: &Config) -> Result<(), ExitCode> {
+    for dir in &config.layouts.dirs {
+        if let Err(err) = registry::load_dir(dir) {
+            eprintln!("Error: cannot read {}: {}", dir.display(), err);
+            return Err(ExitCode::FAILURE);
+        }
+        if config.layouts.watch {
+            if let Err(err) = registry::watch(dir) {
+                eprintln!("Error: cannot watch {}: {}", dir.display(), err);
+                return Err(ExitCode::FAILURE);
+            }
+        }
     }
+    Ok(())
 }
 
-#[actix_web::main]
-async fn main() -> std::io::Result<()> {
-    if std::env::var_os("RUST_LOG").is_none() {
-        std::env::set_var("RUST_LOG", "actix_web=info");
+fn serve(config: &Config) -> ExitCode {
+    init_logging(config);
+    if let Err(code) = load_layouts(config) {
+        return code;
+    }
+
+    match actix_web::rt::System::new().block_on(keymorph::http::serve(config)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            ExitCode::FAILURE
+        }
     }
-    env_logger::init();
-
-    println!("🚀 Server started successfully");
-
-    HttpServer::new(move || {
-        App::new()
-            .wrap(Logger::default())
-            .service(health_checker_handler)
-            .service(convert_text_handler)
-    })
-    .bind(("127.0.0.1", 8000))?
-    .run()
-    .await
 }
@@ -1,9 +1,166 @@
+use crate::analyze::Metric;
+use crate::drills::DrillFocus;
+use crate::keys::KeyEvent;
+use crate::layouts::YoPolicy;
+use crate::profiles::ProfileFormat;
+use crate::shortcuts::KeySemantics;
+use crate::visualize::{HeatScale, ImageFormat};
 use serde::{Deserialize, Serialize};
 
-
 #[derive(Deserialize, Serialize)]
 pub struct TextSchema {
     pub text: String,
     pub from: String,
     pub to: String,
+    #[serde(default)]
+    pub yo_policy: YoPolicy,
+    // Same as `yo_policy: "fold_to_e"`
+    #[serde(default)]
+    pub fold_yo: bool,
+    // Leave punctuation and symbols as they are, converting letters only
+    #[serde(default)]
+    pub keep_punctuation: bool,
+    #[serde(default)]
+    pub format: ResultFormat,
+    // Also return an `undo_token` that reverts the conversion
+    #[serde(default)]
+    pub undo_token: bool,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct UndoSchema {
+    // The converted text, as returned
+    pub text: String,
+    pub undo_token: String,
+}
+
+// How a conversion is returned: the converted text, or the words it changed
+// as `edits::Edit`s for clients that apply it as edits
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ResultFormat {
+    #[default]
+    Text,
+    Spans,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct KeyEventsSchema {
+    pub layout: String,
+    pub events: Vec<KeyEvent>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct LayoutTextSchema {
+    pub layout: String,
+    pub text: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ShortcutsSchema {
+    pub shortcuts: Vec<String>,
+    pub from: String,
+    pub to: String,
+    #[serde(default)]
+    pub semantics: KeySemantics,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct RemapSchema {
+    pub source: String,
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct ProfileSchema {
+    pub profile: String,
+    #[serde(default)]
+    pub format: ProfileFormat,
+    pub from: String,
+    pub to: String,
+    // Names of the fields holding bindings, `profiles::DEFAULT_FIELDS` if
+    // not given
+    #[serde(default)]
+    pub fields: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct AnalyzeSchema {
+    pub text: String,
+    pub layout: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct CompareSchema {
+    pub text: String,
+    pub layouts: Vec<String>,
+    #[serde(default)]
+    pub rank_by: Metric,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct DocumentSchema {
+    pub text: String,
+    pub layouts: Vec<String>,
+    // Defaults to `document::DEFAULT_MIN_CONFIDENCE`
+    pub min_confidence: Option<f64>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct SimilarityQuery {
+    pub a: String,
+    pub b: String,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct DrillQuery {
+    // Layout the user types now, QWERTY if not given
+    pub from: Option<String>,
+    #[serde(default)]
+    pub focus: DrillFocus,
+    // Language of the words, the one usually typed on the layout if not
+    // given
+    pub lang: Option<String>,
+    pub lines: Option<usize>,
+    pub seed: Option<u64>,
+}
+
+// What `GET /layouts/{id}/keyboard` colours the keys by, see
+// `visualize::Overlay`
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum OverlayKind {
+    #[default]
+    None,
+    Heatmap,
+    Diff,
+    Fingers,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct KeyboardQuery {
+    #[serde(default)]
+    pub format: ImageFormat,
+    #[serde(default)]
+    pub overlay: OverlayKind,
+    // Layout a diff is against, QWERTY if not given
+    pub from: Option<String>,
+    // Text a heatmap counts the key presses of. Without it the heatmap shows
+    // the letter frequencies of `lang`, or of the language usually typed on
+    // the layout.
+    pub text: Option<String>,
+    pub lang: Option<String>,
+    #[serde(default)]
+    pub heat_scale: HeatScale,
+    // PNG size relative to the SVG one
+    pub scale: Option<f32>,
+}
+
+#[derive(Deserialize, Serialize)]
+pub struct HeatmapSchema {
+    pub text: String,
+    pub layout: String,
+    #[serde(default)]
+    pub heat_scale: HeatScale,
 }
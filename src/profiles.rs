@@ -0,0 +1,110 @@
+// Shortcut profiles, like the keybindings a game or an application exports,
+// remapped to keep every binding in its place on the keyboard after
+// switching layouts: WASD on QWERTY becomes ,AOE on Dvorak. Profiles are
+// JSON or YAML of any shape; the bindings are the strings under fields with
+// one of the given names, each a key or a shortcut like "Shift+W".
+use crate::layouts::LayoutCode;
+use crate::shortcuts::{self, KeySemantics};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use std::str::FromStr;
+
+// Fields holding bindings when no others are given
+pub const DEFAULT_FIELDS: [&str; 10] = [
+    "key",
+    "keys",
+    "binding",
+    "bindings",
+    "keybind",
+    "keybinding",
+    "shortcut",
+    "hotkey",
+    "primary",
+    "secondary",
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ProfileFormat {
+    #[default]
+    Json,
+    Yaml,
+}
+
+impl FromStr for ProfileFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(ProfileFormat::Json),
+            "yaml" | "yml" => Ok(ProfileFormat::Yaml),
+            _ => Err(()),
+        }
+    }
+}
+
+impl ProfileFormat {
+    // The format of a file, by its extension
+    pub fn of(path: &Path) -> Option<ProfileFormat> {
+        path.extension()?.to_str()?.to_lowercase().parse().ok()
+    }
+}
+
+// Remaps every string in `value`
+fn remap_all(value: &mut Value, from: LayoutCode, to: LayoutCode) {
+    match value {
+        Value::String(binding) => {
+            *binding = shortcuts::remap_shortcut(binding, from, to, KeySemantics::Position);
+        }
+        Value::Array(values) => values
+            .iter_mut()
+            .for_each(|value| remap_all(value, from, to)),
+        Value::Object(fields) => fields
+            .values_mut()
+            .for_each(|value| remap_all(value, from, to)),
+        _ => {}
+    }
+}
+
+// Remaps the bindings under `fields` (matched ignoring case) anywhere in
+// `value`
+pub fn remap_bindings(value: &mut Value, from: LayoutCode, to: LayoutCode, fields: &[&str]) {
+    match value {
+        Value::Array(values) => values
+            .iter_mut()
+            .for_each(|value| remap_bindings(value, from, to, fields)),
+        Value::Object(object) => {
+            for (name, value) in object.iter_mut() {
+                if fields.iter().any(|field| field.eq_ignore_ascii_case(name)) {
+                    remap_all(value, from, to);
+                } else {
+                    remap_bindings(value, from, to, fields);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+// Reads a profile written for `from`, remaps its bindings for `to` and
+// writes it back in the same format
+pub fn remap_profile(
+    profile: &str,
+    format: ProfileFormat,
+    from: LayoutCode,
+    to: LayoutCode,
+    fields: &[&str],
+) -> Result<String, String> {
+    let mut value: Value = match format {
+        ProfileFormat::Json => serde_json::from_str(profile).map_err(|err| err.to_string())?,
+        ProfileFormat::Yaml => serde_yaml::from_str(profile).map_err(|err| err.to_string())?,
+    };
+    remap_bindings(&mut value, from, to, fields);
+    match format {
+        ProfileFormat::Json => serde_json::to_string_pretty(&value)
+            .map(|json| json + "\n")
+            .map_err(|err| err.to_string()),
+        ProfileFormat::Yaml => serde_yaml::to_string(&value).map_err(|err| err.to_string()),
+    }
+}
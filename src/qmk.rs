@@ -0,0 +1,179 @@
+// QMK keymaps, as the keymap.c a QMK build compiles or the keymap.json of
+// QMK Configurator and `qmk compile`. They have a single layer, the one
+// typing the layout; keys the layout needs that the host cannot type are
+// left unbound and listed at the top.
+use crate::firmware::{Binding, Board, Exporter, Keymap, Special};
+use crate::registry::Layout;
+use serde_json::json;
+
+// QMK keycode of each QWERTY key of the main block
+const KEYCODES: [(char, &str); 21] = [
+    ('`', "KC_GRV"),
+    ('-', "KC_MINS"),
+    ('=', "KC_EQL"),
+    ('[', "KC_LBRC"),
+    (']', "KC_RBRC"),
+    ('\\', "KC_BSLS"),
+    (';', "KC_SCLN"),
+    ('\'', "KC_QUOT"),
+    (',', "KC_COMM"),
+    ('.', "KC_DOT"),
+    ('/', "KC_SLSH"),
+    ('0', "KC_0"),
+    ('1', "KC_1"),
+    ('2', "KC_2"),
+    ('3', "KC_3"),
+    ('4', "KC_4"),
+    ('5', "KC_5"),
+    ('6', "KC_6"),
+    ('7', "KC_7"),
+    ('8', "KC_8"),
+    ('9', "KC_9"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Qmk {
+    KeymapC,
+    KeymapJson,
+}
+
+impl Exporter for Qmk {
+    fn name(&self) -> &'static str {
+        "QMK"
+    }
+
+    fn supports(&self, board: Board) -> bool {
+        target(board).is_some()
+    }
+
+    fn export(&self, layout: &Layout, keymap: &Keymap) -> String {
+        match self {
+            Qmk::KeymapC => keymap_c(layout, keymap),
+            Qmk::KeymapJson => keymap_json(layout, keymap) + "\n",
+        }
+    }
+}
+
+// The QMK keyboard and layout macro keymaps for `board` are written for
+fn target(board: Board) -> Option<(&'static str, &'static str)> {
+    match board {
+        Board::Ansi60 => Some(("gh60/satan", "LAYOUT_60_ansi")),
+        Board::Planck => Some(("planck/rev6", "LAYOUT_ortho_4x12")),
+        Board::Corne => Some(("crkbd/rev1", "LAYOUT_split_3x6_3")),
+        Board::Glove80 => None,
+    }
+}
+
+fn special(special: Special) -> String {
+    let keycode = match special {
+        Special::Escape => "KC_ESC",
+        Special::Tab => "KC_TAB",
+        Special::CapsLock => "KC_CAPS",
+        Special::Backspace => "KC_BSPC",
+        Special::Enter => "KC_ENT",
+        Special::Space => "KC_SPC",
+        Special::LeftShift => "KC_LSFT",
+        Special::RightShift => "KC_RSFT",
+        Special::LeftCtrl => "KC_LCTL",
+        Special::RightCtrl => "KC_RCTL",
+        Special::LeftAlt => "KC_LALT",
+        Special::RightAlt => "KC_RALT",
+        Special::LeftGui => "KC_LGUI",
+        Special::RightGui => "KC_RGUI",
+        Special::Menu => "KC_APP",
+        Special::Left => "KC_LEFT",
+        Special::Down => "KC_DOWN",
+        Special::Up => "KC_UP",
+        Special::Right => "KC_RGHT",
+        Special::Home => "KC_HOME",
+        Special::End => "KC_END",
+        Special::PageUp => "KC_PGUP",
+        Special::PageDown => "KC_PGDN",
+        Special::Delete => "KC_DEL",
+        Special::Function(n) => return format!("KC_F{}", n),
+    };
+    keycode.to_string()
+}
+
+pub fn keycode(binding: Binding) -> String {
+    match binding {
+        Binding::Key { key, shift } => {
+            let keycode = match KEYCODES.iter().find(|&&(qwerty, _)| qwerty == key) {
+                Some(&(_, keycode)) => keycode.to_string(),
+                None => format!("KC_{}", key.to_ascii_uppercase()),
+            };
+            if shift {
+                format!("S({})", keycode)
+            } else {
+                keycode
+            }
+        }
+        Binding::Special(key) => special(key),
+        Binding::None => "KC_NO".to_string(),
+    }
+}
+
+// Name of the keymap directory, after the layout
+fn keymap_name(layout: &Layout) -> String {
+    let name: String = layout
+        .name()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("keymorph_{}", name)
+}
+
+fn keymap_c(layout: &Layout, keymap: &Keymap) -> String {
+    let (_, macro_name) = target(keymap.board).expect("a QMK board");
+    let mut c = format!(
+        "// {} for {}, generated by keymorph\n",
+        layout.name(),
+        keymap.board.as_str()
+    );
+    for problem in &keymap.problems {
+        c += &format!("// {}\n", problem);
+    }
+    c += "\n#include QMK_KEYBOARD_H\n\n\
+          const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {\n";
+    c += &format!("    [0] = {}(\n", macro_name);
+    let rows: Vec<String> = keymap
+        .rows
+        .iter()
+        .map(|row| {
+            let keycodes: Vec<String> = row.iter().map(|&binding| keycode(binding)).collect();
+            format!("        {}", keycodes.join(", "))
+        })
+        .collect();
+    c += &rows.join(",\n");
+    c += "\n    )\n};\n";
+    c
+}
+
+fn keymap_json(layout: &Layout, keymap: &Keymap) -> String {
+    let (keyboard, macro_name) = target(keymap.board).expect("a QMK board");
+    let mut notes = format!("{}, generated by keymorph.", layout.name());
+    for problem in &keymap.problems {
+        notes += &format!(" {}.", problem);
+    }
+    let layer: Vec<String> = keymap
+        .rows
+        .iter()
+        .flatten()
+        .map(|&binding| keycode(binding))
+        .collect();
+    let json = json!({
+        "version": 1,
+        "notes": notes,
+        "keyboard": keyboard,
+        "keymap": keymap_name(layout),
+        "layout": macro_name,
+        "layers": [layer],
+    });
+    serde_json::to_string_pretty(&json).expect("keymaps always serialize to JSON")
+}
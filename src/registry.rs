@@ -0,0 +1,354 @@
+use crate::converter::Converter;
+use crate::layouts::{
+    self, CasePolicy, LayoutCode, LayoutId, LayoutOptions, SentenceCase, YoPolicy,
+};
+use crate::schema::{self, LayoutFile};
+use crate::validate::{self, Severity};
+use lazy_static::lazy_static;
+use log::{error, info, warn};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+
+// A custom layout loaded from disk, resolved against QWERTY
+#[derive(Debug)]
+pub struct CustomLayout {
+    pub name: String,
+    pub path: PathBuf,
+    from_qwerty: HashMap<char, char>,
+    to_qwerty: HashMap<char, char>,
+}
+
+impl CustomLayout {
+    // Builds the layout from a file that passed validation without errors
+    fn new(file: &LayoutFile, path: &Path) -> Option<Self> {
+        let base = LayoutCode::from_str(&file.base).ok()?;
+        let keys: HashMap<char, char> = file
+            .keys
+            .iter()
+            .filter_map(|(k, v)| Some((k.chars().next()?, v.chars().next()?)))
+            .collect();
+
+        let mut from_qwerty = HashMap::new();
+        for (lower, upper) in layouts::shift_pairs() {
+            for key in [lower, upper] {
+                let on_base = layouts::char_at(base, key);
+                let output = keys.get(&on_base).copied().unwrap_or(on_base);
+                if output != key {
+                    from_qwerty.insert(key, output);
+                }
+            }
+        }
+        let to_qwerty = from_qwerty.iter().map(|(&k, &v)| (v, k)).collect();
+
+        Some(CustomLayout {
+            name: file.name.to_lowercase(),
+            path: path.to_path_buf(),
+            from_qwerty,
+            to_qwerty,
+        })
+    }
+}
+
+// Either a built-in layout or one registered from the layouts directory
+#[derive(Clone, Debug)]
+pub enum Layout {
+    Builtin(LayoutCode),
+    Custom(Arc<CustomLayout>),
+}
+
+impl Layout {
+    pub fn name(&self) -> &str {
+        match self {
+            Layout::Builtin(code) => code.as_str(),
+            Layout::Custom(custom) => &custom.name,
+        }
+    }
+
+    // QWERTY character of the key that produces `c` on this layout
+    fn key_for(&self, c: char) -> char {
+        match self {
+            Layout::Builtin(code) => layouts::keymap(*code, LayoutCode::Qwerty)
+                .and_then(|map| map.get(&c))
+                .copied()
+                .unwrap_or(c),
+            Layout::Custom(custom) => custom.to_qwerty.get(&c).copied().unwrap_or(c),
+        }
+    }
+
+    // Character this layout produces on the key that produces `key` on QWERTY
+    pub(crate) fn char_at(&self, key: char) -> char {
+        match self {
+            Layout::Builtin(code) => layouts::char_at(*code, key),
+            Layout::Custom(custom) => custom.from_qwerty.get(&key).copied().unwrap_or(key),
+        }
+    }
+
+    // Whether this layout types any capital letters
+    fn is_cased(&self) -> bool {
+        layouts::shift_pairs()
+            .flat_map(|(lower, upper)| [lower, upper])
+            .any(|key| self.char_at(key).is_uppercase())
+    }
+}
+
+lazy_static! {
+    static ref CUSTOM: RwLock<HashMap<String, Arc<CustomLayout>>> = RwLock::new(HashMap::new());
+}
+
+// Incremented whenever the set of custom layouts changes
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+pub fn generation() -> u64 {
+    GENERATION.load(Ordering::SeqCst)
+}
+
+// Looks up a layout by name, built-in layouts first. A `variant` parameter
+// picks a variant of a built-in layout (see `LayoutId`); other parameters
+// are only returned by `resolve_with_options`.
+pub fn resolve(name: &str) -> Option<Layout> {
+    resolve_with_options(name).map(|(layout, _)| layout)
+}
+
+pub fn resolve_with_options(name: &str) -> Option<(Layout, LayoutOptions)> {
+    if let Ok(id) = LayoutId::from_str(name) {
+        return Some((Layout::Builtin(id.layout), id.options));
+    }
+    let (name, variant, options) = layouts::parse_params(name)?;
+    if variant.is_some() {
+        return None;
+    }
+    CUSTOM
+        .read()
+        .unwrap()
+        .get(&name.to_lowercase())
+        .cloned()
+        .map(|custom| (Layout::Custom(custom), options))
+}
+
+// Names of the registered custom layouts, sorted
+pub fn names() -> Vec<String> {
+    let mut names: Vec<String> = CUSTOM.read().unwrap().keys().cloned().collect();
+    names.sort();
+    names
+}
+
+pub fn convert_text(text: &str, from: &Layout, to: &Layout) -> String {
+    convert_text_with(text, from, to, &ConvertOptions::default())
+}
+
+// Options of a conversion that change its result
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ConvertOptions {
+    // Leave punctuation and symbols as they are, converting letters only
+    pub keep_punctuation: bool,
+    pub yo_policy: YoPolicy,
+    // Only for built-in layouts
+    pub digit_symbols: bool,
+    pub case_policy: CasePolicy,
+}
+
+impl ConvertOptions {
+    // These options overridden by those given as layout parameters
+    pub fn with(self, options: LayoutOptions) -> ConvertOptions {
+        ConvertOptions {
+            keep_punctuation: options.keep_punctuation.unwrap_or(self.keep_punctuation),
+            yo_policy: options.yo_policy.unwrap_or(self.yo_policy),
+            digit_symbols: options.digit_symbols.unwrap_or(self.digit_symbols),
+            case_policy: options.case_policy.unwrap_or(self.case_policy),
+        }
+    }
+}
+
+pub fn convert_text_with(
+    text: &str,
+    from: &Layout,
+    to: &Layout,
+    options: &ConvertOptions,
+) -> String {
+    match (from, to) {
+        (Layout::Builtin(from), Layout::Builtin(to)) if *options != ConvertOptions::default() => {
+            match Converter::builder()
+                .from(*from)
+                .to(*to)
+                .keep_punctuation(options.keep_punctuation)
+                .yo_policy(options.yo_policy)
+                .digit_symbols(options.digit_symbols)
+                .case_policy(options.case_policy)
+                .build()
+            {
+                Ok(converter) => converter.convert(text),
+                Err(_) => layouts::parallel_convert_text(text, *from, *to),
+            }
+        }
+        (Layout::Builtin(from), Layout::Builtin(to)) => {
+            layouts::parallel_convert_text(text, *from, *to)
+        }
+        _ => {
+            let convert = |c: char| to.char_at(from.key_for(c));
+            // A capital converting to the same character as its lower case
+            // letter, which `CasePolicy::KeepCapitals` leaves as it is
+            let loses_case = |c: char, converted: char| {
+                let mut lower = c.to_lowercase();
+                match (lower.next(), lower.next()) {
+                    (Some(lower), None) => lower != c && convert(lower) == converted,
+                    _ => false,
+                }
+            };
+            let converted: String = text
+                .chars()
+                .map(|c| {
+                    let converted = convert(c);
+                    let is_symbol = |c: char| !c.is_alphabetic();
+                    let keep = (options.keep_punctuation && is_symbol(c) && is_symbol(converted))
+                        || (options.case_policy == CasePolicy::KeepCapitals
+                            && loses_case(c, converted));
+                    if keep {
+                        c
+                    } else {
+                        converted
+                    }
+                })
+                .collect();
+            let converted = options.yo_policy.apply(&converted);
+            if options.case_policy == CasePolicy::Sentence && !from.is_cased() && to.is_cased() {
+                let mut capitalized = String::with_capacity(converted.len());
+                SentenceCase::default().push(&converted, &mut capitalized);
+                capitalized
+            } else {
+                converted.into_owned()
+            }
+        }
+    }
+}
+
+fn is_layout_file(path: &Path) -> bool {
+    matches!(
+        path.extension().and_then(|ext| ext.to_str()),
+        Some("toml") | Some("json")
+    )
+}
+
+// Loads, validates and registers a layout file, replacing whatever was
+// previously registered from the same path. Layouts with validation errors
+// are rejected and the previous version stays registered.
+pub fn load_file(path: &Path) -> Result<String, String> {
+    let file = schema::load(path)?;
+
+    if LayoutCode::from_str(&file.name).is_ok() {
+        return Err(format!(
+            "{}: '{}' is the name of a built-in layout",
+            path.display(),
+            file.name
+        ));
+    }
+
+    let issues = validate::validate(&file);
+    let errors: Vec<&str> = issues
+        .iter()
+        .filter(|issue| issue.severity == Severity::Error)
+        .map(|issue| issue.message.as_str())
+        .collect();
+    if !errors.is_empty() {
+        return Err(format!("{}: {}", path.display(), errors.join("; ")));
+    }
+    for issue in &issues {
+        warn!("{}: {}", path.display(), issue.message);
+    }
+
+    let layout = CustomLayout::new(&file, path)
+        .ok_or_else(|| format!("{}: invalid layout", path.display()))?;
+    let name = layout.name.clone();
+
+    let mut custom = CUSTOM.write().unwrap();
+    custom.retain(|_, existing| existing.path != path);
+    if let Some(existing) = custom.get(&name) {
+        warn!(
+            "{}: replaces layout '{}' from {}",
+            path.display(),
+            name,
+            existing.path.display()
+        );
+    }
+    custom.insert(name.clone(), Arc::new(layout));
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+    Ok(name)
+}
+
+// Unregisters every layout that was loaded from `path`
+pub fn unload_file(path: &Path) {
+    CUSTOM.write().unwrap().retain(|name, existing| {
+        let keep = existing.path != path;
+        if !keep {
+            info!("Unloaded layout '{}' ({})", name, path.display());
+        }
+        keep
+    });
+    GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+fn reload(path: &Path) {
+    match load_file(path) {
+        Ok(name) => info!("Loaded layout '{}' from {}", name, path.display()),
+        Err(err) => error!("{}", err),
+    }
+}
+
+// Registers every layout file in `dir`, logging the ones that fail to load
+pub fn load_dir(dir: &Path) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if is_layout_file(&path) {
+            reload(&path);
+        }
+    }
+    Ok(())
+}
+
+// Watches `dir` on a background thread and reloads layout files as they
+// are created, changed or removed. Events are collected for a short while
+// before reloading so that editors writing a file in several steps only
+// trigger one reload.
+#[cfg(feature = "server")]
+pub fn watch(dir: &Path) -> notify::Result<()> {
+    use notify::{EventKind, RecursiveMode, Watcher};
+    use std::collections::BTreeSet;
+    use std::sync::mpsc;
+    use std::time::Duration;
+
+    const DEBOUNCE: Duration = Duration::from_millis(200);
+
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+    std::thread::spawn(move || {
+        // Keep the watcher alive for as long as events are being handled
+        let _watcher = watcher;
+        let mut pending = BTreeSet::new();
+        while let Ok(result) = rx.recv() {
+            let mut collect = |result: notify::Result<notify::Event>| match result {
+                Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+                    pending.extend(event.paths.into_iter().filter(|path| is_layout_file(path)))
+                }
+                Ok(_) => {}
+                Err(err) => error!("Layout watcher error: {}", err),
+            };
+            collect(result);
+            while let Ok(result) = rx.recv_timeout(DEBOUNCE) {
+                collect(result);
+            }
+
+            for path in std::mem::take(&mut pending) {
+                if path.exists() {
+                    reload(&path);
+                } else {
+                    unload_file(&path);
+                }
+            }
+        }
+    });
+    Ok(())
+}
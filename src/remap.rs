@@ -0,0 +1,23 @@
+// Key-level remapping between layouts, for tools that rewrite key presses
+// instead of text. Keys are named by the character they produce unshifted on
+// QWERTY, like everywhere else.
+use crate::layouts::{self, LayoutCode};
+use std::collections::HashMap;
+
+// For each key whose unshifted character on `layout` sits on a different key
+// of `system`, the key to press instead. The shift state is passed through
+// untouched, so keys whose character is only reachable with shift on
+// `system` are left alone.
+pub fn key_remap(layout: LayoutCode, system: LayoutCode) -> HashMap<char, char> {
+    let system_keys: HashMap<char, char> = layouts::shift_pairs()
+        .map(|(key, _)| (layouts::char_at(system, key), key))
+        .collect();
+
+    layouts::shift_pairs()
+        .filter_map(|(key, _)| {
+            let wanted = layouts::char_at(layout, key);
+            let target = *system_keys.get(&wanted)?;
+            (target != key).then_some((key, target))
+        })
+        .collect()
+}
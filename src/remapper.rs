@@ -0,0 +1,82 @@
+// kmonad and kanata configurations, which remap the keyboard in software the
+// way a firmware keymap does on the board, for keyboards without one. Only
+// the main block is remapped, the remapper passing the other keys through.
+use crate::firmware::{Binding, Board, Exporter, Keymap, Slot};
+use crate::registry::Layout;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Remapper {
+    Kmonad,
+    Kanata,
+}
+
+impl Exporter for Remapper {
+    fn name(&self) -> &'static str {
+        match self {
+            Remapper::Kmonad => "kmonad",
+            Remapper::Kanata => "kanata",
+        }
+    }
+
+    // Remappers see the keys of an ordinary keyboard
+    fn supports(&self, board: Board) -> bool {
+        board == Board::Ansi60
+    }
+
+    fn export(&self, layout: &Layout, keymap: &Keymap) -> String {
+        let mut config = format!(";; {}, generated by keymorph\n", layout.name());
+        for problem in &keymap.problems {
+            config += &format!(";; {}\n", problem);
+        }
+        config += match self {
+            Remapper::Kmonad => {
+                "\n;; Set input to the keyboard, as listed in /dev/input/by-id\n\
+                 (defcfg\n  \
+                 input  (device-file \"/dev/input/by-id/KEYBOARD-event-kbd\")\n  \
+                 output (uinput-sink \"keymorph\")\n  \
+                 fallthrough true)\n\n"
+            }
+            Remapper::Kanata => "\n(defcfg\n  process-unmapped-keys yes)\n\n",
+        };
+
+        let mut source = Vec::new();
+        let mut layer = Vec::new();
+        for (slots, bindings) in keymap.board.slots().iter().zip(&keymap.rows) {
+            let (keys, sent): (Vec<String>, Vec<String>) = slots
+                .iter()
+                .zip(bindings)
+                .filter_map(|(&slot, &binding)| match slot {
+                    Slot::Key(key) => Some((key_name(key), action(binding))),
+                    _ => None,
+                })
+                .unzip();
+            if keys.is_empty() {
+                continue;
+            }
+            source.push(format!("  {}", keys.join(" ")));
+            layer.push(format!("  {}", sent.join(" ")));
+        }
+        config += &format!(
+            "(defsrc\n{})\n\n(deflayer keymorph\n{})\n",
+            source.join("\n"),
+            layer.join("\n")
+        );
+        config
+    }
+}
+
+// Name of the main block key typing `key` on QWERTY, the same for both
+fn key_name(key: char) -> String {
+    match key {
+        '`' => "grv".to_string(),
+        _ => key.to_string(),
+    }
+}
+
+fn action(binding: Binding) -> String {
+    match binding {
+        Binding::Key { key, shift: false } => key_name(key),
+        Binding::Key { key, shift: true } => format!("S-{}", key_name(key)),
+        Binding::Special(_) | Binding::None => "XX".to_string(),
+    }
+}
@@ -0,0 +1,104 @@
+use crate::canonical;
+use crate::layouts::{self, LayoutCode};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+// On-disk representation of a layout: the character each key produces,
+// keyed by the character the same key produces on the base layout.
+// Keys and values are single characters stored as strings, since TOML
+// only allows string keys.
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct LayoutFile {
+    pub name: String,
+    #[serde(default = "default_base")]
+    pub base: String,
+    #[serde(default)]
+    pub keys: BTreeMap<String, String>,
+    // Informational only, ignored when a layout file is loaded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub derived: Option<Derived>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Derived {
+    pub inverse: BTreeMap<String, String>,
+    pub composite: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+fn default_base() -> String {
+    LayoutCode::Qwerty.as_str().to_string()
+}
+
+fn sorted(map: Option<&HashMap<char, char>>) -> BTreeMap<String, String> {
+    map.map(|m| {
+        m.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    })
+    .unwrap_or_default()
+}
+
+// Dumps a built-in layout, including its generated inverse and composite maps
+pub fn export(code: LayoutCode) -> LayoutFile {
+    let composite = LayoutCode::ALL
+        .iter()
+        .filter(|&&other| other != code && other != LayoutCode::Qwerty)
+        .map(|&other| {
+            (
+                other.as_str().to_string(),
+                sorted(layouts::keymap(code, other)),
+            )
+        })
+        .collect();
+
+    LayoutFile {
+        name: code.as_str().to_string(),
+        base: default_base(),
+        keys: sorted(layouts::keymap(LayoutCode::Qwerty, code)),
+        derived: Some(Derived {
+            inverse: sorted(layouts::keymap(code, LayoutCode::Qwerty)),
+            composite,
+        }),
+    }
+}
+
+pub fn to_json(layout: &LayoutFile) -> String {
+    serde_json::to_string_pretty(layout).expect("layout files always serialize to JSON")
+}
+
+pub fn to_toml(layout: &LayoutFile) -> String {
+    toml::to_string(layout).expect("layout files always serialize to TOML")
+}
+
+pub fn from_json(text: &str) -> Result<LayoutFile, String> {
+    serde_json::from_str(text).map_err(|err| err.to_string())
+}
+
+pub fn from_toml(text: &str) -> Result<LayoutFile, String> {
+    toml::from_str(text).map_err(|err| err.to_string())
+}
+
+// Reads a layout file, treating anything without a `.json` extension as TOML.
+// Files with a `version` are canonical layouts and are imported as such.
+pub fn load(path: &Path) -> Result<LayoutFile, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|err| format!("cannot read {}: {}", path.display(), err))?;
+
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => match serde_json::from_str::<serde_json::Value>(&text) {
+            Ok(value) if value.get("version").is_some() => {
+                canonical::from_json(&text).and_then(|layout| canonical::import(&layout))
+            }
+            _ => from_json(&text),
+        },
+        _ => match toml::from_str::<toml::Table>(&text) {
+            Ok(table) if table.contains_key("version") => {
+                canonical::from_toml(&text).and_then(|layout| canonical::import(&layout))
+            }
+            _ => from_toml(&text),
+        },
+    }
+    .map_err(|err| format!("cannot parse {}: {}", path.display(), err))
+}
@@ -0,0 +1,80 @@
+// Fixes the selected text in place on X11 and Wayland desktops: the
+// selection is read with xclip or wl-paste, run through the fixer and typed
+// back over itself with xdotool or wtype. Everything runs locally; the tools
+// just have to be installed.
+use crate::detect;
+use crate::layouts::LayoutCode;
+use std::process::Command;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    // The text highlighted last (X11 PRIMARY)
+    Primary,
+    Clipboard,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Session {
+    X11,
+    Wayland,
+}
+
+fn session() -> Result<Session, String> {
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        Ok(Session::Wayland)
+    } else if std::env::var_os("DISPLAY").is_some() {
+        Ok(Session::X11)
+    } else {
+        Err("no graphical session found (neither WAYLAND_DISPLAY nor DISPLAY is set)".to_string())
+    }
+}
+
+fn run(program: &str, args: &[&str]) -> Result<String, String> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .map_err(|err| format!("cannot run {}: {}", program, err))?;
+    if !output.status.success() {
+        return Err(format!(
+            "{} failed: {}",
+            program,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn read(session: Session, source: Source) -> Result<String, String> {
+    match (session, source) {
+        (Session::X11, Source::Primary) => run("xclip", &["-o", "-selection", "primary"]),
+        (Session::X11, Source::Clipboard) => run("xclip", &["-o", "-selection", "clipboard"]),
+        (Session::Wayland, Source::Primary) => run("wl-paste", &["--primary", "--no-newline"]),
+        (Session::Wayland, Source::Clipboard) => run("wl-paste", &["--no-newline"]),
+    }
+}
+
+fn type_text(session: Session, text: &str) -> Result<(), String> {
+    match session {
+        Session::X11 => run("xdotool", &["type", "--clearmodifiers", "--", text]),
+        Session::Wayland => run("wtype", &["--", text]),
+    }
+    .map(|_| ())
+}
+
+// Fixes the current selection and types the result over it. Returns the
+// replacement, or `None` if the selection was empty or looked correctly
+// typed, in which case nothing is typed.
+pub fn fix_selection(source: Source, layouts: &[LayoutCode]) -> Result<Option<String>, String> {
+    let session = session()?;
+    let selection = read(session, source)?;
+    if selection.is_empty() {
+        return Ok(None);
+    }
+
+    let fixed = detect::fix_text(&selection, layouts);
+    if fixed == selection {
+        return Ok(None);
+    }
+    type_text(session, &fixed)?;
+    Ok(Some(fixed))
+}
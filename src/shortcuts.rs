@@ -0,0 +1,91 @@
+// Keyboard shortcuts between layouts, for documentation written for one
+// layout and read on another. Whether a shortcut moves depends on the
+// application: most bind the character a key types, so Ctrl+T is wherever
+// the layout has its T, while games and some editors bind the key in the
+// position it has on QWERTY.
+use crate::layouts::{self, LayoutCode};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum KeySemantics {
+    // The shortcut is the character, pressed wherever the layout types it.
+    // Characters the layout cannot type, like the Latin letters on Russian,
+    // fall back to the key in the same position, as operating systems do.
+    #[default]
+    Character,
+    // The shortcut is the key in the same place on the keyboard
+    Position,
+}
+
+impl FromStr for KeySemantics {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "character" => Ok(KeySemantics::Character),
+            "position" => Ok(KeySemantics::Position),
+            _ => Err(()),
+        }
+    }
+}
+
+// Modifiers macOS writes as symbols in front of the key, as in ⌘⇧T
+const MAC_MODIFIERS: &str = "⌘⌥⇧⌃";
+
+// The key a user of `to` presses for `key` (named as on `from`), named by
+// what it types on `to`. Letters are matched whatever their case, as
+// shortcuts write them in capitals, and come out in capitals unless they went
+// in in lower case.
+pub fn remap_key(key: char, from: LayoutCode, to: LayoutCode, semantics: KeySemantics) -> char {
+    let lower = key.to_lowercase().next().unwrap_or(key);
+    if semantics == KeySemantics::Character
+        && layouts::typeable_chars(to).any(|c| c == key || c == lower)
+    {
+        return key;
+    }
+    let moved = layouts::convert_text(&lower.to_string(), from, to)
+        .chars()
+        .next()
+        .unwrap_or(lower);
+    if key.is_lowercase() {
+        moved
+    } else {
+        moved.to_uppercase().next().unwrap_or(moved)
+    }
+}
+
+// Remaps one chord like Ctrl+Shift+T, C-x or ⌘P. The key is what follows the
+// last modifier, so Ctrl++ and Ctrl+- bind + and -; named keys such as F5 or
+// Tab are the same on every layout and are left alone.
+fn remap_chord(chord: &str, from: LayoutCode, to: LayoutCode, semantics: KeySemantics) -> String {
+    let key_start = chord
+        .char_indices()
+        .filter(|&(_, c)| c == '+' || c == '-' || MAC_MODIFIERS.contains(c))
+        .map(|(index, c)| index + c.len_utf8())
+        .rfind(|&end| end < chord.len())
+        .unwrap_or(0);
+    let (modifiers, key) = chord.split_at(key_start);
+    let mut chars = key.chars();
+    match (chars.next(), chars.next()) {
+        (Some(key), None) => format!("{}{}", modifiers, remap_key(key, from, to, semantics)),
+        _ => chord.to_string(),
+    }
+}
+
+// Remaps a shortcut documented for `from` to what a user of `to` presses,
+// keeping its notation. Chords pressed one after the other, like
+// "Ctrl+K Ctrl+S", are separated by spaces.
+pub fn remap_shortcut(
+    shortcut: &str,
+    from: LayoutCode,
+    to: LayoutCode,
+    semantics: KeySemantics,
+) -> String {
+    shortcut
+        .split(' ')
+        .map(|chord| remap_chord(chord, from, to, semantics))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
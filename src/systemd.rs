@@ -0,0 +1,69 @@
+// systemd integration without libsystemd: sockets passed by socket
+// activation (the `sd_listen_fds` protocol) and readiness notification
+// (`sd_notify`). Both are no-ops when not started by systemd.
+use std::net::TcpListener;
+use std::os::fd::{FromRawFd, RawFd};
+use std::os::unix::net::{UnixDatagram, UnixListener};
+
+// First file descriptor passed by systemd
+const LISTEN_FDS_START: RawFd = 3;
+
+// A socket handed over by systemd
+pub enum Listener {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+}
+
+// Takes the sockets systemd passed to this process, if any, and clears the
+// environment variables describing them so child processes do not see them
+pub fn listeners() -> Vec<Listener> {
+    let for_us = std::env::var("LISTEN_PID")
+        .ok()
+        .and_then(|pid| pid.parse::<u32>().ok())
+        .is_some_and(|pid| pid == std::process::id());
+    let count = std::env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|count| count.parse::<RawFd>().ok())
+        .unwrap_or(0);
+    std::env::remove_var("LISTEN_PID");
+    std::env::remove_var("LISTEN_FDS");
+    std::env::remove_var("LISTEN_FDNAMES");
+    if !for_us {
+        return Vec::new();
+    }
+
+    (LISTEN_FDS_START..LISTEN_FDS_START + count)
+        .map(|fd| {
+            // systemd owns nothing after handing the descriptors over, and
+            // each one is taken exactly once here
+            let tcp = unsafe { TcpListener::from_raw_fd(fd) };
+            if tcp.local_addr().is_ok() {
+                return Listener::Tcp(tcp);
+            }
+            let fd = std::os::fd::IntoRawFd::into_raw_fd(tcp);
+            Listener::Unix(unsafe { UnixListener::from_raw_fd(fd) })
+        })
+        .collect()
+}
+
+// Sends `state` (e.g. "READY=1") to the service manager if it asked for
+// notifications
+pub fn notify(state: &str) -> std::io::Result<()> {
+    let Some(path) = std::env::var_os("NOTIFY_SOCKET") else {
+        return Ok(());
+    };
+    let socket = UnixDatagram::unbound()?;
+    let path = path.to_string_lossy();
+    match path.strip_prefix('@') {
+        #[cfg(target_os = "linux")]
+        Some(name) => {
+            use std::os::linux::net::SocketAddrExt;
+            let address = std::os::unix::net::SocketAddr::from_abstract_name(name)?;
+            socket.send_to_addr(state.as_bytes(), &address)?;
+        }
+        _ => {
+            socket.send_to(state.as_bytes(), path.as_ref())?;
+        }
+    }
+    Ok(())
+}
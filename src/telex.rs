@@ -0,0 +1,286 @@
+// Vietnamese Telex, an input method typed on the QWERTY keys. Later
+// keystrokes of a word reshape the letters before them: `aa` → â, `ee` → ê,
+// `oo` → ô, `aw` → ă, `ow` → ơ, `uw` → ư, `dd` → đ, and one of s f r x j
+// gives the word's vowel a tone (z takes it off again). Typing a modifier
+// twice undoes it and types the key itself, so `ass` is "as".
+//
+// Converting to Telex composes keystrokes into Vietnamese letters; converting
+// from it spells each letter out again, with the tone key at the end of the
+// word.
+
+// Each vowel without a tone, then with the sắc, huyền, hỏi, ngã and nặng
+// tones, in the order of `TONE_KEYS`
+const VOWELS: [&str; 12] = [
+    "aáàảãạ",
+    "ăắằẳẵặ",
+    "âấầẩẫậ",
+    "eéèẻẽẹ",
+    "êếềểễệ",
+    "iíìỉĩị",
+    "oóòỏõọ",
+    "ôốồổỗộ",
+    "ơớờởỡợ",
+    "uúùủũụ",
+    "ưứừửữự",
+    "yýỳỷỹỵ",
+];
+
+const TONE_KEYS: &str = "sfrxj";
+
+// (letter, reshaped letter, key typed after the letter to reshape it)
+const MODIFIERS: [(char, char, char); 7] = [
+    ('a', 'â', 'a'),
+    ('e', 'ê', 'e'),
+    ('o', 'ô', 'o'),
+    ('a', 'ă', 'w'),
+    ('o', 'ơ', 'w'),
+    ('u', 'ư', 'w'),
+    ('d', 'đ', 'd'),
+];
+
+// Index into `VOWELS` and tone (0 for none) of a lowercase or capital vowel
+fn vowel(c: char) -> Option<(usize, usize)> {
+    let lower = c.to_lowercase().next()?;
+    VOWELS.iter().enumerate().find_map(|(shape, forms)| {
+        let tone = forms.chars().position(|form| form == lower)?;
+        Some((shape, tone))
+    })
+}
+
+fn with_tone(c: char, tone: usize) -> char {
+    let Some((shape, _)) = vowel(c) else {
+        return c;
+    };
+    let toned = VOWELS[shape].chars().nth(tone).unwrap_or(c);
+    with_case(toned, c.is_uppercase())
+}
+
+fn with_case(c: char, upper: bool) -> char {
+    if upper {
+        c.to_uppercase().next().unwrap_or(c)
+    } else {
+        c
+    }
+}
+
+// The letter without its modifier and the key that adds it, lowercase
+fn unmodified(c: char) -> Option<(char, char)> {
+    let lower = c.to_lowercase().next()?;
+    MODIFIERS
+        .iter()
+        .find(|&&(_, modified, _)| modified == lower)
+        .map(|&(plain, _, key)| (plain, key))
+}
+
+// Replaces `letters[index]` with its form after `key`, if it has one
+fn modify(letters: &mut [char], index: usize, key: char) -> bool {
+    let c = letters[index];
+    let lower = c.to_lowercase().next().unwrap_or(c);
+    let modified = MODIFIERS
+        .iter()
+        .find(|&&(plain, _, modifier)| plain == lower && modifier == key);
+    match modified {
+        Some(&(_, modified, _)) => {
+            letters[index] = with_case(modified, c.is_uppercase());
+            true
+        }
+        None => false,
+    }
+}
+
+fn unmodify(letters: &mut [char], index: usize) {
+    let c = letters[index];
+    if let Some((plain, _)) = unmodified(c) {
+        letters[index] = with_case(plain, c.is_uppercase());
+    }
+}
+
+// Letters Telex types that QWERTY does not
+pub fn letters() -> impl Iterator<Item = char> {
+    VOWELS
+        .iter()
+        .flat_map(|forms| forms.chars())
+        .chain(['đ'])
+        .flat_map(|c| [c, with_case(c, true)])
+        .filter(|c| !c.is_ascii())
+}
+
+// Turns Telex keystrokes into Vietnamese text. Only runs of ASCII letters are
+// composed; everything else passes through unchanged.
+pub fn compose(text: &str) -> String {
+    let mut composed = String::with_capacity(text.len());
+    let mut word = String::new();
+    for c in text.chars() {
+        if c.is_ascii_alphabetic() {
+            word.push(c);
+        } else {
+            composed.extend(compose_word(&word));
+            word.clear();
+            composed.push(c);
+        }
+    }
+    composed.extend(compose_word(&word));
+    composed
+}
+
+fn compose_word(word: &str) -> Vec<char> {
+    let mut letters: Vec<char> = Vec::with_capacity(word.len());
+    let mut tone = 0;
+    for c in word.chars() {
+        let key = c.to_ascii_lowercase();
+        let has_vowel = letters.iter().any(|&letter| vowel(letter).is_some());
+        if let Some(index) = TONE_KEYS.find(key).filter(|_| has_vowel) {
+            if tone == index + 1 {
+                tone = 0;
+                letters.push(c);
+            } else {
+                tone = index + 1;
+            }
+        } else if key == 'z' && tone != 0 {
+            tone = 0;
+        } else if !reshape(&mut letters, c) {
+            letters.push(c);
+        }
+    }
+    if tone != 0 {
+        if let Some(index) = tone_position(&letters) {
+            letters[index] = with_tone(letters[index], tone);
+        }
+    }
+    letters
+}
+
+// Applies a modifier key to the letters typed so far, returning false if
+// `c` is to be typed as a letter instead
+fn reshape(letters: &mut Vec<char>, c: char) -> bool {
+    let key = c.to_ascii_lowercase();
+    match key {
+        'd' if letters.len() == 1 => {
+            if unmodified(letters[0]) == Some(('d', 'd')) {
+                unmodify(letters, 0);
+                letters.push(c);
+                true
+            } else {
+                modify(letters, 0, 'd')
+            }
+        }
+        'a' | 'e' | 'o' => {
+            let Some(index) = letters.iter().rposition(|&l| vowel(l).is_some()) else {
+                return false;
+            };
+            match unmodified(letters[index]) {
+                Some((plain, modifier)) if plain == key && modifier == key => {
+                    unmodify(letters, index);
+                    letters.push(c);
+                    true
+                }
+                _ => modify(letters, index, key),
+            }
+        }
+        'w' => {
+            let vowels = letters.iter().rposition(|&l| vowel(l).is_some());
+            let Some(last) = vowels else {
+                // A lone w types ư
+                letters.push(with_case('ư', c.is_uppercase()));
+                return true;
+            };
+            let target = (0..=last).rev().find(|&index| {
+                let lower = letters[index]
+                    .to_lowercase()
+                    .next()
+                    .unwrap_or(letters[index]);
+                matches!(lower, 'a' | 'o' | 'u' | 'ă' | 'ơ' | 'ư')
+            });
+            let Some(index) = target else {
+                return false;
+            };
+            // In uo both letters take the horn
+            let pair = index > 0
+                && letters[index].to_lowercase().eq(['o'])
+                && letters[index - 1].to_lowercase().eq(['u']);
+            if unmodified(letters[index]).is_some_and(|(_, modifier)| modifier == 'w') {
+                unmodify(letters, index);
+                if index > 0 && letters[index - 1].to_lowercase().eq(['ư']) {
+                    unmodify(letters, index - 1);
+                }
+                letters.push(c);
+            } else {
+                modify(letters, index, 'w');
+                if pair {
+                    modify(letters, index - 1, 'w');
+                }
+            }
+            true
+        }
+        _ => false,
+    }
+}
+
+// Which letter of a syllable carries its tone mark: a vowel with a modifier
+// if there is one, else the last vowel before a final consonant, the middle
+// of three vowels or the first of two. The u of qu and the i of gi before
+// another vowel belong to the consonant.
+fn tone_position(letters: &[char]) -> Option<usize> {
+    let lower: Vec<char> = letters
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    let is_vowel = |index: usize| {
+        let glide = index > 0
+            && matches!((lower[index - 1], lower[index]), ('q', 'u') | ('g', 'i'))
+            && lower
+                .get(index + 1)
+                .is_some_and(|&next| vowel(next).is_some());
+        vowel(lower[index]).is_some() && !glide
+    };
+    let start = (0..lower.len()).find(|&index| is_vowel(index))?;
+    let end = (start..lower.len())
+        .find(|&index| !is_vowel(index))
+        .unwrap_or(lower.len());
+
+    let modified = (start..end)
+        .rev()
+        .find(|&index| unmodified(lower[index]).is_some());
+    Some(match modified {
+        Some(index) => index,
+        None if end < lower.len() => end - 1,
+        None if end - start >= 3 => start + 1,
+        None => start,
+    })
+}
+
+// Spells Vietnamese text out as the Telex keystrokes that type it
+pub fn decompose(text: &str) -> String {
+    let mut decomposed = String::with_capacity(text.len());
+    let mut tone_key = None;
+    // The tone key goes after the word, in the case of its last letter
+    let push_tone = |decomposed: &mut String, tone_key: &mut Option<char>| {
+        if let Some(key) = tone_key.take() {
+            let upper = decomposed.chars().last().is_some_and(char::is_uppercase);
+            decomposed.push(with_case(key, upper));
+        }
+    };
+    for c in text.chars() {
+        if !c.is_alphabetic() {
+            push_tone(&mut decomposed, &mut tone_key);
+            decomposed.push(c);
+            continue;
+        }
+        let (plain, tone) = match vowel(c) {
+            Some((_, tone)) if tone > 0 => (with_tone(c, 0), tone),
+            _ => (c, 0),
+        };
+        if tone > 0 {
+            tone_key = TONE_KEYS.chars().nth(tone - 1);
+        }
+        match unmodified(plain) {
+            Some((base, key)) => {
+                decomposed.push(with_case(base, plain.is_uppercase()));
+                decomposed.push(with_case(key, plain.is_uppercase()));
+            }
+            None => decomposed.push(plain),
+        }
+    }
+    push_tone(&mut decomposed, &mut tone_key);
+    decomposed
+}
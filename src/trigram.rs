@@ -0,0 +1,74 @@
+// Character trigram models of English and Russian, built from the texts in
+// src/corpora and the dictionaries by build.rs. A word scores by how likely
+// each of its letters is after the two before it, which tells names, slang
+// and word forms the dictionaries do not list from keyboard mash.
+use crate::detect::Language;
+
+mod smoothing;
+
+pub struct TrigramModel {
+    // Letters in the training text, counting the space padding each word
+    total: u32,
+    // Average log probability of the letters of the training words
+    typical: f64,
+    // Counts of each letter, pair and triple of letters, sorted
+    letters: &'static [(&'static str, u32)],
+    pairs: &'static [(&'static str, u32)],
+    triples: &'static [(&'static str, u32)],
+}
+
+include!(concat!(env!("OUT_DIR"), "/trigrams.rs"));
+
+impl TrigramModel {
+    fn count(&self, ngram: &[char]) -> u32 {
+        let table = match ngram.len() {
+            1 => self.letters,
+            2 => self.pairs,
+            _ => self.triples,
+        };
+        let key: String = ngram.iter().collect();
+        table
+            .binary_search_by(|(entry, _)| (*entry).cmp(key.as_str()))
+            .map_or(0, |index| table[index].1)
+    }
+
+    // Probability of the last letter of `trigram` after the first two, a
+    // space standing for the start or end of a word
+    pub(crate) fn probability(&self, trigram: &[char]) -> f64 {
+        smoothing::probability(
+            |ngram| self.count(ngram),
+            self.total,
+            self.letters.len(),
+            trigram,
+        )
+    }
+
+    // How often each letter occurs in the training text
+    pub(crate) fn letter_counts(&self) -> impl Iterator<Item = (char, u32)> + '_ {
+        self.letters
+            .iter()
+            .filter_map(|(letter, count)| Some((letter.chars().next()?, *count)))
+            .filter(|&(letter, _)| letter != ' ')
+    }
+
+    // From 0 for a word no more likely than random letters to 1 for one as
+    // likely as a typical word of the language
+    pub fn score(&self, word: &str) -> f64 {
+        let padded = smoothing::pad(word);
+        let trigrams = padded.windows(3);
+        let len = trigrams.len();
+        let average = trigrams
+            .map(|trigram| self.probability(trigram).ln())
+            .sum::<f64>()
+            / len as f64;
+        let random = (1.0 / self.letters.len() as f64).ln();
+        ((average - random) / (self.typical - random)).clamp(0.0, 1.0)
+    }
+}
+
+pub fn model(language: Language) -> &'static TrigramModel {
+    match language {
+        Language::English => &ENGLISH,
+        Language::Russian => &RUSSIAN,
+    }
+}
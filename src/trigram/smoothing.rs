@@ -0,0 +1,35 @@
+// The smoothed trigram estimate, shared by src/trigram.rs and by build.rs,
+// which scores the training words with it to find what a typical word
+// scores. Both have to compute it the same way for that score to mean
+// anything at runtime.
+
+// Weights of the trigram, bigram and single letter estimates. Mixing in the
+// shorter contexts keeps the few trigrams a small corpus never saw from
+// ruling out a word.
+pub const WEIGHTS: [f64; 3] = [0.6, 0.3, 0.1];
+
+// Probability of the last letter of `trigram` after the first two, given
+// the `count` of each letter, pair and triple, the `total` number of letters
+// and the size of the `alphabet`. A space stands for the start or end of a
+// word.
+pub fn probability(
+    count: impl Fn(&[char]) -> u32,
+    total: u32,
+    alphabet: usize,
+    trigram: &[char],
+) -> f64 {
+    let ratio = |ngram: &[char]| match count(&ngram[..ngram.len() - 1]) {
+        0 => 0.0,
+        context => count(ngram) as f64 / context as f64,
+    };
+    let letter = (count(&trigram[2..]) + 1) as f64 / (total as usize + alphabet) as f64;
+    WEIGHTS[0] * ratio(trigram) + WEIGHTS[1] * ratio(&trigram[1..]) + WEIGHTS[2] * letter
+}
+
+// `word` in lower case, padded with a space on both sides
+pub fn pad(word: &str) -> Vec<char> {
+    let mut padded = vec![' '];
+    padded.extend(word.to_lowercase().chars());
+    padded.push(' ');
+    padded
+}
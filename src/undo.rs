@@ -0,0 +1,91 @@
+// Undo tokens, which let a later call revert a conversion exactly. A token
+// records the layouts and, for each word the conversion changed, where it is
+// in the converted text. Converting a word back with the layouts swapped
+// restores it unless the pair is lossy there (a capital on a caseless
+// layout, a folded ё, a key both layouts lack), so only those words carry
+// their original spelling.
+use crate::edits;
+use crate::registry::{self, ConvertOptions};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+// Bumped when the encoding changes, so old tokens are rejected rather than
+// misread
+const VERSION: &str = "1";
+
+#[derive(Serialize, Deserialize)]
+struct Token {
+    from: String,
+    to: String,
+    // Characters in the converted text, to tell when it has been edited
+    len: usize,
+    // Character offsets into the converted text, with the original word when
+    // converting it back does not restore it
+    spans: Vec<(usize, usize, Option<String>)>,
+}
+
+// Converts `text` back from `to` to `from` with no options
+fn convert_back(text: &str, from: &str, to: &str) -> Result<String, String> {
+    let from_layout = registry::resolve(from).ok_or(format!("unknown layout '{}'", from))?;
+    let to_layout = registry::resolve(to).ok_or(format!("unknown layout '{}'", to))?;
+    Ok(registry::convert_text_with(
+        text,
+        &to_layout,
+        &from_layout,
+        &ConvertOptions::default(),
+    ))
+}
+
+// A token reverting `converted`, which is `original` converted from layout
+// `from` to `to` (as `registry::resolve` names them)
+pub fn token(original: &str, converted: &str, from: &str, to: &str) -> Result<String, String> {
+    let mut spans = Vec::new();
+    for edit in edits::word_edits(converted, original) {
+        let restored = convert_back(&edit.original, from, to)?;
+        let original = (restored != edit.replacement).then_some(edit.replacement);
+        spans.push((edit.start, edit.end, original));
+    }
+    let token = Token {
+        from: from.to_string(),
+        to: to.to_string(),
+        len: converted.chars().count(),
+        spans,
+    };
+    let json = serde_json::to_vec(&token).map_err(|err| err.to_string())?;
+    Ok(format!("{}.{}", VERSION, URL_SAFE_NO_PAD.encode(json)))
+}
+
+// Reverts the conversion `token` was made for in `text`, which must be the
+// converted text unchanged
+pub fn undo(text: &str, token: &str) -> Result<String, String> {
+    let invalid = || "invalid undo token".to_string();
+    let encoded = token
+        .strip_prefix(VERSION)
+        .and_then(|rest| rest.strip_prefix('.'))
+        .ok_or_else(invalid)?;
+    let json = URL_SAFE_NO_PAD.decode(encoded).map_err(|_| invalid())?;
+    let token: Token = serde_json::from_slice(&json).map_err(|_| invalid())?;
+
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() != token.len {
+        return Err("the text has changed since it was converted".to_string());
+    }
+
+    let mut reverted = String::with_capacity(text.len());
+    let mut position = 0;
+    for (start, end, original) in token.spans {
+        if start < position || end < start || end > chars.len() {
+            return Err(invalid());
+        }
+        reverted.extend(&chars[position..start]);
+        let word: String = chars[start..end].iter().collect();
+        match original {
+            Some(original) => reverted.push_str(&original),
+            None => reverted.push_str(&convert_back(&word, &token.from, &token.to)?),
+        }
+        position = end;
+    }
+    reverted.extend(&chars[position..]);
+    Ok(reverted)
+}
@@ -0,0 +1,158 @@
+use crate::layouts::{self, LayoutCode};
+use crate::schema::LayoutFile;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::str::FromStr;
+
+#[derive(Serialize, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Serialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    UnknownBase,
+    InvalidEntry,
+    Conflict,
+    MissingShiftPair,
+    LossyRoundTrip,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct Issue {
+    pub severity: Severity,
+    pub kind: IssueKind,
+    pub message: String,
+}
+
+impl Issue {
+    fn error(kind: IssueKind, message: String) -> Self {
+        Issue {
+            severity: Severity::Error,
+            kind,
+            message,
+        }
+    }
+
+    fn warning(kind: IssueKind, message: String) -> Self {
+        Issue {
+            severity: Severity::Warning,
+            kind,
+            message,
+        }
+    }
+}
+
+fn single_char(s: &str) -> Option<char> {
+    let mut chars = s.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Some(c),
+        _ => None,
+    }
+}
+
+// Checks a custom layout for problems that would make conversions through it
+// ambiguous or surprising. Issues are returned in a stable order.
+pub fn validate(layout: &LayoutFile) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    let Ok(base) = LayoutCode::from_str(&layout.base) else {
+        issues.push(Issue::error(
+            IssueKind::UnknownBase,
+            format!("unknown base layout '{}'", layout.base),
+        ));
+        return issues;
+    };
+
+    let (unshifted, shifted): (Vec<char>, Vec<char>) = layouts::shift_pairs()
+        .map(|(lower, upper)| (layouts::char_at(base, lower), layouts::char_at(base, upper)))
+        .unzip();
+    let universe: Vec<char> = unshifted.iter().chain(shifted.iter()).copied().collect();
+    let on_base: HashSet<char> = universe.iter().copied().collect();
+
+    let mut map = BTreeMap::new();
+    for (key, value) in &layout.keys {
+        match (single_char(key), single_char(value)) {
+            (Some(k), Some(_)) if !on_base.contains(&k) => issues.push(Issue::error(
+                IssueKind::InvalidEntry,
+                format!("'{}' is not produced by any key on {}", k, layout.base),
+            )),
+            (Some(k), Some(v)) => {
+                map.insert(k, v);
+            }
+            _ => issues.push(Issue::error(
+                IssueKind::InvalidEntry,
+                format!(
+                    "'{}' = '{}' must map a single character to a single character",
+                    key, value
+                ),
+            )),
+        }
+    }
+
+    let output = |c: char| map.get(&c).copied().unwrap_or(c);
+
+    let mut producers: BTreeMap<char, Vec<char>> = BTreeMap::new();
+    for &c in &universe {
+        producers.entry(output(c)).or_default().push(c);
+    }
+    let mut conflicting = HashSet::new();
+    for (out, keys) in &producers {
+        if keys.len() > 1 {
+            conflicting.insert(*out);
+            let keys: Vec<String> = keys.iter().map(|k| format!("'{}'", k)).collect();
+            issues.push(Issue::error(
+                IssueKind::Conflict,
+                format!("'{}' is produced by {}", out, keys.join(", ")),
+            ));
+        }
+    }
+
+    for (&lower, &upper) in unshifted.iter().zip(shifted.iter()) {
+        match (map.get(&lower), map.get(&upper)) {
+            (Some(_), None) => issues.push(Issue::warning(
+                IssueKind::MissingShiftPair,
+                format!("'{}' is remapped but its shifted '{}' is not", lower, upper),
+            )),
+            (None, Some(_)) => issues.push(Issue::warning(
+                IssueKind::MissingShiftPair,
+                format!(
+                    "'{}' is remapped but its unshifted '{}' is not",
+                    upper, lower
+                ),
+            )),
+            (Some(&lo), Some(&hi))
+                if lo.is_alphabetic() && hi.is_alphabetic() && !layouts::is_case_pair(lo, hi) =>
+            {
+                issues.push(Issue::warning(
+                    IssueKind::MissingShiftPair,
+                    format!(
+                        "'{}' produces '{}' but its shifted '{}' produces '{}'",
+                        lower, lo, upper, hi
+                    ),
+                ))
+            }
+            _ => {}
+        }
+    }
+
+    let mut inverse = HashMap::new();
+    for (&k, &v) in &map {
+        inverse.entry(v).or_insert(k);
+    }
+    for &c in &universe {
+        let out = output(c);
+        let back = inverse.get(&out).copied().unwrap_or(out);
+        if back != c && !conflicting.contains(&out) {
+            issues.push(Issue::warning(
+                IssueKind::LossyRoundTrip,
+                format!("'{}' converts to '{}' but back to '{}'", c, out, back),
+            ));
+        }
+    }
+
+    issues
+}
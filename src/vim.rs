@@ -0,0 +1,228 @@
+// Rewrites the mappings of a vimrc for a new layout so that every mapping is
+// still pressed with the same keys as before: the left-hand side of each
+// `map`, `noremap` and `unmap` command, and the leader keys, are converted
+// to what those keys type on the new layout. Right-hand sides are what vim
+// runs and are left alone, as are comments and every other line.
+use crate::layouts::{self, LayoutCode};
+
+// Map commands and the shortest abbreviation vim accepts for each
+const COMMANDS: [(&str, usize); 30] = [
+    ("map", 3),
+    ("nmap", 2),
+    ("vmap", 2),
+    ("xmap", 2),
+    ("smap", 4),
+    ("omap", 2),
+    ("imap", 2),
+    ("lmap", 2),
+    ("cmap", 2),
+    ("tmap", 3),
+    ("noremap", 2),
+    ("nnoremap", 2),
+    ("vnoremap", 2),
+    ("xnoremap", 2),
+    ("snoremap", 4),
+    ("onoremap", 3),
+    ("inoremap", 3),
+    ("lnoremap", 2),
+    ("cnoremap", 3),
+    ("tnoremap", 3),
+    ("unmap", 3),
+    ("nunmap", 3),
+    ("vunmap", 2),
+    ("xunmap", 2),
+    ("sunmap", 4),
+    ("ounmap", 2),
+    ("iunmap", 2),
+    ("lunmap", 2),
+    ("cunmap", 2),
+    ("tunmap", 5),
+];
+
+// Arguments that may come between the command and the left-hand side
+const ATTRIBUTES: [&str; 7] = [
+    "<buffer>",
+    "<nowait>",
+    "<silent>",
+    "<special>",
+    "<script>",
+    "<expr>",
+    "<unique>",
+];
+
+// Keys written by name because typing them would end or confuse the
+// mapping, and the backslash, which may be written either way
+const NAMED_CHARS: [(&str, char); 4] =
+    [("lt", '<'), ("Bar", '|'), ("Space", ' '), ("Bslash", '\\')];
+
+fn is_map_command(word: &str) -> bool {
+    let word = word.strip_suffix('!').unwrap_or(word);
+    COMMANDS
+        .iter()
+        .any(|&(full, shortest)| word.len() >= shortest && full.starts_with(word))
+}
+
+// What the key typing `c` on `from` types on `to`
+fn remap_char(c: char, from: LayoutCode, to: LayoutCode) -> char {
+    layouts::convert_text(c.encode_utf8(&mut [0; 4]), from, to)
+        .chars()
+        .next()
+        .unwrap_or(c)
+}
+
+// `c` as written in a left-hand side
+fn push_key(lhs: &mut String, c: char) {
+    match NAMED_CHARS.iter().find(|&&(_, named)| named == c) {
+        Some(&(name, named)) if named != '\\' => {
+            lhs.push('<');
+            lhs.push_str(name);
+            lhs.push('>');
+        }
+        _ => lhs.push(c),
+    }
+}
+
+// Remaps the key inside `<...>` notation: the character after modifiers
+// like `C-` or `M-`, or a character written by name. Other named keys such
+// as `<CR>`, `<F5>` or `<leader>` are the same on every layout.
+fn remap_special(name: &str, from: LayoutCode, to: LayoutCode) -> String {
+    if let Some(&(_, c)) = NAMED_CHARS
+        .iter()
+        .find(|(named, _)| named.eq_ignore_ascii_case(name))
+    {
+        let mut remapped = String::new();
+        push_key(&mut remapped, remap_char(c, from, to));
+        return remapped;
+    }
+    let bytes = name.as_bytes();
+    let mut modifiers = 0;
+    while name.len() > modifiers + 2
+        && bytes[modifiers].is_ascii_alphabetic()
+        && bytes[modifiers + 1] == b'-'
+    {
+        modifiers += 2;
+    }
+    let mut key = name[modifiers..].chars();
+    let (Some(c), None) = (key.next(), key.next()) else {
+        return format!("<{}>", name);
+    };
+    if modifiers == 0 {
+        return format!("<{}>", name);
+    }
+    // Ctrl ignores Shift on letters, so <C-W> is typed like <C-w>
+    let c = if name[..modifiers].to_ascii_uppercase().contains("C-") {
+        c.to_ascii_lowercase()
+    } else {
+        c
+    };
+    let remapped = remap_char(c, from, to);
+    match NAMED_CHARS.iter().find(|&&(_, named)| named == remapped) {
+        Some(&(named, _)) => format!("<{}{}>", &name[..modifiers], named),
+        None => format!("<{}{}>", &name[..modifiers], remapped),
+    }
+}
+
+fn remap_lhs(lhs: &str, from: LayoutCode, to: LayoutCode) -> String {
+    let mut remapped = String::with_capacity(lhs.len());
+    let mut rest = lhs;
+    while let Some(c) = rest.chars().next() {
+        let special = rest
+            .strip_prefix('<')
+            .and_then(|inner| inner.split_once('>'))
+            .filter(|(name, _)| !name.is_empty() && !name.contains('<'));
+        if let Some((name, after)) = special {
+            remapped.push_str(&remap_special(name, from, to));
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("\\|") {
+            // An escaped bar, which would otherwise end the command
+            remapped.push_str("\\|");
+            rest = after;
+        } else {
+            push_key(&mut remapped, remap_char(c, from, to));
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    remapped
+}
+
+// Splits off the first word of `text`, with the whitespace after it
+fn split_word(text: &str) -> (&str, &str, &str) {
+    let end = text.find(char::is_whitespace).unwrap_or(text.len());
+    let (word, rest) = text.split_at(end);
+    let gap = rest.len() - rest.trim_start().len();
+    (word, &rest[..gap], &rest[gap..])
+}
+
+// The single character of `let mapleader = ","` and the like, remapped
+fn remap_leader(line: &str, from: LayoutCode, to: LayoutCode) -> Option<String> {
+    let body = line.trim_start().trim_start_matches(':');
+    let rest = body.strip_prefix("let")?.trim_start();
+    let (name, value) = rest.split_once('=')?;
+    if !matches!(
+        name.trim_end(),
+        "mapleader" | "maplocalleader" | "g:mapleader" | "g:maplocalleader"
+    ) {
+        return None;
+    }
+    let value = value.trim_start();
+    let quote = value.chars().next().filter(|&q| q == '"' || q == '\'')?;
+    let mut chars = value[1..].chars();
+    let (Some(c), Some(end)) = (chars.next(), chars.next()) else {
+        return None;
+    };
+    let remapped = remap_char(c, from, to);
+    // Characters that would have to be escaped in the string are left alone
+    let escaped = |c: char| c == quote || (quote == '"' && c == '\\');
+    if end != quote || escaped(c) || escaped(remapped) {
+        return None;
+    }
+    let start = line.len() - value.len() + 1;
+    Some(format!(
+        "{}{}{}",
+        &line[..start],
+        remapped,
+        &line[start + c.len_utf8()..]
+    ))
+}
+
+fn remap_line(line: &str, from: LayoutCode, to: LayoutCode) -> String {
+    if let Some(remapped) = remap_leader(line, from, to) {
+        return remapped;
+    }
+    let indent = line.len() - line.trim_start().len();
+    let body = &line[indent..];
+    let colons = body.len() - body.trim_start_matches(':').len();
+    let (command, gap, mut rest) = split_word(&body[colons..]);
+    if !is_map_command(command) || rest.is_empty() {
+        return line.to_string();
+    }
+
+    let mut remapped = format!("{}{}{}", &line[..indent + colons], command, gap);
+    // Attributes may also be written without spaces, as <buffer><silent>
+    while let Some(attr) = ATTRIBUTES.iter().find(|attr| {
+        rest.get(..attr.len())
+            .is_some_and(|start| start.eq_ignore_ascii_case(attr))
+    }) {
+        let after = &rest[attr.len()..];
+        let gap = after.len() - after.trim_start().len();
+        remapped.push_str(&rest[..attr.len() + gap]);
+        rest = &after[gap..];
+    }
+    let (lhs, gap, rhs) = split_word(rest);
+    remapped.push_str(&remap_lhs(lhs, from, to));
+    remapped.push_str(gap);
+    remapped.push_str(rhs);
+    remapped
+}
+
+// Rewrites the mappings in `vimrc`, written for `from`, to be pressed with
+// the same keys on `to`
+pub fn remap_vimrc(vimrc: &str, from: LayoutCode, to: LayoutCode) -> String {
+    vimrc
+        .split_inclusive('\n')
+        .map(|line| {
+            let content = line.trim_end_matches(['\n', '\r']);
+            remap_line(content, from, to) + &line[content.len()..]
+        })
+        .collect()
+}
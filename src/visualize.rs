@@ -0,0 +1,291 @@
+use crate::analyze;
+use crate::detect::Language;
+use crate::geometry::{self, Finger};
+use crate::layouts::{self, LayoutCode};
+use crate::trigram;
+#[cfg(feature = "png")]
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+// Draws the unshifted layer of `layout` as a staggered ASCII keyboard.
+// Keys for which `marked` returns true (given the key's QWERTY character)
+// are followed by an asterisk.
+pub fn ascii_keyboard(layout: LayoutCode, marked: impl Fn(char) -> bool) -> String {
+    const INDENT: [usize; 4] = [0, 4, 5, 7];
+
+    layouts::QWERTY_ROWS
+        .iter()
+        .zip(INDENT)
+        .map(|((unshifted, _), indent)| {
+            let keys: String = unshifted
+                .chars()
+                .map(|key| {
+                    let c = layouts::char_at(layout, key);
+                    if marked(key) {
+                        format!(" {}*", c)
+                    } else {
+                        format!(" {} ", c)
+                    }
+                })
+                .collect();
+            format!("{}{}", " ".repeat(indent), keys.trim_end())
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageFormat {
+    #[default]
+    Svg,
+    Png,
+}
+
+impl FromStr for ImageFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "svg" => Ok(ImageFormat::Svg),
+            "png" => Ok(ImageFormat::Png),
+            _ => Err(()),
+        }
+    }
+}
+
+// How a heatmap maps key presses to colours, between none and those of the
+// most pressed key
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HeatScale {
+    #[default]
+    Linear,
+    // By the logarithm of the presses, which tells rarely pressed keys apart
+    // instead of colouring them all pale
+    Log,
+}
+
+impl FromStr for HeatScale {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "linear" => Ok(HeatScale::Linear),
+            "log" => Ok(HeatScale::Log),
+            _ => Err(()),
+        }
+    }
+}
+
+// What the keys of a keyboard image are coloured by
+#[derive(Debug, Clone, PartialEq)]
+pub enum Overlay {
+    None,
+    // How often each key is pressed, by its unshifted QWERTY character
+    Heatmap(HashMap<char, u32>, HeatScale),
+    // Keys typing something else than on the given layout, which is shown
+    // in the corner of the key
+    Diff(LayoutCode),
+    // The finger pressing each key
+    Fingers,
+}
+
+// Presses of each key, by its unshifted QWERTY character, to type `text` on
+// `layout`
+pub fn key_presses(text: &str, layout: LayoutCode) -> HashMap<char, u32> {
+    let keys = analyze::typing_keys(layout);
+    let mut presses = HashMap::new();
+    for c in text.chars() {
+        if let Some(&key) = keys.get(&c) {
+            *presses.entry(unshifted(key)).or_default() += 1;
+        }
+    }
+    presses
+}
+
+// Presses of each key to type the letters of `language` on `layout`, in the
+// proportions of the trigram model's training text
+pub fn letter_presses(language: Language, layout: LayoutCode) -> HashMap<char, u32> {
+    let keys = analyze::typing_keys(layout);
+    let mut presses = HashMap::new();
+    for (letter, count) in trigram::model(language).letter_counts() {
+        if let Some(&key) = keys.get(&letter) {
+            *presses.entry(unshifted(key)).or_default() += count;
+        }
+    }
+    presses
+}
+
+fn unshifted(key: char) -> char {
+    layouts::shift_pairs()
+        .find(|&(_, upper)| upper == key)
+        .map_or(key, |(lower, _)| lower)
+}
+
+// Size of a key and the space around the keyboard, in pixels
+const KEY_SIZE: f64 = 60.0;
+const KEY_GAP: f64 = 4.0;
+const MARGIN: f64 = 10.0;
+// Width of the keyboard in key widths: the top row ends a key and a half
+// past the number row
+const KEYBOARD_WIDTH: f64 = 14.5;
+
+const FINGER_COLOURS: [(Finger, &str); 8] = [
+    (Finger::LeftPinky, "#f4a6a6"),
+    (Finger::LeftRing, "#f6c99a"),
+    (Finger::LeftMiddle, "#f3e79b"),
+    (Finger::LeftIndex, "#b9e3a4"),
+    (Finger::RightIndex, "#9fd8cf"),
+    (Finger::RightMiddle, "#a8c6ef"),
+    (Finger::RightRing, "#c5b3ea"),
+    (Finger::RightPinky, "#e9b0d8"),
+];
+const KEY_COLOUR: &str = "#f2f2f2";
+const DIFF_COLOUR: &str = "#ffc46b";
+
+fn escape(c: char) -> String {
+    match c {
+        '&' => "&amp;".to_string(),
+        '<' => "&lt;".to_string(),
+        '>' => "&gt;".to_string(),
+        '"' => "&quot;".to_string(),
+        '\'' => "&apos;".to_string(),
+        c => c.to_string(),
+    }
+}
+
+// White for no presses to dark red for the most pressed key
+fn heat(presses: u32, most: u32, scale: HeatScale) -> String {
+    let t = match scale {
+        _ if most == 0 => 0.0,
+        HeatScale::Linear => presses as f64 / most as f64,
+        HeatScale::Log => (presses as f64).ln_1p() / (most as f64).ln_1p(),
+    };
+    let channel = |from: f64, to: f64| (from + (to - from) * t).round() as u8;
+    format!(
+        "#{:02x}{:02x}{:02x}",
+        channel(255.0, 215.0),
+        channel(255.0, 48.0),
+        channel(255.0, 31.0)
+    )
+}
+
+// Draws both shift levels of `layout` on an ANSI keyboard as SVG, coloured by
+// `overlay`. Shifted characters are shown in the top left corner unless they
+// are the capital of the unshifted one.
+pub fn svg_keyboard(layout: LayoutCode, overlay: &Overlay) -> String {
+    let width = KEYBOARD_WIDTH * KEY_SIZE + 2.0 * MARGIN;
+    let height = layouts::QWERTY_ROWS.len() as f64 * KEY_SIZE + 2.0 * MARGIN;
+    let most = match overlay {
+        Overlay::Heatmap(presses, _) => presses.values().copied().max().unwrap_or(0),
+        _ => 0,
+    };
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+         viewBox=\"0 0 {w} {h}\" font-family=\"DejaVu Sans, sans-serif\">\n\
+         <rect width=\"{w}\" height=\"{h}\" fill=\"#ffffff\"/>\n",
+        w = width,
+        h = height
+    );
+    for (unshifted, shifted) in layouts::QWERTY_ROWS {
+        for (key, shifted_key) in unshifted.chars().zip(shifted.chars()) {
+            let Some(pos) = geometry::position(key) else {
+                continue;
+            };
+            let lower = layouts::char_at(layout, key);
+            let upper = layouts::char_at(layout, shifted_key);
+            let fill = match overlay {
+                Overlay::None => KEY_COLOUR.to_string(),
+                Overlay::Heatmap(presses, scale) => {
+                    heat(presses.get(&key).copied().unwrap_or(0), most, *scale)
+                }
+                Overlay::Diff(from) => {
+                    if layouts::char_at(*from, key) != lower
+                        || layouts::char_at(*from, shifted_key) != upper
+                    {
+                        DIFF_COLOUR.to_string()
+                    } else {
+                        KEY_COLOUR.to_string()
+                    }
+                }
+                Overlay::Fingers => FINGER_COLOURS
+                    .iter()
+                    .find(|(finger, _)| *finger == pos.finger)
+                    .map_or(KEY_COLOUR, |(_, colour)| colour)
+                    .to_string(),
+            };
+            let x = MARGIN + pos.x * KEY_SIZE + KEY_GAP / 2.0;
+            let y = MARGIN + pos.y * KEY_SIZE + KEY_GAP / 2.0;
+            let size = KEY_SIZE - KEY_GAP;
+            svg += &format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" rx=\"6\" fill=\"{}\" stroke=\"#999999\"/>\n",
+                x, y, size, size, fill
+            );
+            svg += &format!(
+                "<text x=\"{}\" y=\"{}\" font-size=\"24\" text-anchor=\"middle\">{}</text>\n",
+                x + size / 2.0,
+                y + size * 0.72,
+                escape(lower)
+            );
+            if !layouts::is_case_pair(lower, upper) && upper != lower {
+                svg += &format!(
+                    "<text x=\"{}\" y=\"{}\" font-size=\"14\" fill=\"#555555\">{}</text>\n",
+                    x + 5.0,
+                    y + 16.0,
+                    escape(upper)
+                );
+            }
+            if let Overlay::Diff(from) = overlay {
+                let before = layouts::char_at(*from, key);
+                if before != lower {
+                    svg += &format!(
+                        "<text x=\"{}\" y=\"{}\" font-size=\"12\" fill=\"#8a5a00\" text-anchor=\"end\">{}</text>\n",
+                        x + size - 5.0,
+                        y + size - 5.0,
+                        escape(before)
+                    );
+                }
+            }
+        }
+    }
+    svg += "</svg>\n";
+    svg
+}
+
+#[cfg(feature = "png")]
+lazy_static! {
+    static ref FONTS: std::sync::Arc<resvg::usvg::fontdb::Database> = {
+        let mut fonts = resvg::usvg::fontdb::Database::new();
+        fonts.load_system_fonts();
+        std::sync::Arc::new(fonts)
+    };
+}
+
+// Renders an SVG image as PNG, `scale` times its size, with the system fonts
+#[cfg(feature = "png")]
+pub fn png(svg: &str, scale: f32) -> Result<Vec<u8>, String> {
+    use resvg::{tiny_skia, usvg};
+
+    let options = usvg::Options {
+        fontdb: FONTS.clone(),
+        ..Default::default()
+    };
+    let tree = usvg::Tree::from_str(svg, &options).map_err(|err| err.to_string())?;
+    let size = tree
+        .size()
+        .to_int_size()
+        .scale_by(scale)
+        .ok_or("invalid scale")?;
+    let mut pixmap =
+        tiny_skia::Pixmap::new(size.width(), size.height()).ok_or("image too large")?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+    pixmap.encode_png().map_err(|err| err.to_string())
+}
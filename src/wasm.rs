@@ -0,0 +1,54 @@
+use crate::detect;
+use crate::layouts::{self, LayoutCode};
+use std::str::FromStr;
+use wasm_bindgen::prelude::*;
+
+fn parse_layout(layout: &str) -> Result<LayoutCode, JsError> {
+    LayoutCode::from_str(layout).map_err(|_| JsError::new(&format!("unknown layout '{}'", layout)))
+}
+
+fn parse_layouts(layouts: Option<Vec<String>>) -> Result<Vec<LayoutCode>, JsError> {
+    match layouts {
+        Some(layouts) => layouts.iter().map(|layout| parse_layout(layout)).collect(),
+        None => Ok(detect::DEFAULT_LAYOUTS.to_vec()),
+    }
+}
+
+#[wasm_bindgen]
+pub fn convert(text: &str, from: &str, to: &str) -> Result<String, JsError> {
+    let (from, to) = (parse_layout(from)?, parse_layout(to)?);
+    Ok(layouts::convert_text(text, from, to))
+}
+
+#[wasm_bindgen(getter_with_clone)]
+pub struct Detection {
+    pub from: String,
+    pub to: String,
+    pub confidence: f64,
+}
+
+// Returns `undefined` if the text looks correctly typed. `layouts` defaults
+// to `detect::DEFAULT_LAYOUTS`, QWERTY and Russian.
+#[wasm_bindgen]
+pub fn detect(text: &str, layouts: Option<Vec<String>>) -> Result<Option<Detection>, JsError> {
+    let layouts = parse_layouts(layouts)?;
+    Ok(detect::detect(text, &layouts).map(|detection| Detection {
+        from: detection.from.as_str().to_string(),
+        to: detection.to.as_str().to_string(),
+        confidence: detection.confidence,
+    }))
+}
+
+#[wasm_bindgen]
+pub fn fix(text: &str, layouts: Option<Vec<String>>) -> Result<String, JsError> {
+    let layouts = parse_layouts(layouts)?;
+    Ok(detect::fix_text(text, &layouts))
+}
+
+#[wasm_bindgen(js_name = listLayouts)]
+pub fn list_layouts() -> Vec<String> {
+    LayoutCode::ALL
+        .iter()
+        .map(|layout| layout.as_str().to_string())
+        .collect()
+}
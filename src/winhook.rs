@@ -0,0 +1,349 @@
+// Windows mode that watches typing through a low-level keyboard hook and, on
+// a hotkey, retypes the last word converted to the layout it was meant for:
+// the word is erased with backspaces and the replacement sent as Unicode
+// input. Keys are tracked by scan code, so the buffer holds the QWERTY keys
+// that were pressed and can be read back on any layout. Caps lock is not
+// tracked.
+use crate::detect;
+use crate::layouts::{self, LayoutCode};
+use std::sync::{Mutex, OnceLock};
+use windows_sys::Win32::Foundation::{LPARAM, LRESULT, WPARAM};
+use windows_sys::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows_sys::Win32::System::Threading::GetCurrentThreadId;
+use windows_sys::Win32::UI::Input::KeyboardAndMouse::{
+    GetKeyState, GetKeyboardLayout, SendInput, INPUT, INPUT_0, INPUT_KEYBOARD, KEYBDINPUT,
+    KEYEVENTF_KEYUP, KEYEVENTF_UNICODE, VK_BACK, VK_CAPITAL, VK_CONTROL, VK_F1, VK_LCONTROL,
+    VK_LMENU, VK_LSHIFT, VK_LWIN, VK_MENU, VK_PAUSE, VK_RCONTROL, VK_RMENU, VK_RSHIFT, VK_RWIN,
+    VK_SCROLL, VK_SHIFT, VK_SPACE,
+};
+use windows_sys::Win32::UI::WindowsAndMessaging::{
+    CallNextHookEx, GetForegroundWindow, GetMessageW, GetWindowThreadProcessId, PostThreadMessageW,
+    SetWindowsHookExW, UnhookWindowsHookEx, KBDLLHOOKSTRUCT, LLKHF_INJECTED, MSG, WH_KEYBOARD_LL,
+    WM_APP, WM_KEYDOWN, WM_SYSKEYDOWN,
+};
+
+// Set-1 scan codes of the keys in `layouts::QWERTY_ROWS`, by their unshifted
+// QWERTY character
+const SCAN_CODES: [(u32, char); 47] = [
+    (0x29, '`'),
+    (0x02, '1'),
+    (0x03, '2'),
+    (0x04, '3'),
+    (0x05, '4'),
+    (0x06, '5'),
+    (0x07, '6'),
+    (0x08, '7'),
+    (0x09, '8'),
+    (0x0a, '9'),
+    (0x0b, '0'),
+    (0x0c, '-'),
+    (0x0d, '='),
+    (0x10, 'q'),
+    (0x11, 'w'),
+    (0x12, 'e'),
+    (0x13, 'r'),
+    (0x14, 't'),
+    (0x15, 'y'),
+    (0x16, 'u'),
+    (0x17, 'i'),
+    (0x18, 'o'),
+    (0x19, 'p'),
+    (0x1a, '['),
+    (0x1b, ']'),
+    (0x2b, '\\'),
+    (0x1e, 'a'),
+    (0x1f, 's'),
+    (0x20, 'd'),
+    (0x21, 'f'),
+    (0x22, 'g'),
+    (0x23, 'h'),
+    (0x24, 'j'),
+    (0x25, 'k'),
+    (0x26, 'l'),
+    (0x27, ';'),
+    (0x28, '\''),
+    (0x2c, 'z'),
+    (0x2d, 'x'),
+    (0x2e, 'c'),
+    (0x2f, 'v'),
+    (0x30, 'b'),
+    (0x31, 'n'),
+    (0x32, 'm'),
+    (0x33, ','),
+    (0x34, '.'),
+    (0x35, '/'),
+];
+
+// Posted by the hook to the message loop, which does the actual work so the
+// hook returns quickly
+const FIX_REQUESTED: u32 = WM_APP;
+const WORD_TYPED: u32 = WM_APP + 1;
+
+const LANG_RUSSIAN: isize = 0x19;
+
+struct Settings {
+    thread: u32,
+    hotkey: u16,
+    layouts: Vec<LayoutCode>,
+}
+
+// QWERTY keys of the word being typed, and of the previous word with the
+// number of spaces typed after it
+#[derive(Default)]
+struct Buffer {
+    current: Vec<char>,
+    previous: Vec<char>,
+    spaces: usize,
+}
+
+static SETTINGS: OnceLock<Settings> = OnceLock::new();
+static BUFFER: Mutex<Buffer> = Mutex::new(Buffer {
+    current: Vec::new(),
+    previous: Vec::new(),
+    spaces: 0,
+});
+
+// Virtual-key code for the supported hotkey names, which follow the evdev
+// names used by the Linux daemon
+pub fn parse_hotkey(name: &str) -> Option<u16> {
+    match name {
+        "KEY_PAUSE" => Some(VK_PAUSE),
+        "KEY_SCROLLLOCK" => Some(VK_SCROLL),
+        _ => {
+            let number: u16 = name.strip_prefix("KEY_F")?.parse().ok()?;
+            (1..=24).contains(&number).then(|| VK_F1 + number - 1)
+        }
+    }
+}
+
+fn is_down(vk: u16) -> bool {
+    unsafe { GetKeyState(vk as i32) < 0 }
+}
+
+fn is_modifier(vk: u16) -> bool {
+    [
+        VK_SHIFT,
+        VK_LSHIFT,
+        VK_RSHIFT,
+        VK_CONTROL,
+        VK_LCONTROL,
+        VK_RCONTROL,
+        VK_MENU,
+        VK_LMENU,
+        VK_RMENU,
+        VK_LWIN,
+        VK_RWIN,
+        VK_CAPITAL,
+    ]
+    .contains(&vk)
+}
+
+fn key_for_scan_code(scan_code: u32) -> Option<char> {
+    SCAN_CODES
+        .iter()
+        .find(|(code, _)| *code == scan_code)
+        .map(|&(_, key)| key)
+}
+
+fn shifted(key: char) -> char {
+    layouts::shift_pairs()
+        .find(|&(lower, _)| lower == key)
+        .map_or(key, |(_, upper)| upper)
+}
+
+// Updates the word buffer for a key press. Returns whether the key should be
+// swallowed.
+fn on_key_down(settings: &Settings, hook: &KBDLLHOOKSTRUCT) -> bool {
+    let vk = hook.vkCode as u16;
+    if vk == settings.hotkey {
+        unsafe { PostThreadMessageW(settings.thread, FIX_REQUESTED, 0, 0) };
+        return true;
+    }
+    if is_modifier(vk) {
+        return false;
+    }
+
+    let Ok(mut buffer) = BUFFER.lock() else {
+        return false;
+    };
+    if is_down(VK_CONTROL) || is_down(VK_MENU) {
+        // Shortcuts may move the cursor, so the buffer no longer matches
+        *buffer = Buffer::default();
+    } else if vk == VK_BACK {
+        if buffer.current.pop().is_none() {
+            *buffer = Buffer::default();
+        }
+    } else if vk == VK_SPACE {
+        if !buffer.current.is_empty() {
+            buffer.previous = std::mem::take(&mut buffer.current);
+            buffer.spaces = 1;
+            unsafe { PostThreadMessageW(settings.thread, WORD_TYPED, 0, 0) };
+        } else if !buffer.previous.is_empty() {
+            buffer.spaces += 1;
+        }
+    } else if let Some(key) = key_for_scan_code(hook.scanCode) {
+        let key = if is_down(VK_SHIFT) { shifted(key) } else { key };
+        buffer.current.push(key);
+    } else {
+        *buffer = Buffer::default();
+    }
+    false
+}
+
+unsafe extern "system" fn hook_proc(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code >= 0 && (wparam as u32 == WM_KEYDOWN || wparam as u32 == WM_SYSKEYDOWN) {
+        let hook = &*(lparam as *const KBDLLHOOKSTRUCT);
+        // Our own retyping comes back through the hook as injected input
+        if hook.flags & LLKHF_INJECTED == 0 {
+            if let Some(settings) = SETTINGS.get() {
+                if on_key_down(settings, hook) {
+                    return 1;
+                }
+            }
+        }
+    }
+    CallNextHookEx(0, code, wparam, lparam)
+}
+
+// Layout of the window being typed into, as far as the built-in layouts can
+// tell it apart
+fn active_layout() -> LayoutCode {
+    let layout = unsafe {
+        let thread = GetWindowThreadProcessId(GetForegroundWindow(), std::ptr::null_mut());
+        GetKeyboardLayout(thread)
+    };
+    if layout & 0x3ff == LANG_RUSSIAN {
+        LayoutCode::Russian
+    } else {
+        LayoutCode::Qwerty
+    }
+}
+
+fn typed(keys: &[char], layout: LayoutCode) -> String {
+    keys.iter()
+        .map(|&key| layouts::char_at(layout, key))
+        .collect()
+}
+
+// What `word`, typed on `layout`, should have been: the detected fix, or
+// else the same keys on the first other layout
+fn replacement(word: &str, layout: LayoutCode, settings: &Settings) -> Option<String> {
+    detect::fix_word(word, &settings.layouts).or_else(|| {
+        let other = *settings.layouts.iter().find(|&&other| other != layout)?;
+        Some(layouts::convert_text(word, layout, other))
+    })
+}
+
+fn key_input(vk: u16, scan: u16, flags: u32) -> INPUT {
+    INPUT {
+        r#type: INPUT_KEYBOARD,
+        Anonymous: INPUT_0 {
+            ki: KEYBDINPUT {
+                wVk: vk,
+                wScan: scan,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    }
+}
+
+// Presses backspace `erase` times, then types `text`
+fn retype(erase: usize, text: &str) {
+    let mut inputs = Vec::new();
+    for _ in 0..erase {
+        inputs.push(key_input(VK_BACK, 0, 0));
+        inputs.push(key_input(VK_BACK, 0, KEYEVENTF_KEYUP));
+    }
+    for unit in text.encode_utf16() {
+        inputs.push(key_input(0, unit, KEYEVENTF_UNICODE));
+        inputs.push(key_input(0, unit, KEYEVENTF_UNICODE | KEYEVENTF_KEYUP));
+    }
+    unsafe {
+        SendInput(
+            inputs.len() as u32,
+            inputs.as_ptr(),
+            std::mem::size_of::<INPUT>() as i32,
+        )
+    };
+}
+
+fn fix_last_word(settings: &Settings) {
+    let (keys, spaces) = {
+        let Ok(mut buffer) = BUFFER.lock() else {
+            return;
+        };
+        let buffer = std::mem::take(&mut *buffer);
+        if buffer.current.is_empty() {
+            (buffer.previous, buffer.spaces)
+        } else {
+            (buffer.current, 0)
+        }
+    };
+    if keys.is_empty() {
+        return;
+    }
+
+    let layout = active_layout();
+    let word = typed(&keys, layout);
+    if let Some(replacement) = replacement(&word, layout, settings) {
+        log::info!("retyping '{}' as '{}'", word, replacement);
+        retype(
+            word.chars().count() + spaces,
+            &(replacement + &" ".repeat(spaces)),
+        );
+    }
+}
+
+fn report_last_word(settings: &Settings) {
+    let keys = match BUFFER.lock() {
+        Ok(buffer) => buffer.previous.clone(),
+        Err(_) => return,
+    };
+    let word = typed(&keys, active_layout());
+    if let Some(fixed) = detect::fix_word(&word, &settings.layouts) {
+        log::info!("'{}' looks like '{}'", word, fixed);
+    }
+}
+
+// Installs the hook and runs the message loop until the process is stopped.
+// `hotkey` is a name accepted by `parse_hotkey`.
+pub fn run(layouts: Vec<LayoutCode>, hotkey: &str) -> Result<(), String> {
+    let hotkey = parse_hotkey(hotkey).ok_or_else(|| format!("unknown key '{}'", hotkey))?;
+    let settings = Settings {
+        thread: unsafe { GetCurrentThreadId() },
+        hotkey,
+        layouts,
+    };
+    SETTINGS
+        .set(settings)
+        .map_err(|_| "the keyboard hook is already running".to_string())?;
+    let settings = SETTINGS.get().expect("settings were just set");
+
+    let hook = unsafe {
+        SetWindowsHookExW(
+            WH_KEYBOARD_LL,
+            Some(hook_proc),
+            GetModuleHandleW(std::ptr::null()),
+            0,
+        )
+    };
+    if hook == 0 {
+        return Err(format!(
+            "cannot install the keyboard hook: {}",
+            std::io::Error::last_os_error()
+        ));
+    }
+
+    let mut message: MSG = unsafe { std::mem::zeroed() };
+    while unsafe { GetMessageW(&mut message, 0, 0, 0) } > 0 {
+        match message.message {
+            FIX_REQUESTED => fix_last_word(settings),
+            WORD_TYPED => report_last_word(settings),
+            _ => {}
+        }
+    }
+    unsafe { UnhookWindowsHookEx(hook) };
+    Ok(())
+}
@@ -0,0 +1,190 @@
+// XKB symbols files, to install a layout on Linux: the exporter writes the
+// keys of the main block of a layout, AltGr levels included, and the
+// importer reads such a file back as a custom layout. Only keysyms for
+// ASCII characters are written by name, everything else as Unicode
+// (U0444), and the importer knows no other names.
+use crate::layouts::{self, LayoutCode};
+use crate::registry::Layout;
+use crate::schema::LayoutFile;
+use std::collections::BTreeMap;
+
+// XKB names of the keys of each `layouts::QWERTY_ROWS` row
+const KEY_NAMES: [&[&str]; 4] = [
+    &[
+        "TLDE", "AE01", "AE02", "AE03", "AE04", "AE05", "AE06", "AE07", "AE08", "AE09", "AE10",
+        "AE11", "AE12",
+    ],
+    &[
+        "AD01", "AD02", "AD03", "AD04", "AD05", "AD06", "AD07", "AD08", "AD09", "AD10", "AD11",
+        "AD12", "BKSL",
+    ],
+    &[
+        "AC01", "AC02", "AC03", "AC04", "AC05", "AC06", "AC07", "AC08", "AC09", "AC10", "AC11",
+    ],
+    &[
+        "AB01", "AB02", "AB03", "AB04", "AB05", "AB06", "AB07", "AB08", "AB09", "AB10",
+    ],
+];
+
+// Keysym names of the ASCII punctuation
+const KEYSYMS: [(char, &str); 33] = [
+    (' ', "space"),
+    ('!', "exclam"),
+    ('"', "quotedbl"),
+    ('#', "numbersign"),
+    ('$', "dollar"),
+    ('%', "percent"),
+    ('&', "ampersand"),
+    ('\'', "apostrophe"),
+    ('(', "parenleft"),
+    (')', "parenright"),
+    ('*', "asterisk"),
+    ('+', "plus"),
+    (',', "comma"),
+    ('-', "minus"),
+    ('.', "period"),
+    ('/', "slash"),
+    (':', "colon"),
+    (';', "semicolon"),
+    ('<', "less"),
+    ('=', "equal"),
+    ('>', "greater"),
+    ('?', "question"),
+    ('@', "at"),
+    ('[', "bracketleft"),
+    ('\\', "backslash"),
+    (']', "bracketright"),
+    ('^', "asciicircum"),
+    ('_', "underscore"),
+    ('`', "grave"),
+    ('{', "braceleft"),
+    ('|', "bar"),
+    ('}', "braceright"),
+    ('~', "asciitilde"),
+];
+
+fn keysym(c: char) -> String {
+    if c.is_ascii_alphanumeric() {
+        return c.to_string();
+    }
+    match KEYSYMS.iter().find(|&&(punctuation, _)| punctuation == c) {
+        Some((_, name)) => name.to_string(),
+        None => format!("U{:04X}", c as u32),
+    }
+}
+
+fn parse_keysym(name: &str) -> Result<char, String> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(c);
+    }
+    if let Some(&(c, _)) = KEYSYMS.iter().find(|&&(_, keysym)| keysym == name) {
+        return Ok(c);
+    }
+    name.strip_prefix('U')
+        .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+        .and_then(char::from_u32)
+        .ok_or(format!("unknown keysym '{}'", name))
+}
+
+// The symbols of `layout` as an XKB symbols file, to be saved as
+// /usr/share/X11/xkb/symbols/<name> or ~/.config/xkb/symbols/<name>.
+// Layouts that compose keystrokes, like Korean or Telex, type the
+// keystrokes only.
+pub fn export(layout: &Layout) -> String {
+    let altgr = match layout {
+        Layout::Builtin(code) => layouts::altgr_keys(*code),
+        Layout::Custom(_) => &[],
+    };
+    let mut symbols = format!(
+        "// {} layout, exported by keymorph\n\
+         default partial alphanumeric_keys\n\
+         xkb_symbols \"{}\" {{\n    name[Group1] = \"{} (keymorph)\";\n\n",
+        layout.name(),
+        layout.name(),
+        layout.name()
+    );
+    for ((unshifted, shifted), names) in layouts::QWERTY_ROWS.iter().zip(KEY_NAMES) {
+        for ((key, shifted_key), name) in unshifted.chars().zip(shifted.chars()).zip(names) {
+            let mut levels = vec![
+                keysym(layout.char_at(key)),
+                keysym(layout.char_at(shifted_key)),
+            ];
+            if let Some(altgr) = altgr.iter().find(|altgr| altgr.key == key) {
+                levels.push(keysym(altgr.unshifted));
+                levels.push(keysym(altgr.shifted));
+            }
+            symbols += &format!("    key <{}> {{ [ {} ] }};\n", name, levels.join(", "));
+        }
+    }
+    if !altgr.is_empty() {
+        symbols += "\n    include \"level3(ralt_switch)\"\n";
+    }
+    symbols += "};\n";
+    symbols
+}
+
+// Reads the first `xkb_symbols` section of an XKB symbols file as a custom
+// layout based on QWERTY. The first two levels of the keys of the main block
+// are read; AltGr levels and other keys are ignored, and keys the section
+// does not list, which would come from its includes, keep what they type on
+// QWERTY.
+pub fn import(symbols: &str) -> Result<LayoutFile, String> {
+    let source: String = symbols
+        .lines()
+        .map(|line| line.split("//").next().unwrap_or(""))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let start = source.find("xkb_symbols").ok_or("no xkb_symbols section")?;
+    let section = &source[start..];
+    let name = section
+        .split('"')
+        .nth(1)
+        .filter(|name| !name.is_empty())
+        .ok_or("the xkb_symbols section has no name")?;
+
+    let mut keys = BTreeMap::new();
+    for statement in section.split("key <").skip(1) {
+        let (key_name, rest) = statement.split_once('>').ok_or("unterminated key name")?;
+        let Some((row, column)) = KEY_NAMES
+            .iter()
+            .enumerate()
+            .find_map(|(row, names)| Some((row, names.iter().position(|n| *n == key_name)?)))
+        else {
+            continue;
+        };
+        let body = rest.split_once('}').map_or(rest, |(body, _)| body);
+        // The levels are the brackets after `{` or `symbols[Group1] =`,
+        // not those naming a group
+        let levels = body
+            .match_indices('[')
+            .find(|&(index, _)| {
+                matches!(
+                    body[..index].trim_end().chars().last(),
+                    Some('{' | '=' | ',')
+                )
+            })
+            .and_then(|(index, _)| body[index + 1..].split_once(']'))
+            .map(|(levels, _)| levels)
+            .ok_or(format!("key <{}> has no symbols", key_name))?;
+        let (unshifted, shifted) = layouts::QWERTY_ROWS[row];
+        let qwerty = [unshifted.chars().nth(column), shifted.chars().nth(column)];
+        for (level, keysym) in levels.split(',').map(str::trim).take(2).enumerate() {
+            let (Some(key), false) = (qwerty[level], keysym.is_empty() || keysym == "NoSymbol")
+            else {
+                continue;
+            };
+            let c = parse_keysym(keysym).map_err(|err| format!("key <{}>: {}", key_name, err))?;
+            if c != key {
+                keys.insert(key.to_string(), c.to_string());
+            }
+        }
+    }
+
+    Ok(LayoutFile {
+        name: name.to_string(),
+        base: LayoutCode::Qwerty.as_str().to_string(),
+        keys,
+        derived: None,
+    })
+}
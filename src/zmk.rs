@@ -0,0 +1,117 @@
+// ZMK keymaps, the devicetree `.keymap` file of a board's ZMK config. Like
+// the QMK ones they have a single layer typing the layout, and leave keys
+// the host cannot type unbound.
+use crate::firmware::{Binding, Board, Exporter, Keymap, Special};
+use crate::registry::Layout;
+
+// ZMK keycode of each QWERTY key of the main block that is not a letter
+const KEYCODES: [(char, &str); 21] = [
+    ('`', "GRAVE"),
+    ('-', "MINUS"),
+    ('=', "EQUAL"),
+    ('[', "LBKT"),
+    (']', "RBKT"),
+    ('\\', "BSLH"),
+    (';', "SEMI"),
+    ('\'', "SQT"),
+    (',', "COMMA"),
+    ('.', "DOT"),
+    ('/', "FSLH"),
+    ('0', "N0"),
+    ('1', "N1"),
+    ('2', "N2"),
+    ('3', "N3"),
+    ('4', "N4"),
+    ('5', "N5"),
+    ('6', "N6"),
+    ('7', "N7"),
+    ('8', "N8"),
+    ('9', "N9"),
+];
+
+pub struct Zmk;
+
+impl Exporter for Zmk {
+    fn name(&self) -> &'static str {
+        "ZMK"
+    }
+
+    fn supports(&self, board: Board) -> bool {
+        matches!(board, Board::Corne | Board::Glove80)
+    }
+
+    fn export(&self, layout: &Layout, keymap: &Keymap) -> String {
+        let mut dts = format!(
+            "/*\n * {} for {}, generated by keymorph\n",
+            layout.name(),
+            keymap.board.as_str()
+        );
+        for problem in &keymap.problems {
+            dts += &format!(" * {}\n", problem);
+        }
+        dts += " */\n\n\
+                #include <behaviors.dtsi>\n\
+                #include <dt-bindings/zmk/keys.h>\n\n\
+                / {\n    \
+                keymap {\n        \
+                compatible = \"zmk,keymap\";\n\n        \
+                default_layer {\n            \
+                bindings = <\n";
+        for row in &keymap.rows {
+            let bindings: Vec<String> = row.iter().map(|&b| behavior(b)).collect();
+            dts += &format!("                {}\n", bindings.join(" "));
+        }
+        dts += "            >;\n        };\n    };\n};\n";
+        dts
+    }
+}
+
+fn special(special: Special) -> String {
+    let keycode = match special {
+        Special::Escape => "ESC",
+        Special::Tab => "TAB",
+        Special::CapsLock => "CAPS",
+        Special::Backspace => "BSPC",
+        Special::Enter => "RET",
+        Special::Space => "SPACE",
+        Special::LeftShift => "LSHFT",
+        Special::RightShift => "RSHFT",
+        Special::LeftCtrl => "LCTRL",
+        Special::RightCtrl => "RCTRL",
+        Special::LeftAlt => "LALT",
+        Special::RightAlt => "RALT",
+        Special::LeftGui => "LGUI",
+        Special::RightGui => "RGUI",
+        Special::Menu => "K_APP",
+        Special::Left => "LEFT",
+        Special::Down => "DOWN",
+        Special::Up => "UP",
+        Special::Right => "RIGHT",
+        Special::Home => "HOME",
+        Special::End => "END",
+        Special::PageUp => "PG_UP",
+        Special::PageDown => "PG_DN",
+        Special::Delete => "DEL",
+        Special::Function(n) => return format!("F{}", n),
+    };
+    keycode.to_string()
+}
+
+// The behavior a key is bound to
+pub fn behavior(binding: Binding) -> String {
+    match binding {
+        Binding::Key { key, shift } => {
+            let keycode = match KEYCODES.iter().find(|&&(qwerty, _)| qwerty == key) {
+                Some(&(_, keycode)) => keycode.to_string(),
+                None => key.to_ascii_uppercase().to_string(),
+            };
+            if shift {
+                format!("&kp LS({})", keycode)
+            } else {
+                format!("&kp {}", keycode)
+            }
+        }
+        Binding::Special(key) => format!("&kp {}", special(key)),
+        Binding::None => "&none".to_string(),
+    }
+}
@@ -0,0 +1,119 @@
+// Typing effort reports
+use keymorph::analyze::{self, Metric};
+use keymorph::geometry::{Finger, Row};
+use keymorph::layouts::LayoutCode;
+
+const TEXT: &str = "The quick brown fox jumps over the lazy dog, twice.";
+
+#[test]
+fn spreads_the_load_over_fingers_and_hands() {
+    for code in [LayoutCode::Qwerty, LayoutCode::Dvorak, LayoutCode::Colemak] {
+        let report = analyze::analyze(TEXT, code);
+        let fingers: f64 = report.finger_load.iter().map(|finger| finger.load).sum();
+        assert!((fingers - 100.0).abs() < 1e-9, "{}", code.as_str());
+        assert!((report.left_hand + report.right_hand - 100.0).abs() < 1e-9);
+        let left: f64 = report.finger_load[..4]
+            .iter()
+            .map(|finger| finger.load)
+            .sum();
+        assert!((left - report.left_hand).abs() < 1e-9, "{}", code.as_str());
+    }
+
+    let report = analyze::analyze("aaaj", LayoutCode::Qwerty);
+    assert_eq!(report.finger_load[0].finger, Finger::LeftPinky);
+    assert_eq!(report.finger_load[0].load, 75.0);
+    assert_eq!(report.right_hand, 25.0);
+
+    let report = analyze::analyze("", LayoutCode::Qwerty);
+    assert_eq!(report.left_hand, 0.0);
+}
+
+#[test]
+fn classifies_bigrams_and_trigrams() {
+    // Ring to middle to index, then index back to the ring finger, which
+    // makes `dfs` a redirect
+    let report = analyze::analyze("sdfs", LayoutCode::Qwerty);
+    assert!((report.inward_rolls - 200.0 / 3.0).abs() < 1e-9);
+    assert!((report.outward_rolls - 100.0 / 3.0).abs() < 1e-9);
+    assert_eq!(report.redirects, 50.0);
+
+    let report = analyze::analyze("sfd", LayoutCode::Qwerty);
+    assert_eq!(report.redirects, 100.0);
+
+    // Middle finger to the index finger stretching to the centre column
+    let report = analyze::analyze("dg", LayoutCode::Qwerty);
+    assert_eq!(report.lateral_stretches, 100.0);
+    let report = analyze::analyze("df", LayoutCode::Qwerty);
+    assert_eq!(report.lateral_stretches, 0.0);
+
+    // Alternating hands is neither a roll nor a redirect
+    let report = analyze::analyze("fjfj", LayoutCode::Qwerty);
+    assert_eq!(
+        report.inward_rolls + report.outward_rolls + report.redirects,
+        0.0
+    );
+
+    // Whitespace breaks sequences
+    let report = analyze::analyze("s d f", LayoutCode::Qwerty);
+    assert_eq!(report.inward_rolls, 0.0);
+}
+
+#[test]
+fn breaks_keystrokes_down_by_row() {
+    let report = analyze::analyze("1qaz", LayoutCode::Qwerty);
+    let rows: Vec<(Row, f64)> = report
+        .row_usage
+        .iter()
+        .map(|row| (row.row, row.usage))
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            (Row::Number, 25.0),
+            (Row::Top, 25.0),
+            (Row::Home, 25.0),
+            (Row::Bottom, 25.0)
+        ]
+    );
+    assert_eq!(report.home_row, 25.0);
+
+    for code in [LayoutCode::Qwerty, LayoutCode::Dvorak, LayoutCode::Colemak] {
+        let report = analyze::analyze(TEXT, code);
+        let usage: f64 = report.row_usage.iter().map(|row| row.usage).sum();
+        assert!((usage - 100.0).abs() < 1e-9, "{}", code.as_str());
+    }
+}
+
+#[test]
+fn ranks_layouts() {
+    let layouts = [
+        LayoutCode::Russian,
+        LayoutCode::Qwerty,
+        LayoutCode::Dvorak,
+        LayoutCode::Colemak,
+    ];
+    let ranked = analyze::compare(TEXT, &layouts, Metric::default());
+    assert_eq!(ranked.len(), layouts.len());
+    // Russian types none of the text and comes last despite no travel
+    assert_eq!(ranked.last().unwrap().report.layout, "russian");
+    assert_eq!(ranked[0].rank, 1);
+    let travel: Vec<f64> = ranked[..3]
+        .iter()
+        .map(|ranked| ranked.report.finger_travel / ranked.report.keystrokes as f64)
+        .collect();
+    assert!(travel.windows(2).all(|pair| pair[0] <= pair[1]));
+
+    // Higher is better for the home row, and ties share a rank
+    let ranked = analyze::compare(
+        "asdf",
+        &[LayoutCode::Dvorak, LayoutCode::Qwerty, LayoutCode::Colemak],
+        Metric::HomeRow,
+    );
+    assert_eq!(ranked[0].report.layout, "qwerty");
+    let ranked = analyze::compare(
+        "",
+        &[LayoutCode::Dvorak, LayoutCode::Qwerty],
+        Metric::HomeRow,
+    );
+    assert!(ranked.iter().all(|ranked| ranked.rank == 1));
+}
@@ -0,0 +1,57 @@
+// Round trips of layouts through the canonical schema
+use keymorph::canonical::{self, VERSION};
+use keymorph::layouts::{self, LayoutCode};
+use keymorph::registry::Layout;
+
+#[test]
+fn round_trips_every_layout() {
+    for code in LayoutCode::ALL {
+        let exported = canonical::export(&Layout::Builtin(code));
+        let json = canonical::from_json(&canonical::to_json(&exported)).unwrap();
+        let toml = canonical::from_toml(&canonical::to_toml(&exported)).unwrap();
+        assert_eq!(json, exported, "{}", code.as_str());
+        assert_eq!(toml, exported, "{}", code.as_str());
+
+        let file = canonical::import(&exported).unwrap();
+        for (lower, upper) in layouts::shift_pairs() {
+            for key in [lower, upper] {
+                let imported = file
+                    .keys
+                    .get(&key.to_string())
+                    .map_or(key.to_string(), Clone::clone);
+                assert_eq!(
+                    imported,
+                    layouts::char_at(code, key).to_string(),
+                    "{} on {}",
+                    key,
+                    code.as_str()
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn rejects_invalid_layouts() {
+    let layout = canonical::export(&Layout::Builtin(LayoutCode::Dvorak));
+
+    let mut newer = layout.clone();
+    newer.version = VERSION + 1;
+    assert!(canonical::import(&newer).is_err());
+
+    let mut unknown = layout.clone();
+    unknown.keys[0].code = "KeyÅ".to_string();
+    assert!(canonical::import(&unknown).is_err());
+
+    let mut twice = layout.clone();
+    twice.keys[1].code = twice.keys[0].code.clone();
+    assert!(canonical::import(&twice).is_err());
+
+    let mut short = layout.clone();
+    short.keys[0].chars.pop();
+    assert!(canonical::import(&short).is_err());
+
+    let mut long = layout;
+    long.keys[0].chars[0] = "ab".to_string();
+    assert!(canonical::import(&long).is_err());
+}
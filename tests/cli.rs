@@ -0,0 +1,150 @@
+// The command line interface, run as a process
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Output, Stdio};
+
+// Runs keymorph in `dir` with `args`, feeding it `input`
+fn keymorph(dir: &Path, args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_keymorph"))
+        .args(args)
+        .current_dir(dir)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Commands failing before they read their input close it early
+    let _ = child.stdin.take().unwrap().write_all(input.as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn in_place_needs_an_input_file() {
+    let dir = std::env::temp_dir().join(format!("keymorph-cli-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for args in [
+        &[
+            "convert",
+            "--from",
+            "qwerty",
+            "--to",
+            "russian",
+            "--in-place",
+        ][..],
+        &["fix", "--in-place"],
+    ] {
+        let output = keymorph(&dir, args, "ghbdtn");
+        assert_eq!(output.status.code(), Some(2), "{:?}", args);
+        assert!(String::from_utf8_lossy(&output.stderr).contains("needs an input file"));
+        // Nothing is written to a file named after standard input
+        assert!(!dir.join("-").exists(), "{:?}", args);
+    }
+
+    // Files are still converted in place
+    let file = dir.join("text.txt");
+    std::fs::write(&file, "ghbdtn").unwrap();
+    let output = keymorph(
+        &dir,
+        &[
+            "convert", "--from", "qwerty", "--to", "russian", "-i", "text.txt",
+        ],
+        "",
+    );
+    assert!(output.status.success());
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "привет");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn types_key_events_as_lines_or_an_array() {
+    let dir = std::env::temp_dir();
+    let lines = "{\"key\": \"KeyH\", \"shift\": true}\n\n{\"key\": \"KeyI\"}\n";
+    let array = "[{\"key\": \"KeyH\", \"shift\": true}, {\"key\": \"KeyI\"}]";
+    for input in [lines, array] {
+        let output = keymorph(&dir, &["keys", "type", "--layout", "qwerty"], input);
+        assert!(output.status.success(), "{}", input);
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "Hi");
+    }
+
+    let output = keymorph(
+        &dir,
+        &["keys", "type", "--layout", "qwerty"],
+        "{\"key\": 1}\n",
+    );
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("line 1"));
+}
+
+#[test]
+fn layout_ids_carry_options() {
+    let dir = std::env::temp_dir();
+    let convert = |to: &str| keymorph(&dir, &["convert", "--from", "qwerty", "--to", to], "`kf");
+    let output = convert("russian?yo=false");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim_end(), "ела");
+
+    let output = convert("russian?yo=maybe");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).trim_end(),
+        "Error: unknown layout 'russian?yo=maybe'"
+    );
+}
+
+#[test]
+fn converts_with_configured_custom_layouts() {
+    let dir = std::env::temp_dir().join(format!("keymorph-cli-layouts-{}", std::process::id()));
+    let layouts = dir.join("layouts");
+    std::fs::create_dir_all(&layouts).unwrap();
+    std::fs::write(
+        layouts.join("qwerty-swapped.toml"),
+        "name = \"qwerty-swapped\"\nbase = \"qwerty\"\n\
+         [keys]\nq = \"w\"\nw = \"q\"\nQ = \"W\"\nW = \"Q\"\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("keymorph.toml"),
+        "[layouts]\ndirs = [\"layouts\"]\n",
+    )
+    .unwrap();
+
+    let run = |args: &[&str], input: &str| {
+        let args = [&["--config", "keymorph.toml"][..], args].concat();
+        keymorph(&dir, &args, input)
+    };
+    let output = run(
+        &["convert", "--from", "qwerty-swapped", "--to", "russian"],
+        "Wqe",
+    );
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Йцу");
+
+    let output = run(
+        &["convert", "--from", "russian", "--to", "qwerty-swapped"],
+        "йц",
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "wq");
+
+    // Options of the streaming converter only apply to built-in layouts
+    let output = run(
+        &[
+            "convert",
+            "--from",
+            "qwerty-swapped",
+            "--to",
+            "russian",
+            "--strict",
+        ],
+        "wq",
+    );
+    assert_eq!(output.status.code(), Some(2));
+
+    // Custom layouts have no words to detect them by
+    let output = run(&["fix", "--layouts", "qwerty-swapped,russian"], "wq");
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).trim_end(),
+        "Error: cannot detect custom layout 'qwerty-swapped'"
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
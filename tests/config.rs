@@ -0,0 +1,60 @@
+// Configuration files and the environment overlay. The environment is shared
+// by the whole process, so everything reading it is one test.
+use keymorph::config::Config;
+use keymorph::detect::Backend;
+use std::path::PathBuf;
+
+#[test]
+fn environment_overrides_the_file() {
+    let dir = std::env::temp_dir().join(format!("keymorph-config-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("keymorph.toml");
+    std::fs::write(
+        &path,
+        "[server]\nport = 9000\nhost = \"0.0.0.0\"\n\n[limits]\nmax_text_chars = 10\n",
+    )
+    .unwrap();
+
+    let config = Config::load(Some(&path)).unwrap();
+    assert_eq!(config.server.port, 9000);
+    assert_eq!(config.limits.max_text_chars, 10);
+    // Sections and fields the file leaves out keep their defaults
+    assert_eq!(config.limits.max_payload_bytes, 4 * 1024 * 1024);
+    assert!(config.layouts.watch);
+
+    std::env::set_var("KEYMORPH_PORT", "9100");
+    std::env::set_var("KEYMORPH_DETECTION_BACKEND", "trigram");
+    std::env::set_var("KEYMORPH_LAYOUTS_DIRS", "a, ,b");
+    std::env::set_var("KEYMORPH_LAYOUTS_WATCH", "false");
+    let config = Config::load(Some(&path)).unwrap();
+    assert_eq!(config.server.port, 9100);
+    assert_eq!(config.server.host, "0.0.0.0");
+    assert_eq!(config.limits.max_text_chars, 10);
+    assert_eq!(config.detection.backend, Backend::Trigram);
+    assert_eq!(
+        config.layouts.dirs,
+        [PathBuf::from("a"), PathBuf::from("b")]
+    );
+    assert!(!config.layouts.watch);
+
+    std::env::set_var("KEYMORPH_PORT", "eighty");
+    assert_eq!(
+        Config::load(Some(&path)).unwrap_err(),
+        "invalid value 'eighty' for KEYMORPH_PORT"
+    );
+    std::env::remove_var("KEYMORPH_PORT");
+    std::env::set_var("KEYMORPH_DETECTION_BACKEND", "magic");
+    assert!(Config::load(Some(&path)).is_err());
+    std::env::remove_var("KEYMORPH_DETECTION_BACKEND");
+
+    // Unknown fields are typos, not ignored
+    std::fs::write(&path, "[server]\nprot = 9000\n").unwrap();
+    let err = Config::load(Some(&path)).unwrap_err();
+    assert!(err.starts_with("cannot parse"), "{}", err);
+
+    // A file given explicitly has to exist
+    let missing = dir.join("missing.toml");
+    let err = Config::load(Some(&missing)).unwrap_err();
+    assert!(err.starts_with("cannot read"), "{}", err);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
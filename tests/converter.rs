@@ -0,0 +1,177 @@
+// Conversion options of the converter and of custom layouts
+use keymorph::converter::Converter;
+use keymorph::detect;
+use keymorph::layouts::{CasePolicy, LayoutCode, LayoutId, SentenceCase, YoPolicy};
+use keymorph::registry::{self, ConvertOptions, Layout};
+use std::str::FromStr;
+
+fn converter(from: LayoutCode, to: LayoutCode, case_policy: CasePolicy) -> Converter {
+    Converter::builder()
+        .from(from)
+        .to(to)
+        .case_policy(case_policy)
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn sentences_end_at_a_stop_and_whitespace() {
+    let mut capitalized = String::new();
+    let mut sentences = SentenceCase::default();
+    sentences.push("pi is 3.14. see example.com! ok?x", &mut capitalized);
+    sentences.push(" then\nnext", &mut capitalized);
+    assert_eq!(capitalized, "Pi is 3.14. See example.com! Ok?x then\nNext");
+
+    let hebrew = converter(LayoutCode::Hebrew, LayoutCode::Qwerty, CasePolicy::Sentence);
+    let text = hebrew.convert("שלום! שלום");
+    assert_eq!(text, "Akuo! Akuo");
+}
+
+#[test]
+fn sentence_case_leaves_kept_urls_alone() {
+    let converter = Converter::builder()
+        .from(LayoutCode::Hebrew)
+        .to(LayoutCode::Qwerty)
+        .skip_urls(true)
+        .case_policy(CasePolicy::Sentence)
+        .build()
+        .unwrap();
+    assert_eq!(
+        converter.convert("שלום https://example.com/path"),
+        "Akuo https://example.com/path"
+    );
+    // A kept word may start the text, or end a sentence
+    assert_eq!(
+        converter.convert("https://example.com. שלום"),
+        "https://example.com. Akuo"
+    );
+}
+
+#[test]
+fn keep_capitals_survives_a_round_trip() {
+    let there = converter(
+        LayoutCode::Qwerty,
+        LayoutCode::Hebrew,
+        CasePolicy::KeepCapitals,
+    );
+    let back = converter(LayoutCode::Hebrew, LayoutCode::Qwerty, CasePolicy::Keys);
+    for text in ["Hello World", "NATO akuo"] {
+        assert_eq!(back.convert(&there.convert(text)), text);
+    }
+    // By default capitals are typed as the keys they are on
+    let there = converter(LayoutCode::Qwerty, LayoutCode::Hebrew, CasePolicy::Keys);
+    assert_eq!(back.convert(&there.convert("Hello World")), "hello world");
+}
+
+#[test]
+fn custom_layouts_apply_the_case_policy() {
+    let dir = std::env::temp_dir().join(format!("keymorph-converter-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("qwerty-swapped.toml");
+    let file = "name = \"qwerty-swapped\"\nbase = \"qwerty\"\n\
+                [keys]\nq = \"w\"\nw = \"q\"\nQ = \"W\"\nW = \"Q\"\n";
+    std::fs::write(&path, file).unwrap();
+    registry::load_file(&path).unwrap();
+    let custom = registry::resolve("qwerty-swapped").unwrap();
+    let hebrew = Layout::Builtin(LayoutCode::Hebrew);
+
+    let options = |case_policy| ConvertOptions {
+        case_policy,
+        ..Default::default()
+    };
+    assert_eq!(
+        registry::convert_text_with(
+            "שלום! שלום",
+            &hebrew,
+            &custom,
+            &options(CasePolicy::Sentence)
+        ),
+        "Akuo! Akuo"
+    );
+    let there = registry::convert_text_with(
+        "Hello",
+        &custom,
+        &hebrew,
+        &options(CasePolicy::KeepCapitals),
+    );
+    assert_eq!(
+        registry::convert_text_with(&there, &hebrew, &custom, &options(CasePolicy::Keys)),
+        "Hello"
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn digit_symbols_convert_on_request() {
+    let digit_symbols = |from, to, digit_symbols| {
+        Converter::builder()
+            .from(from)
+            .to(to)
+            .digit_symbols(digit_symbols)
+            .build()
+            .unwrap()
+    };
+    let there = digit_symbols(LayoutCode::Qwerty, LayoutCode::Russian, true);
+    let back = digit_symbols(LayoutCode::Russian, LayoutCode::Qwerty, true);
+    assert_eq!(there.convert("#1 $ ^ &"), "№1 ; : ?");
+    assert_eq!(back.convert("№1 ; : ?"), "#1 $ ^ &");
+    // Hashtags and email addresses are left alone by default
+    let there = digit_symbols(LayoutCode::Qwerty, LayoutCode::Russian, false);
+    assert_eq!(there.convert("#1 $ ^ &"), "#1 $ ^ &");
+
+    // Strict mode keeps words with № unless it is one of the converted symbols
+    let strict = |digit_symbols| {
+        Converter::builder()
+            .from(LayoutCode::Russian)
+            .to(LayoutCode::Qwerty)
+            .strict(true)
+            .digit_symbols(digit_symbols)
+            .build()
+            .unwrap()
+    };
+    assert_eq!(strict(false).convert("№1 привет"), "№1 ghbdtn");
+    assert_eq!(strict(true).convert("№1 привет"), "#1 ghbdtn");
+}
+
+#[test]
+fn yo_policies_fold_or_restore_yo() {
+    assert_eq!(YoPolicy::Keep.apply("Ёлка"), "Ёлка");
+    assert_eq!(YoPolicy::FoldToE.apply("Ёлка и ёж"), "Елка и еж");
+    // Only words always spelled with ё are restored, in the case typed
+    assert_eq!(
+        YoPolicy::Restore.apply("Еж, ЕЩЕ елка и лес"),
+        "Ёж, ЕЩЁ ёлка и лес"
+    );
+    assert_eq!(detect::restore_yo("еж-еж"), "ёж-ёж");
+
+    let to = |id: &str| {
+        let id = LayoutId::from_str(id).unwrap();
+        Converter::builder()
+            .from(LayoutCode::Qwerty)
+            .to(id.layout)
+            .options(id.options)
+            .build()
+            .unwrap()
+    };
+    assert_eq!(to("russian").convert("`krf"), "ёлка");
+    assert_eq!(to("russian?yo=false").convert("`krf"), "елка");
+    assert_eq!(to("russian?yo=fold_to_e").convert("`krf"), "елка");
+    assert_eq!(to("russian?yo=restore").convert("T; b tot"), "Ёж и ещё");
+}
+
+#[test]
+fn keep_punctuation_converts_keys_typing_letters() {
+    let converter = |keep_punctuation| {
+        Converter::builder()
+            .from(LayoutCode::Qwerty)
+            .to(LayoutCode::Russian)
+            .keep_punctuation(keep_punctuation)
+            .build()
+            .unwrap()
+    };
+    assert_eq!(converter(false).convert("ghbdtn? vbh/"), "привет, мир.");
+    assert_eq!(converter(true).convert("ghbdtn? vbh/"), "привет? мир/");
+    // Brackets and quotes are Russian letters
+    assert_eq!(converter(true).convert("[t]"), "хеъ");
+    assert_eq!(converter(true).convert("\"vbh\""), "ЭмирЭ");
+}
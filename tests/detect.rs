@@ -0,0 +1,58 @@
+// Scoring words against the dictionaries, allowing for typos
+use keymorph::detect;
+use keymorph::layouts::LayoutCode;
+
+fn score(word: &str) -> f64 {
+    detect::word_score(word, LayoutCode::Qwerty).unwrap()
+}
+
+#[test]
+fn tolerates_typos_by_word_length() {
+    assert_eq!(score("house"), 1.0);
+    assert_eq!(score("House,"), 1.0);
+    // One edit in words of four to seven letters: an insertion, a deletion
+    // or a substitution, but not the two of swapped letters
+    assert_eq!(score("hous"), 0.8);
+    assert_eq!(score("housse"), 0.8);
+    assert_eq!(score("houze"), 0.8);
+    assert_eq!(score("becuase"), 0.4);
+    // Two edits in longer words, where swapped letters fit
+    assert_eq!(score("somethnig"), 0.8);
+    assert_eq!(score("diferentt"), 0.8);
+    assert_eq!(score("smthng"), 0.4);
+    // None at all in words of three letters or fewer
+    assert_eq!(score("the"), 1.0);
+    assert_eq!(score("tge"), 0.4);
+    // Much longer or shorter words are not typos of a dictionary word
+    assert_eq!(score("peoplepeople"), 0.4);
+
+    assert_eq!(
+        detect::word_score("привт", LayoutCode::Russian).unwrap(),
+        0.8
+    );
+    assert_eq!(score("привет"), 0.0);
+    assert_eq!(detect::word_score("42!", LayoutCode::Qwerty), None);
+}
+
+#[test]
+fn fixes_misspelled_words_typed_in_the_wrong_layout() {
+    let layouts = [LayoutCode::Qwerty, LayoutCode::Russian];
+    assert_eq!(
+        detect::fix_word("ghbdn", &layouts).as_deref(),
+        Some("привт")
+    );
+    // A typo of an English word stays English
+    assert_eq!(detect::fix_word("somethnig", &layouts), None);
+}
+
+#[test]
+fn default_layouts_leave_correct_text_alone() {
+    for text in ["yesterday we met at noon", "the quick brown fox"] {
+        assert_eq!(detect::fix_text(text, &detect::DEFAULT_LAYOUTS), text);
+        assert!(detect::detect(text, &detect::DEFAULT_LAYOUTS).is_none());
+    }
+    assert_eq!(
+        detect::fix_text("ghbdtn, the fox", &detect::DEFAULT_LAYOUTS),
+        "привет, the fox"
+    );
+}
@@ -0,0 +1,27 @@
+// Similarity of layouts
+use keymorph::diff;
+use keymorph::layouts::LayoutCode;
+
+#[test]
+fn layouts_are_similar_to_themselves() {
+    for code in LayoutCode::ALL {
+        let similarity = diff::similarity(code, code);
+        assert_eq!(similarity.score, 1.0, "{}", code.as_str());
+        assert_eq!(similarity.same_key, similarity.keys, "{}", code.as_str());
+        let keys: usize = similarity.rows.iter().map(|row| row.keys).sum();
+        assert_eq!(keys, similarity.keys, "{}", code.as_str());
+    }
+}
+
+#[test]
+fn close_variants_score_higher() {
+    let colemak_dh = diff::similarity(LayoutCode::Colemak, LayoutCode::ColemakDh);
+    let dvorak = diff::similarity(LayoutCode::Colemak, LayoutCode::Dvorak);
+    assert!(colemak_dh.score > dvorak.score);
+    assert_eq!(colemak_dh.missing, 0);
+    // Colemak-DH only moves keys off the number row
+    assert_eq!(colemak_dh.rows[0].agreement, 1.0);
+
+    let russian = diff::similarity(LayoutCode::Qwerty, LayoutCode::Russian);
+    assert!(russian.missing > 0);
+}
@@ -0,0 +1,48 @@
+// Fixing documents sentence by sentence
+use keymorph::document::{self, Decision};
+use keymorph::layouts::LayoutCode;
+
+const LAYOUTS: [LayoutCode; 2] = [LayoutCode::Qwerty, LayoutCode::Russian];
+
+#[test]
+fn converts_only_wrong_layout_sentences() {
+    let text = "Hello there.  Ghbdtn rfr ltkf\n\n\nThe weather is nice today!";
+    let fixed = document::fix(text, &LAYOUTS, document::DEFAULT_MIN_CONFIDENCE);
+    assert_eq!(
+        fixed.text,
+        "Hello there.  Привет как дела\n\n\nThe weather is nice today!"
+    );
+
+    let decisions: Vec<(usize, usize, Decision)> = fixed
+        .segments
+        .iter()
+        .map(|segment| (segment.paragraph, segment.sentence, segment.decision))
+        .collect();
+    assert_eq!(
+        decisions,
+        [
+            (0, 0, Decision::Unchanged),
+            (0, 1, Decision::Converted),
+            (1, 0, Decision::Unchanged)
+        ]
+    );
+    let converted = &fixed.segments[1];
+    assert_eq!(&text[converted.start..converted.end], "Ghbdtn rfr ltkf");
+    assert_eq!(converted.detection.unwrap().to, LayoutCode::Russian);
+}
+
+#[test]
+fn leaves_uncertain_sentences_alone() {
+    let text = "I told him ghbdtn and left, as you do at the end of the day.";
+    let fixed = document::fix(text, &LAYOUTS, 0.9);
+    assert_eq!(fixed.text, text);
+    assert_eq!(fixed.segments[0].decision, Decision::Uncertain);
+
+    // With no threshold at all, the whole sentence follows its detection
+    let fixed = document::fix(text, &LAYOUTS, 0.0);
+    assert_eq!(fixed.segments[0].decision, Decision::Converted);
+
+    let fixed = document::fix(" \n\n ", &LAYOUTS, 0.5);
+    assert!(fixed.segments.is_empty());
+    assert_eq!(fixed.text, " \n\n ");
+}
@@ -0,0 +1,51 @@
+// Practice drills for learning a layout
+use keymorph::detect::Language;
+use keymorph::drills::{self, DrillFocus};
+use keymorph::layouts::{self, LayoutCode};
+
+fn drill(focus: DrillFocus, lines: usize, seed: u64) -> Vec<String> {
+    drills::drill(
+        LayoutCode::Colemak,
+        LayoutCode::Qwerty,
+        focus,
+        Language::English,
+        lines,
+        seed,
+    )
+    .unwrap()
+    .lines
+}
+
+#[test]
+fn drills_follow_seed_focus_and_lines() {
+    let lines = drill(DrillFocus::HomeRow, 5, 42);
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines, drill(DrillFocus::HomeRow, 5, 42));
+    assert_ne!(lines, drill(DrillFocus::HomeRow, 5, 43));
+    assert_eq!(drill(DrillFocus::All, 3, 42).len(), 3);
+
+    // Home row lines only use what Colemak types on the home row
+    let home_row: Vec<char> = "asdfghjkl;'"
+        .chars()
+        .map(|key| layouts::char_at(LayoutCode::Colemak, key))
+        .collect();
+    for line in &lines {
+        assert!(!line.is_empty());
+        assert!(
+            line.chars().all(|c| c == ' ' || home_row.contains(&c)),
+            "{}",
+            line
+        );
+    }
+
+    // Nothing moves between a layout and itself
+    assert!(drills::drill(
+        LayoutCode::Qwerty,
+        LayoutCode::Qwerty,
+        DrillFocus::All,
+        Language::English,
+        5,
+        42
+    )
+    .is_err());
+}
@@ -0,0 +1,48 @@
+// Words changed by a conversion
+use keymorph::converter::Converter;
+use keymorph::edits::{self, Edit};
+use keymorph::layouts::LayoutCode;
+
+fn fields(edit: &Edit) -> (usize, usize, usize, usize, &str, &str) {
+    (
+        edit.start,
+        edit.end,
+        edit.line,
+        edit.column,
+        &edit.original,
+        &edit.replacement,
+    )
+}
+
+#[test]
+fn edits_give_lines_columns_and_char_offsets() {
+    let converter = Converter::builder()
+        .from(LayoutCode::Qwerty)
+        .to(LayoutCode::Russian)
+        .build()
+        .unwrap();
+    let edits = converter.convert_spans("ok ghbdtn\n  vbh");
+    let edits: Vec<_> = edits.iter().map(fields).collect();
+    assert_eq!(
+        edits,
+        [
+            (0, 2, 1, 1, "ok", "щл"),
+            (3, 9, 1, 4, "ghbdtn", "привет"),
+            (12, 15, 2, 3, "vbh", "мир"),
+        ]
+    );
+
+    // Offsets count characters, not the two bytes of each Cyrillic letter
+    let edits = edits::word_edits("мир ok привет", "мир ok ghbvtn");
+    let edits: Vec<_> = edits.iter().map(fields).collect();
+    assert_eq!(edits, [(7, 13, 1, 8, "привет", "ghbvtn")]);
+}
+
+#[test]
+fn texts_with_other_words_are_one_edit() {
+    let edits = edits::word_edits("a b\nc", "a bc");
+    let edits: Vec<_> = edits.iter().map(fields).collect();
+    assert_eq!(edits, [(0, 5, 1, 1, "a b\nc", "a bc")]);
+
+    assert!(edits::word_edits("same text", "same text").is_empty());
+}
@@ -0,0 +1,37 @@
+// Emacs key bindings between layouts
+use keymorph::emacs;
+use keymorph::layouts::LayoutCode;
+
+#[test]
+fn key_specs_keep_their_keys() {
+    let cases = [
+        (
+            "(global-set-key (kbd \"C-c p\") 'project-find-file)",
+            "(global-set-key (kbd \"C-с з\") 'project-find-file)",
+        ),
+        (
+            "(keymap-set map \"C-x f\" #'find-file)",
+            "(keymap-set map \"C-ч а\" #'find-file)",
+        ),
+        ("(kbd \"M-<f5> RET\")", "(kbd \"M-<f5> RET\")"),
+        (
+            "(use-package magit\n  :bind ((\"C-c g\" . magit-status)\n         (\"M-o\" . other-window)))",
+            "(use-package magit\n  :bind ((\"C-с п\" . magit-status)\n         (\"M-щ\" . other-window)))",
+        ),
+        // Old-style strings, vectors and comments are left alone
+        ("(kbd \"\\C-cp\")", "(kbd \"\\C-cp\")"),
+        ("(define-key map [?\\C-x ?f] 'find-file)", "(define-key map [?\\C-x ?f] 'find-file)"),
+        ("; (kbd \"C-c p\")", "; (kbd \"C-c p\")"),
+    ];
+    for (source, remapped) in cases {
+        assert_eq!(
+            emacs::remap_elisp(source, LayoutCode::Qwerty, LayoutCode::Russian),
+            remapped
+        );
+    }
+    // Keys typing a quote come out escaped
+    assert_eq!(
+        emacs::remap_elisp("(kbd \"C-с Э\")", LayoutCode::Russian, LayoutCode::Qwerty),
+        "(kbd \"C-c \\\"\")"
+    );
+}
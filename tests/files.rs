@@ -0,0 +1,47 @@
+// Converting files in windows
+use keymorph::files;
+use keymorph::layouts::LayoutCode;
+
+#[test]
+fn windows_end_at_line_breaks_or_characters() {
+    let windows = |text: &str, window| -> Vec<String> {
+        files::windows(text.as_bytes(), window)
+            .into_iter()
+            .map(|window| String::from_utf8(window.to_vec()).unwrap())
+            .collect()
+    };
+    assert_eq!(windows("ab\ncd\nef", 4), ["ab\n", "cd\n", "ef"]);
+    assert_eq!(windows("ab\ncdefg\n", 5), ["ab\n", "cdefg", "\n"]);
+    // Two-byte letters stay whole when a window has no line break
+    assert_eq!(windows("приве", 5), ["пр", "ив", "е"]);
+    assert_eq!(windows("abc", 3), ["abc"]);
+    assert!(windows("", 4).is_empty());
+}
+
+#[test]
+fn converts_files_in_place_keeping_their_mode() {
+    let dir = std::env::temp_dir().join(format!("keymorph-files-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let file = dir.join("text.txt");
+    std::fs::write(&file, "ghbdtn\nvbh\n").unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&file, std::fs::Permissions::from_mode(0o640)).unwrap();
+    }
+    files::convert_file(&file, &file, LayoutCode::Qwerty, LayoutCode::Russian).unwrap();
+    assert_eq!(std::fs::read_to_string(&file).unwrap(), "привет\nмир\n");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+
+    let empty = dir.join("empty.txt");
+    std::fs::write(&empty, "").unwrap();
+    let out = dir.join("out.txt");
+    files::convert_file(&empty, &out, LayoutCode::Qwerty, LayoutCode::Russian).unwrap();
+    assert_eq!(std::fs::read_to_string(&out).unwrap(), "");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
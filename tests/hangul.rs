@@ -0,0 +1,38 @@
+// Hangul syllable composition on the dubeolsik layout
+use keymorph::hangul;
+use keymorph::layouts::{self, LayoutCode};
+
+fn korean(keys: &str) -> String {
+    layouts::convert_text(keys, LayoutCode::Qwerty, LayoutCode::Korean)
+}
+
+#[test]
+fn composes_syllables_from_jamo() {
+    assert_eq!(hangul::compose("ㅇㅏㄴㄴㅕㅇ"), "안녕");
+    assert_eq!(korean("dkssud"), "안녕");
+    // Compound vowels, alone and in a syllable
+    assert_eq!(hangul::compose("ㅗㅏ"), "ㅘ");
+    assert_eq!(korean("dhk"), "와");
+    // Jamo that start no syllable are kept
+    assert_eq!(hangul::compose("ㅋㅋㅋ"), "ㅋㅋㅋ");
+    assert_eq!(korean("dkssud, g"), "안녕, ㅎ");
+}
+
+#[test]
+fn double_finals_split_before_a_vowel() {
+    assert_eq!(korean("ekfr"), "닭");
+    // The second consonant of the final starts the next syllable
+    assert_eq!(korean("ekfrdl"), "닭이");
+    assert_eq!(korean("ekfrl"), "달기");
+    assert_eq!(korean("dksl"), "아니");
+}
+
+#[test]
+fn decomposes_into_keystrokes() {
+    assert_eq!(hangul::decompose("닭이"), "ㄷㅏㄹㄱㅇㅣ");
+    assert_eq!(hangul::decompose("ㅘ"), "ㅗㅏ");
+    for text in ["안녕하세요", "닭이", "와", "읽었다"] {
+        let keys = layouts::convert_text(text, LayoutCode::Korean, LayoutCode::Qwerty);
+        assert_eq!(korean(&keys), text, "{}", keys);
+    }
+}
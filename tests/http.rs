@@ -0,0 +1,118 @@
+// The HTTP API, served in-process
+use actix_web::{test, web, App};
+use keymorph::config::Config;
+use keymorph::http::{self, AppState};
+use serde_json::{json, Value};
+
+// Sends `request` for `path` under `/api`, returning the status and the
+// response
+async fn call(request: test::TestRequest, path: &str) -> (u16, Value) {
+    let state = web::Data::new(AppState::new(&Config::default()));
+    let app = test::init_service(App::new().service(http::scope("/api", state))).await;
+    let request = request.uri(&format!("/api{}", path)).to_request();
+    let response = test::call_service(&app, request).await;
+    let status = response.status().as_u16();
+    (status, test::read_body_json(response).await)
+}
+
+async fn post(path: &str, body: Value) -> (u16, Value) {
+    call(test::TestRequest::post().set_json(body), path).await
+}
+
+async fn get(path: &str) -> (u16, Value) {
+    call(test::TestRequest::get(), path).await
+}
+
+#[actix_web::test]
+async fn layout_ids_take_variants_everywhere() {
+    let (status, body) = post(
+        "/analyze",
+        json!({"text": "hello", "layout": "latvian?variant=ergonomic"}),
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert_eq!(body["data"]["layout"], "latvian-ergonomic");
+
+    let (status, body) = post(
+        "/analyze/compare",
+        json!({"text": "hello", "layouts": ["qwerty", "dvorak?variant=programmer"]}),
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert_eq!(body["data"]["layouts"].as_array().unwrap().len(), 2);
+}
+
+#[actix_web::test]
+async fn conversion_options_only_apply_to_convert() {
+    let (status, body) = post(
+        "/convert",
+        json!({"text": "`", "from": "qwerty", "to": "russian?yo=false"}),
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert_eq!(body["data"], "е");
+
+    for (path, body) in [
+        (
+            "/analyze",
+            json!({"text": "hello", "layout": "russian?yo=keep"}),
+        ),
+        (
+            "/shortcuts",
+            json!({"shortcuts": ["ctrl+c"], "from": "qwerty", "to": "russian?yo=keep"}),
+        ),
+        (
+            "/document",
+            json!({"text": "ghbdtn", "layouts": ["qwerty", "russian?yo=keep"]}),
+        ),
+    ] {
+        let (status, body) = post(path, body).await;
+        assert_eq!(status, 400, "{}", path);
+        assert!(
+            body["message"]
+                .as_str()
+                .unwrap()
+                .contains("only apply to /convert"),
+            "{}",
+            path
+        );
+    }
+
+    let (status, body) = get("/layouts/russian?yo=false").await;
+    assert_eq!(status, 400);
+    assert_eq!(
+        body["message"],
+        "Layout options only apply to /convert, not to 'russian?yo=false'."
+    );
+    let (status, body) = get("/layouts/russian").await;
+    assert_eq!(status, 200);
+    assert_eq!(body["data"]["name"], "russian");
+
+    let (status, body) = post("/analyze", json!({"text": "hello", "layout": "nope"})).await;
+    assert_eq!(status, 400);
+    assert_eq!(body["message"], "Invalid layout code provided.");
+}
+
+#[actix_web::test]
+async fn conversions_return_spans() {
+    let (status, body) = post(
+        "/convert",
+        json!({"text": "ok\nghbdtn", "from": "qwerty", "to": "russian", "format": "spans"}),
+    )
+    .await;
+    assert_eq!(status, 200);
+    assert_eq!(
+        body["data"],
+        json!([
+            {"start": 0, "end": 2, "line": 1, "column": 1, "original": "ok", "replacement": "щл"},
+            {
+                "start": 3,
+                "end": 9,
+                "line": 2,
+                "column": 1,
+                "original": "ghbdtn",
+                "replacement": "привет"
+            }
+        ])
+    );
+}
@@ -0,0 +1,56 @@
+// KLC exports of every layout
+use keymorph::klc;
+use keymorph::layouts::{self, LayoutCode};
+use keymorph::registry::Layout;
+use std::collections::HashSet;
+
+// A character cell of the LAYOUT section
+fn parse_cell(cell: &str) -> char {
+    match cell.chars().count() {
+        1 => cell.chars().next().unwrap(),
+        _ => char::from_u32(u32::from_str_radix(cell, 16).unwrap()).unwrap(),
+    }
+}
+
+#[test]
+fn exports_every_key_once() {
+    for code in LayoutCode::ALL {
+        let exported = klc::export(&Layout::Builtin(code));
+        let rows: Vec<Vec<&str>> = exported
+            .split("LAYOUT")
+            .nth(1)
+            .unwrap()
+            .split("KEYNAME")
+            .next()
+            .unwrap()
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with(['/', '\t']))
+            .map(|line| line.split('\t').filter(|cell| !cell.is_empty()).collect())
+            .collect();
+        // The main block, space and the numpad decimal key
+        assert_eq!(rows.len(), 49, "{}", code.as_str());
+        let scan_codes: HashSet<&str> = rows.iter().map(|row| row[0]).collect();
+        let virtual_keys: HashSet<&str> = rows.iter().map(|row| row[1]).collect();
+        assert_eq!(scan_codes.len(), rows.len(), "{}", code.as_str());
+        assert_eq!(virtual_keys.len(), rows.len(), "{}", code.as_str());
+
+        let typed: Vec<(char, char)> = rows
+            .iter()
+            .take(47)
+            .map(|row| (parse_cell(row[3]), parse_cell(row[4])))
+            .collect();
+        let expected: Vec<(char, char)> = layouts::shift_pairs()
+            .map(|(lower, upper)| (layouts::char_at(code, lower), layouts::char_at(code, upper)))
+            .collect();
+        assert_eq!(typed, expected, "{}", code.as_str());
+    }
+}
+
+#[test]
+fn encodes_as_utf16_with_crlf() {
+    let bytes = klc::to_utf16("KBD\tx\n");
+    assert_eq!(
+        bytes,
+        [0xFF, 0xFE, b'K', 0, b'B', 0, b'D', 0, b'\t', 0, b'x', 0, b'\r', 0, b'\n', 0]
+    );
+}
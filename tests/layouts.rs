@@ -0,0 +1,91 @@
+// Built-in layout maps
+use keymorph::layouts::{self, CasePolicy, LayoutCode, LayoutId, LayoutOptions, YoPolicy};
+use keymorph::registry::{self, Layout};
+use std::str::FromStr;
+
+#[test]
+fn colemak_types_every_letter_on_one_key() {
+    // Regressions from the old Colemak map, which sent P from the semicolon
+    // key, swapped the quote and minus keys and had no O or K
+    assert_eq!(layouts::char_at(LayoutCode::Colemak, ';'), 'o');
+    assert_eq!(layouts::char_at(LayoutCode::Colemak, ':'), 'O');
+    assert_eq!(layouts::char_at(LayoutCode::Colemak, 'n'), 'k');
+    assert_eq!(layouts::char_at(LayoutCode::Colemak, 'p'), ';');
+    assert_eq!(layouts::char_at(LayoutCode::Colemak, '\''), '\'');
+    assert_eq!(layouts::char_at(LayoutCode::Colemak, '-'), '-');
+
+    for (unshifted, shifted) in layouts::shift_pairs().filter(|(key, _)| key.is_alphabetic()) {
+        for letter in [unshifted, shifted] {
+            let keys = layouts::shift_pairs()
+                .flat_map(|(unshifted, shifted)| [unshifted, shifted])
+                .filter(|&key| layouts::char_at(LayoutCode::Colemak, key) == letter)
+                .count();
+            assert_eq!(keys, 1, "{}", letter);
+        }
+    }
+}
+
+#[test]
+fn layout_ids_take_variants_and_options() {
+    let id = LayoutId::from_str("latvian?variant=ergonomic").unwrap();
+    assert_eq!(id.layout, LayoutCode::LatvianErgonomic);
+    // The variant may also be the prefix of the name
+    let id = LayoutId::from_str("dvorak?variant=programmer").unwrap();
+    assert_eq!(id.layout, LayoutCode::ProgrammerDvorak);
+
+    let id = LayoutId::from_str("Russian?yo=false&digit_symbols=1&case=sentence").unwrap();
+    assert_eq!(id.layout, LayoutCode::Russian);
+    assert_eq!(
+        id.options,
+        LayoutOptions {
+            yo_policy: Some(YoPolicy::FoldToE),
+            digit_symbols: Some(true),
+            keep_punctuation: None,
+            case_policy: Some(CasePolicy::Sentence),
+        }
+    );
+    assert_eq!(
+        LayoutId::from_str("russian?yo=true")
+            .unwrap()
+            .options
+            .yo_policy,
+        Some(YoPolicy::Keep)
+    );
+    assert_eq!(
+        LayoutId::from_str("russian?").unwrap().options,
+        LayoutOptions::default()
+    );
+
+    // Options given on the target win over those on the source
+    let from = LayoutId::from_str("qwerty?keep_punctuation=true&yo=keep").unwrap();
+    let to = LayoutId::from_str("russian?yo=false").unwrap();
+    let options = to.options.or(from.options);
+    assert_eq!(options.yo_policy, Some(YoPolicy::FoldToE));
+    assert_eq!(options.keep_punctuation, Some(true));
+}
+
+#[test]
+fn rejects_malformed_layout_ids() {
+    for id in [
+        "nope",
+        "russian?variant=ergonomic",
+        "russian?colour=red",
+        "russian?yo",
+        "russian?yo=maybe",
+        "russian?digit_symbols=yes",
+        "russian?case=sideways",
+    ] {
+        assert!(LayoutId::from_str(id).is_err(), "{}", id);
+        assert!(registry::resolve(id).is_none(), "{}", id);
+    }
+    // A bare layout code has no room for conversion options
+    assert!(LayoutCode::from_str("russian?yo=keep").is_err());
+    assert_eq!(
+        LayoutCode::from_str("latvian?variant=ergonomic"),
+        Ok(LayoutCode::LatvianErgonomic)
+    );
+
+    let (layout, options) = registry::resolve_with_options("russian?yo=fold_to_e").unwrap();
+    assert!(matches!(layout, Layout::Builtin(LayoutCode::Russian)));
+    assert_eq!(options.yo_policy, Some(YoPolicy::FoldToE));
+}
@@ -0,0 +1,71 @@
+// Shortcut profiles between layouts
+use keymorph::layouts::LayoutCode;
+use keymorph::profiles::{self, ProfileFormat, DEFAULT_FIELDS};
+
+fn remap(profile: &str, format: ProfileFormat, fields: &[&str]) -> String {
+    profiles::remap_profile(
+        profile,
+        format,
+        LayoutCode::Qwerty,
+        LayoutCode::Dvorak,
+        fields,
+    )
+    .unwrap()
+}
+
+#[test]
+fn profiles_keep_their_shape_and_order() {
+    let json = r#"{
+  "zoom": {
+    "Key": "W",
+    "name": "Walk"
+  },
+  "actions": [
+    {
+      "KEYS": [
+        "Shift+S",
+        "F5"
+      ]
+    }
+  ],
+  "alpha": "Q"
+}
+"#;
+    let remapped = r#"{
+  "zoom": {
+    "Key": ",",
+    "name": "Walk"
+  },
+  "actions": [
+    {
+      "KEYS": [
+        "Shift+O",
+        "F5"
+      ]
+    }
+  ],
+  "alpha": "Q"
+}
+"#;
+    assert_eq!(remap(json, ProfileFormat::Json, &DEFAULT_FIELDS), remapped);
+
+    let yaml = "zoom:\n  Key: W\n  name: Walk\nbinds:\n  forward: Ctrl+E\nalpha: Q\n";
+    assert_eq!(
+        remap(yaml, ProfileFormat::Yaml, &DEFAULT_FIELDS),
+        "zoom:\n  Key: ','\n  name: Walk\nbinds:\n  forward: Ctrl+E\nalpha: Q\n"
+    );
+    // Fields given by name replace the default ones
+    assert_eq!(
+        remap(yaml, ProfileFormat::Yaml, &["Forward", "alpha"]),
+        "zoom:\n  Key: W\n  name: Walk\nbinds:\n  forward: Ctrl+.\nalpha: ''''\n"
+    );
+
+    assert!(profiles::remap_profile(
+        "{",
+        ProfileFormat::Json,
+        LayoutCode::Qwerty,
+        LayoutCode::Dvorak,
+        &DEFAULT_FIELDS
+    )
+    .is_err());
+}
@@ -0,0 +1,119 @@
+// Randomized properties of conversion. Round trips only hold for layouts
+// that type each character on one key; the others lose something on the way
+// back (case in caseless scripts, dead keys composing letters the layout
+// also has a key for) and are only checked for not panicking.
+use keymorph::converter::Converter;
+use keymorph::layouts::{self, LayoutCode};
+use proptest::prelude::*;
+
+const REVERSIBLE: [LayoutCode; 38] = [
+    LayoutCode::Qwerty,
+    LayoutCode::Dvorak,
+    LayoutCode::Colemak,
+    LayoutCode::Russian,
+    LayoutCode::German,
+    LayoutCode::Azerty,
+    LayoutCode::Bepo,
+    LayoutCode::Spanish,
+    LayoutCode::Abnt2,
+    LayoutCode::Norwegian,
+    LayoutCode::Danish,
+    LayoutCode::Uk,
+    LayoutCode::ProgrammerDvorak,
+    LayoutCode::DvorakLeft,
+    LayoutCode::DvorakRight,
+    LayoutCode::ColemakDh,
+    LayoutCode::Norman,
+    LayoutCode::Halmak,
+    LayoutCode::Engram,
+    LayoutCode::Ukrainian,
+    LayoutCode::Kazakh,
+    LayoutCode::Bulgarian,
+    LayoutCode::BulgarianPhonetic,
+    LayoutCode::Macedonian,
+    LayoutCode::Persian,
+    LayoutCode::Turkish,
+    LayoutCode::Armenian,
+    LayoutCode::Thai,
+    LayoutCode::Hindi,
+    LayoutCode::Polish,
+    LayoutCode::Hungarian,
+    LayoutCode::Romanian,
+    LayoutCode::Lithuanian,
+    LayoutCode::Latvian,
+    LayoutCode::LatvianErgonomic,
+    LayoutCode::Mongolian,
+    LayoutCode::RussianTypewriter,
+    LayoutCode::RussianMac,
+];
+
+fn layout() -> impl Strategy<Value = LayoutCode> {
+    prop::sample::select(LayoutCode::ALL.to_vec())
+}
+
+fn reversible() -> impl Strategy<Value = LayoutCode> {
+    prop::sample::select(REVERSIBLE.to_vec())
+}
+
+// Text typed on `layout` with the keys of the main block, spaces and line
+// breaks
+fn typed(layout: LayoutCode) -> impl Strategy<Value = String> {
+    let keys: Vec<char> = layouts::shift_pairs()
+        .flat_map(|(lower, upper)| [lower, upper])
+        .chain([' ', '\n'])
+        .collect();
+    prop::collection::vec(prop::sample::select(keys), 0..64).prop_map(move |keys| {
+        let keys: String = keys.into_iter().collect();
+        layouts::convert_text(&keys, LayoutCode::Qwerty, layout)
+    })
+}
+
+proptest! {
+    #[test]
+    fn converts_any_text(text in any::<String>(), from in layout(), to in layout()) {
+        layouts::convert_text(&text, from, to);
+        layouts::parallel_convert_text(&text, from, to);
+    }
+
+    #[test]
+    fn converts_any_text_with_options(
+        text in any::<String>(),
+        from in layout(),
+        to in layout(),
+        strict in any::<bool>(),
+        skip_urls in any::<bool>(),
+    ) {
+        let converter = Converter::builder()
+            .from(from)
+            .to(to)
+            .strict(strict)
+            .skip_urls(skip_urls)
+            .build()
+            .unwrap();
+        converter.convert(&text);
+    }
+
+    #[test]
+    fn converts_any_bytes_in_place(bytes in any::<Vec<u8>>(), from in layout(), to in layout()) {
+        let mut converted = bytes.clone();
+        match layouts::convert_bytes_in_place(&mut converted, from, to) {
+            Ok(()) => {
+                let text = std::str::from_utf8(&bytes).unwrap();
+                prop_assert_eq!(
+                    std::str::from_utf8(&converted).unwrap(),
+                    layouts::convert_text(text, from, to)
+                );
+            }
+            Err(_) => prop_assert_eq!(converted, bytes),
+        }
+    }
+
+    #[test]
+    fn round_trips(
+        (from, text) in reversible().prop_flat_map(|from| (Just(from), typed(from))),
+        to in reversible(),
+    ) {
+        let converted = layouts::convert_text(&text, from, to);
+        prop_assert_eq!(layouts::convert_text(&converted, to, from), text);
+    }
+}
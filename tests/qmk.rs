@@ -0,0 +1,72 @@
+// QMK keymaps of every layout on every board
+use keymorph::firmware::{self, Binding, Board, Exporter, Slot};
+use keymorph::layouts::{self, LayoutCode};
+use keymorph::qmk::{self, Qmk};
+use keymorph::registry::Layout;
+
+#[test]
+fn keymaps_type_the_layout_on_the_host() {
+    for board in Board::ALL {
+        for code in LayoutCode::ALL {
+            let layout = Layout::Builtin(code);
+            let keymap = firmware::keymap(&layout, board, LayoutCode::Qwerty);
+            let mut untypeable = 0;
+            for (slots, bindings) in board.slots().iter().zip(&keymap.rows) {
+                assert_eq!(slots.len(), bindings.len(), "{}", board.as_str());
+                for (&slot, &binding) in slots.iter().zip(bindings) {
+                    let Slot::Key(key) = slot else {
+                        continue;
+                    };
+                    match binding {
+                        Binding::Key { key: sent, shift } => {
+                            let sent = if shift {
+                                layouts::shift_pairs()
+                                    .find(|&(lower, _)| lower == sent)
+                                    .unwrap()
+                                    .1
+                            } else {
+                                sent
+                            };
+                            assert_eq!(
+                                sent,
+                                layouts::char_at(code, key),
+                                "{} on {}",
+                                key,
+                                code.as_str()
+                            );
+                        }
+                        Binding::None => untypeable += 1,
+                        Binding::Special(_) => panic!("{} is bound to a special key", key),
+                    }
+                }
+            }
+            assert!(
+                keymap.problems.len() >= untypeable,
+                "{} on {}",
+                code.as_str(),
+                board.as_str()
+            );
+        }
+    }
+}
+
+#[test]
+fn writes_keymaps() {
+    let layout = Layout::Builtin(LayoutCode::Qwerty);
+    let keymap = firmware::keymap(&layout, Board::Ansi60, LayoutCode::Qwerty);
+    assert!(keymap.problems.is_empty());
+
+    let c = Qmk::KeymapC.export(&layout, &keymap);
+    assert!(c.contains("[0] = LAYOUT_60_ansi(\n        KC_GRV, KC_1, KC_2,"));
+    let json: serde_json::Value =
+        serde_json::from_str(&Qmk::KeymapJson.export(&layout, &keymap)).unwrap();
+    assert_eq!(json["keyboard"], "gh60/satan");
+    assert_eq!(json["layers"][0].as_array().unwrap().len(), 61);
+    assert_eq!(json["layers"][0][15], "KC_Q");
+
+    let dvorak = Layout::Builtin(LayoutCode::Dvorak);
+    let keymap = firmware::keymap(&dvorak, Board::Corne, LayoutCode::Qwerty);
+    assert!(keymap.problems.is_empty());
+    assert_eq!(qmk::keycode(keymap.rows[0][1]), "KC_QUOT");
+    assert_eq!(qmk::keycode(keymap.rows[1][2]), "KC_O");
+}
@@ -0,0 +1,59 @@
+// kmonad and kanata configurations of every layout
+use keymorph::firmware::{self, Board, Exporter};
+use keymorph::layouts::LayoutCode;
+use keymorph::registry::Layout;
+use keymorph::remapper::Remapper;
+
+// The keys of a `defsrc` or `deflayer` block
+fn block<'a>(config: &'a str, name: &str) -> Vec<&'a str> {
+    config
+        .split(&format!("({}", name))
+        .nth(1)
+        .unwrap()
+        .split(')')
+        .next()
+        .unwrap()
+        .split_whitespace()
+        .collect()
+}
+
+#[test]
+fn remaps_the_main_block() {
+    for remapper in [Remapper::Kmonad, Remapper::Kanata] {
+        for code in LayoutCode::ALL {
+            let layout = Layout::Builtin(code);
+            let keymap = firmware::keymap(&layout, Board::Ansi60, LayoutCode::Qwerty);
+            let config = remapper.export(&layout, &keymap);
+            assert_eq!(block(&config, "defsrc").len(), 47, "{}", code.as_str());
+            // The layer is named after the block, `keymorph`
+            assert_eq!(block(&config, "deflayer").len(), 48, "{}", code.as_str());
+        }
+    }
+}
+
+#[test]
+fn remaps_between_layouts() {
+    let layout = Layout::Builtin(LayoutCode::Colemak);
+    let keymap = firmware::keymap(&layout, Board::Ansi60, LayoutCode::Qwerty);
+    let config = Remapper::Kanata.export(&layout, &keymap);
+    let source = block(&config, "defsrc");
+    let layer = block(&config, "deflayer");
+    let sent = |key: &str| layer[source.iter().position(|&k| k == key).unwrap() + 1];
+    assert_eq!(source[0], "grv");
+    assert_eq!(sent("e"), "f");
+    assert_eq!(sent(";"), "o");
+    assert_eq!(sent("p"), ";");
+
+    // Typing QWERTY on a computer set to Colemak puts every key back
+    let qwerty = Layout::Builtin(LayoutCode::Qwerty);
+    let keymap = firmware::keymap(&qwerty, Board::Ansi60, LayoutCode::Colemak);
+    let config = Remapper::Kmonad.export(&qwerty, &keymap);
+    let source = block(&config, "defsrc");
+    let layer = block(&config, "deflayer");
+    assert_eq!(
+        layer[1 + source.iter().position(|&k| k == "f").unwrap()],
+        "e"
+    );
+    assert!(Remapper::Kmonad.supports(Board::Ansi60));
+    assert!(!Remapper::Kanata.supports(Board::Corne));
+}
@@ -0,0 +1,31 @@
+// Keyboard shortcuts between layouts
+use keymorph::layouts::LayoutCode;
+use keymorph::shortcuts::{self, KeySemantics};
+
+#[test]
+fn shortcuts_keep_their_notation() {
+    let remap = |shortcut, to, semantics| {
+        shortcuts::remap_shortcut(shortcut, LayoutCode::Qwerty, to, semantics)
+    };
+    let cases = [
+        // Dvorak types every character of these shortcuts somewhere else
+        ("Ctrl+T", LayoutCode::Dvorak, "Ctrl+T", "Ctrl+Y"),
+        ("Ctrl++", LayoutCode::Dvorak, "Ctrl++", "Ctrl+}"),
+        ("Ctrl+-", LayoutCode::Dvorak, "Ctrl+-", "Ctrl+["),
+        ("C-x C-f", LayoutCode::Dvorak, "C-x C-f", "C-q C-u"),
+        ("⌘⇧T", LayoutCode::Dvorak, "⌘⇧T", "⌘⇧Y"),
+        (
+            "Ctrl+Shift+F5",
+            LayoutCode::Dvorak,
+            "Ctrl+Shift+F5",
+            "Ctrl+Shift+F5",
+        ),
+        // Russian types no Latin letters, which fall back to their keys
+        ("Ctrl+T", LayoutCode::Russian, "Ctrl+Е", "Ctrl+Е"),
+        ("C-x C-f", LayoutCode::Russian, "C-ч C-а", "C-ч C-а"),
+    ];
+    for (shortcut, to, character, position) in cases {
+        assert_eq!(remap(shortcut, to, KeySemantics::Character), character);
+        assert_eq!(remap(shortcut, to, KeySemantics::Position), position);
+    }
+}
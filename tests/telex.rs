@@ -0,0 +1,41 @@
+// Vietnamese Telex composition
+use keymorph::telex;
+
+#[test]
+fn tones_land_on_the_right_vowel() {
+    assert_eq!(telex::compose("Vieetj Nam"), "Việt Nam");
+    // The tone key may come before the modifier it ends up with
+    assert_eq!(telex::compose("tieesng"), "tiếng");
+    assert_eq!(telex::compose("tiesen"), "tiến");
+    // First of two vowels, the middle of three, the last before a consonant
+    assert_eq!(telex::compose("hoaf"), "hòa");
+    assert_eq!(telex::compose("khuyux"), "khuỹu");
+    assert_eq!(telex::compose("toans"), "toán");
+    // The u of qu and the i of gi belong to the consonant
+    assert_eq!(telex::compose("quas"), "quá");
+    assert_eq!(telex::compose("gias"), "giá");
+    // uo takes the horn on both letters, and the tone on the ơ
+    assert_eq!(telex::compose("nguowif"), "người");
+    assert_eq!(telex::compose("VIEETJ"), "VIỆT");
+}
+
+#[test]
+fn repeated_modifiers_undo_themselves() {
+    assert_eq!(telex::compose("ass"), "as");
+    assert_eq!(telex::compose("aaa"), "aa");
+    assert_eq!(telex::compose("ddi"), "đi");
+    assert_eq!(telex::compose("dddi"), "ddi");
+    assert_eq!(telex::compose("asz"), "a");
+    assert_eq!(telex::compose("w"), "ư");
+    // Only letters are composed
+    assert_eq!(telex::compose("as, 42"), "á, 42");
+}
+
+#[test]
+fn decompose_spells_tones_at_the_end() {
+    assert_eq!(telex::decompose("Việt Nam"), "Vieetj Nam");
+    assert_eq!(telex::decompose("người"), "nguwowif");
+    for text in ["Tiếng Việt", "người", "hòa bình", "ĐẸP", "quá"] {
+        assert_eq!(telex::compose(&telex::decompose(text)), text);
+    }
+}
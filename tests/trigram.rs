@@ -0,0 +1,52 @@
+// The character trigram models and the detection backends built on them
+use keymorph::detect::{self, Backend, Language};
+use keymorph::layouts::LayoutCode;
+use keymorph::trigram;
+
+#[test]
+fn words_score_above_keyboard_mash() {
+    let english = trigram::model(Language::English);
+    let russian = trigram::model(Language::Russian);
+    for word in ["the", "hello", "Brandon"] {
+        assert!(english.score(word) > 0.5, "{}", word);
+        assert_eq!(russian.score(word), 0.0, "{}", word);
+    }
+    for word in ["привет", "Москва"] {
+        assert!(russian.score(word) > 0.5, "{}", word);
+        assert_eq!(english.score(word), 0.0, "{}", word);
+    }
+    // Russian typed on QWERTY and mash look like neither language
+    for word in ["ghbdtn", "qxzvkj"] {
+        assert_eq!(english.score(word), 0.0, "{}", word);
+    }
+    assert_eq!(russian.score("щшщшщ"), 0.0);
+    assert_eq!(english.score("HELLO"), english.score("hello"));
+}
+
+// The backend is global, so this is the only test switching it
+#[test]
+fn backends_score_words_the_dictionaries_miss() {
+    let layouts = [LayoutCode::Qwerty, LayoutCode::Russian];
+    let trigram = trigram::model(Language::English).score("Brandon");
+    assert_eq!(detect::backend(), Backend::Dictionary);
+    assert_eq!(detect::word_score("Brandon", LayoutCode::Qwerty), Some(0.4));
+    assert_eq!(detect::fix_word("Vjcrdf", &layouts), None);
+
+    detect::set_backend(Backend::Trigram);
+    assert_eq!(
+        detect::word_score("Brandon", LayoutCode::Qwerty),
+        Some(trigram)
+    );
+    assert_eq!(
+        detect::fix_word("Vjcrdf", &layouts).as_deref(),
+        Some("Москва")
+    );
+
+    detect::set_backend(Backend::Blend);
+    assert_eq!(
+        detect::word_score("Brandon", LayoutCode::Qwerty),
+        Some((0.4 + trigram) / 2.0)
+    );
+    assert!(detect::word_score("house", LayoutCode::Qwerty).unwrap() > 0.5);
+    detect::set_backend(Backend::Dictionary);
+}
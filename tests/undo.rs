@@ -0,0 +1,71 @@
+// Undo tokens
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use keymorph::layouts::{LayoutCode, YoPolicy};
+use keymorph::registry::{self, ConvertOptions, Layout};
+use keymorph::undo;
+
+fn convert(text: &str, options: &ConvertOptions) -> String {
+    registry::convert_text_with(
+        text,
+        &Layout::Builtin(LayoutCode::Qwerty),
+        &Layout::Builtin(LayoutCode::Russian),
+        options,
+    )
+}
+
+#[test]
+fn tokens_restore_the_original_text() {
+    for original in ["ghbdtn vbh", "Ghbdtn, vbh!", "", "hello 123"] {
+        let converted = convert(original, &ConvertOptions::default());
+        let token = undo::token(original, &converted, "qwerty", "russian").unwrap();
+        assert_eq!(undo::undo(&converted, &token).unwrap(), original);
+    }
+
+    // Folding ё loses the key it was typed on, so the token keeps the word
+    let options = ConvertOptions {
+        yo_policy: YoPolicy::FoldToE,
+        ..Default::default()
+    };
+    let original = "`krf b ktc";
+    let converted = convert(original, &options);
+    assert_eq!(converted, "елка и лес");
+    let token = undo::token(original, &converted, "qwerty", "russian").unwrap();
+    assert_eq!(undo::undo(&converted, &token).unwrap(), original);
+}
+
+#[test]
+fn rejects_edited_text_and_tampered_tokens() {
+    let original = "ghbdtn vbh";
+    let converted = convert(original, &ConvertOptions::default());
+    let token = undo::token(original, &converted, "qwerty", "russian").unwrap();
+    assert_eq!(
+        undo::undo("привет", &token).unwrap_err(),
+        "the text has changed since it was converted"
+    );
+
+    let encoded = |json: &str| format!("1.{}", URL_SAFE_NO_PAD.encode(json));
+    for token in [
+        "",
+        "1.",
+        "1.not base64!",
+        &encoded("{}"),
+        &token.replacen('1', "2", 1),
+        // Spans out of order or past the end of the text
+        &encoded(r#"{"from":"qwerty","to":"russian","len":10,"spans":[[7,10,null],[0,6,null]]}"#),
+        &encoded(r#"{"from":"qwerty","to":"russian","len":10,"spans":[[7,11,null]]}"#),
+    ] {
+        assert_eq!(
+            undo::undo(&converted, token).unwrap_err(),
+            "invalid undo token",
+            "{}",
+            token
+        );
+    }
+
+    let unknown = encoded(r#"{"from":"nope","to":"russian","len":10,"spans":[[0,6,null]]}"#);
+    assert_eq!(
+        undo::undo(&converted, &unknown).unwrap_err(),
+        "unknown layout 'nope'"
+    );
+}
@@ -0,0 +1,40 @@
+// Vim mappings between layouts
+use keymorph::layouts::LayoutCode;
+use keymorph::vim;
+
+#[test]
+fn vimrc_mappings_keep_their_keys() {
+    let cases = [
+        // Attributes, with or without spaces, are not keys
+        (
+            "imap <buffer><silent>jk <Esc>",
+            "imap <buffer><silent>ол <Esc>",
+        ),
+        ("nnoremap <silent> jk :w<CR>", "nnoremap <silent> ол :w<CR>"),
+        // Keys after modifiers, with Ctrl ignoring Shift
+        ("nnoremap <C-w>h <C-w>h", "nnoremap <C-ц>р <C-w>h"),
+        ("nmap <C-W>x :q<CR>", "nmap <C-ц>ч :q<CR>"),
+        ("nmap <leader>f :Files<CR>", "nmap <leader>а :Files<CR>"),
+        // Characters written by name, and escaped bars
+        ("nmap <lt>x <Nop>", "nmap Бч <Nop>"),
+        ("nmap a\\|b :echo<CR>", "nmap ф\\|и :echo<CR>"),
+        ("nun ; ", "nun ж "),
+        // Leader keys
+        ("let mapleader = \",\"", "let mapleader = \"б\""),
+        ("let g:maplocalleader=';'", "let g:maplocalleader='ж'"),
+        // Everything else is left alone
+        ("\" nmap jk <Esc>", "\" nmap jk <Esc>"),
+        ("set number", "set number"),
+    ];
+    for (line, remapped) in cases {
+        assert_eq!(
+            vim::remap_vimrc(line, LayoutCode::Qwerty, LayoutCode::Russian),
+            remapped
+        );
+    }
+    let vimrc = "let mapleader = \",\"\r\nnmap jk <Esc>\n";
+    assert_eq!(
+        vim::remap_vimrc(vimrc, LayoutCode::Qwerty, LayoutCode::Russian),
+        "let mapleader = \"б\"\r\nnmap ол <Esc>\n"
+    );
+}
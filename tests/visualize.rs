@@ -0,0 +1,34 @@
+// Keyboard heatmaps
+use keymorph::layouts::LayoutCode;
+use keymorph::visualize::{self, HeatScale, Overlay};
+
+// Fill of the key typing `label`, as its red, green and blue channels
+fn fill(svg: &str, label: &str) -> (u8, u8, u8) {
+    let label = format!(">{}</text>", label);
+    let before = &svg[..svg.find(&label).unwrap()];
+    let fill = before.rsplit("fill=\"#").next().unwrap();
+    let channel = |i: usize| u8::from_str_radix(&fill[i..i + 2], 16).unwrap();
+    (channel(0), channel(2), channel(4))
+}
+
+#[test]
+fn log_scale_tells_rare_keys_apart() {
+    let text = format!("{}q", "e".repeat(100));
+    let presses = visualize::key_presses(&text, LayoutCode::Qwerty);
+    let linear = visualize::svg_keyboard(
+        LayoutCode::Qwerty,
+        &Overlay::Heatmap(presses.clone(), HeatScale::Linear),
+    );
+    let log = visualize::svg_keyboard(
+        LayoutCode::Qwerty,
+        &Overlay::Heatmap(presses, HeatScale::Log),
+    );
+
+    // The most pressed key is as dark either way, and unpressed keys white
+    assert_eq!(fill(&linear, "e"), fill(&log, "e"));
+    assert_eq!(fill(&linear, "w"), (255, 255, 255));
+    assert_eq!(fill(&log, "w"), (255, 255, 255));
+    // A key pressed once in a hundred barely shows on the linear scale
+    assert!(fill(&linear, "q").1 > 250);
+    assert!(fill(&log, "q").1 < 230);
+}
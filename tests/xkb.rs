@@ -0,0 +1,103 @@
+// Round trips of layouts through XKB symbols files
+use keymorph::layouts::{self, LayoutCode};
+use keymorph::registry::{self, Layout};
+use keymorph::{schema, xkb};
+
+// The `key <...>` lines of a symbols file, without the AltGr levels a
+// custom layout does not have
+fn key_lines(symbols: &str) -> Vec<String> {
+    symbols
+        .lines()
+        .filter(|line| line.trim_start().starts_with("key <"))
+        .map(|line| {
+            let levels: Vec<&str> = line.split(", ").collect();
+            levels[..2].join(", ").trim_end_matches(" ] };").to_string()
+        })
+        .collect()
+}
+
+#[test]
+fn imports_every_exported_layout() {
+    for code in LayoutCode::ALL {
+        let symbols = xkb::export(&Layout::Builtin(code));
+        let file = xkb::import(&symbols).unwrap();
+        assert_eq!(file.name, code.as_str());
+        for (lower, upper) in layouts::shift_pairs() {
+            for key in [lower, upper] {
+                let imported = file
+                    .keys
+                    .get(&key.to_string())
+                    .map_or(key.to_string(), Clone::clone);
+                assert_eq!(
+                    imported,
+                    layouts::char_at(code, key).to_string(),
+                    "{} on {}",
+                    key,
+                    code.as_str()
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn exports_imported_layouts_unchanged() {
+    let dir = std::env::temp_dir().join(format!("keymorph-xkb-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for code in LayoutCode::ALL {
+        let symbols = xkb::export(&Layout::Builtin(code));
+        let mut file = xkb::import(&symbols).unwrap();
+        file.name = format!("{}-imported", code.as_str());
+        let path = dir.join(format!("{}.toml", file.name));
+        std::fs::write(&path, schema::to_toml(&file)).unwrap();
+        let Ok(name) = registry::load_file(&path) else {
+            // Layouts typing a character on two keys do not validate as
+            // custom layouts
+            continue;
+        };
+        let imported = registry::resolve(&name).unwrap();
+        assert_eq!(
+            key_lines(&xkb::export(&imported)),
+            key_lines(&symbols),
+            "{}",
+            code.as_str()
+        );
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn imports_symbols_with_includes_and_comments() {
+    let symbols = r#"
+        // A partial layout on top of us
+        default partial alphanumeric_keys
+        xkb_symbols "swapped" {
+            include "us(basic)"
+            name[Group1] = "Swapped";
+            key <AD01> { [ z, Z ] }; // q
+            key <AB01> { type[Group1] = "ALPHABETIC", symbols[Group1] = [ q, Q ] };
+            key <AC10> { [ U0444, U0424, NoSymbol, NoSymbol ] };
+            key <LSGT> { [ less, greater ] };
+        };
+    "#;
+    let file = xkb::import(symbols).unwrap();
+    assert_eq!(file.name, "swapped");
+    let keys: Vec<(&str, &str)> = file
+        .keys
+        .iter()
+        .map(|(key, c)| (key.as_str(), c.as_str()))
+        .collect();
+    assert_eq!(
+        keys,
+        [
+            (":", "Ф"),
+            (";", "ф"),
+            ("Q", "Z"),
+            ("Z", "Q"),
+            ("q", "z"),
+            ("z", "q")
+        ]
+    );
+    assert!(xkb::import("key <AD01> { [ q ] };").is_err());
+    assert!(xkb::import("xkb_symbols \"x\" { key <AD01> { [ Cyrillic_a ] }; };").is_err());
+}
@@ -0,0 +1,45 @@
+// ZMK keymaps of every layout on the boards ZMK has keymaps for
+use keymorph::firmware::{self, Board, Exporter};
+use keymorph::layouts::LayoutCode;
+use keymorph::registry::Layout;
+use keymorph::zmk::{self, Zmk};
+
+#[test]
+fn binds_every_key() {
+    for board in Board::ALL.into_iter().filter(|&board| Zmk.supports(board)) {
+        for code in LayoutCode::ALL {
+            let layout = Layout::Builtin(code);
+            let keymap = firmware::keymap(&layout, board, LayoutCode::Qwerty);
+            let exported = Zmk.export(&layout, &keymap);
+            let bindings = exported
+                .split("bindings = <")
+                .nth(1)
+                .unwrap()
+                .split(">;")
+                .next()
+                .unwrap();
+            let keys: usize = board.slots().iter().map(Vec::len).sum();
+            assert_eq!(
+                bindings.matches('&').count(),
+                keys,
+                "{} on {}",
+                code.as_str(),
+                board.as_str()
+            );
+        }
+    }
+}
+
+#[test]
+fn writes_behaviors() {
+    let layout = Layout::Builtin(LayoutCode::Colemak);
+    let keymap = firmware::keymap(&layout, Board::Corne, LayoutCode::Qwerty);
+    assert_eq!(zmk::behavior(keymap.rows[0][3]), "&kp F");
+    assert_eq!(zmk::behavior(keymap.rows[0][11]), "&kp BSPC");
+    assert_eq!(zmk::behavior(keymap.rows[1][10]), "&kp O");
+
+    let dvorak = Layout::Builtin(LayoutCode::Dvorak);
+    let keymap = firmware::keymap(&dvorak, Board::Glove80, LayoutCode::Qwerty);
+    assert_eq!(zmk::behavior(keymap.rows[1][11]), "&kp LBKT");
+    assert!(!Zmk.supports(Board::Ansi60));
+}